@@ -1,4 +1,6 @@
-use libass::{DefaultFontProvider, Layer, Library};
+use std::cell::RefCell;
+
+use libass::{DefaultFontProvider, Layer, Library, Renderer};
 use thiserror::Error;
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -18,6 +20,8 @@ pub enum Error {
 pub struct AssRenderer<'a> {
     width: usize,
     height: usize,
+    // Made once, finding the fonts is slow. Dropped before the library.
+    renderer: RefCell<Renderer<'a>>,
     lib: Library<'a>,
 }
 
@@ -27,10 +31,22 @@ impl<'a> AssRenderer<'a> {
             Ok(lib) => lib,
             Err(_) => return Err(Error::LibraryError),
         };
+        let mut renderer = match lib.new_renderer() {
+            Ok(renderer) => renderer,
+            Err(_) => return Err(Error::RendererError),
+        };
+        renderer.set_fonts(
+            None,
+            "sans-serif",
+            DefaultFontProvider::Autodetect,
+            None,
+            false,
+        );
 
         Ok(Self {
             width: 0,
             height: 0,
+            renderer: RefCell::new(renderer),
             lib,
         })
     }
@@ -40,35 +56,55 @@ impl<'a> AssRenderer<'a> {
         /*style_list, event_list, meta*/ width: usize,
         height: usize,
     ) -> Result<()> {
-        let mut renderer = match self.lib.new_renderer() {
-            Ok(renderer) => renderer,
-            Err(_) => return Err(Error::RendererError),
-        };
-        renderer.set_fonts(
-            None,
-            "sans-serif",
-            DefaultFontProvider::Autodetect,
-            None,
-            false,
-        );
-        renderer.set_frame_size(width as i32, height as i32);
+        self.width = width;
+        self.height = height;
+        self.renderer
+            .get_mut()
+            .set_frame_size(width as i32, height as i32);
         Ok(())
     }
 
-    fn draw_layer(layer: Layer, dst: &mut [u8]) {
+    /// Renders `script` at `timestamp` (in milliseconds) over a frame filled
+    /// with `background`, returning its RGBA pixels.
+    pub fn render_frame(
+        &self,
+        script: &str,
+        timestamp: i64,
+        background: [u8; 4],
+    ) -> Result<Vec<u8>> {
+        let track = match self.lib.new_track_from_memory(script.as_bytes(), "UTF-8") {
+            Ok(track) => track,
+            Err(_) => return Err(Error::TrackError),
+        };
+
+        let mut framebuffer = background.repeat(self.width * self.height);
+        if let (Some(image), _) = self.renderer.borrow_mut().render_frame(track, timestamp) {
+            for layer in image {
+                self.draw_layer(layer, &mut framebuffer);
+            }
+        }
+
+        Ok(framebuffer)
+    }
+
+    fn draw_layer(&self, layer: Layer, dst: &mut [u8]) {
         // RGBA order
         let mut color = layer.color.to_be_bytes();
         color[3] = 255 - color[3]; // Inverse alpha
 
         for y in 0..layer.height as usize {
             for x in 0..layer.width as usize {
-                let k = layer.bitmap[y * layer.width as usize + x] as u16;
-
                 let dst_x = x + layer.x as usize;
                 let dst_y = y + layer.y as usize;
-                let dst_p = (dst_y * 1920 + dst_x) * 4;
+                if dst_x >= self.width || dst_y >= self.height {
+                    continue;
+                }
+
+                // Glyph coverage scaled by the color alpha
+                let k = layer.bitmap[y * layer.width as usize + x] as u16 * color[3] as u16 / 255;
+                let dst_p = (dst_y * self.width + dst_x) * 4;
 
-                for i in 0..4 {
+                for i in 0..3 {
                     let dst_off = dst_p + i;
                     let dst_orig = dst[dst_off] as u16;
                     dst[dst_off] = ((k * color[i] as u16 + (255 - k) * dst_orig) / 255) as u8;
@@ -0,0 +1,241 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::ass_renderer::{self, AssRenderer};
+use crate::subs_viewer::{
+    resample, Conversion, CpsLevel, CpsSettings, SubsControlsValues, SubtitleFile,
+    SubtitleFileError, Time, Timebase, TimingSettings, DEFAULT_RESOLUTION,
+};
+
+const USAGE: &str = "\
+Usage: spirit_sub [<command> <arguments>]
+
+Without a command, the editor opens. Subtitles are read and written as .ass
+or .srt files, told apart by their extension. Times are kept to the
+centisecond, so .srt milliseconds are rounded.
+
+Commands:
+  convert <input> <output>
+      Writes the subtitles in the format of the output.
+  shift <input> <output> <milliseconds>
+      Moves every line, earlier when negative.
+  fps <input> <output> <from> <to> [--keep-times]
+      Moves every line to its frame at another frame rate, as 23.976 or
+      24000/1001, or with the frame times of a v1 or v2 timecodes file, for
+      video sped up or slowed down. With --keep-times, lines keep their times
+      snapped to the new frames instead, for video as long as before, as
+      after an inverse telecine.
+  resample <input> <output> <width> <height>
+      Scales the positions and sizes to another resolution.
+  lint <input>
+      Lists the timing and reading speed issues, exits with 1 if any.
+  render <input> <time> <output.png> [<width> <height>]
+      Renders the frame at a hh:mm:ss:cc time, by default at the script
+      resolution.
+  help
+      Shows this help.";
+
+// First arguments running a command, the editor opens for any other
+const COMMANDS: &[&str] = &[
+    "convert", "shift", "fps", "resample", "lint", "render", "help", "--help", "-h",
+];
+
+// Frames are rendered over black, the renderer blends into opaque pixels
+const RENDER_BACKGROUND: [u8; 4] = [0, 0, 0, 0xFF];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Usage(String),
+    #[error("Invalid {name}: {reason}")]
+    Argument { name: &'static str, reason: String },
+    #[error(transparent)]
+    File(#[from] SubtitleFileError),
+    #[error("Line {line}: invalid time {time}")]
+    InvalidTime { line: usize, time: String },
+    #[error("Rendering failed: {0}")]
+    Render(#[from] ass_renderer::Error),
+    #[error(transparent)]
+    Png(#[from] png::EncodingError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Whether `arg`, the first argument, names a command.
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.contains(&arg)
+}
+
+/// Runs the command of `args`, the arguments after the program name, and
+/// returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    match command(args) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("spirit_sub: {}", error);
+            if let Error::Usage(_) = error {
+                eprintln!("\n{}", USAGE);
+            }
+            2
+        }
+    }
+}
+
+fn command(args: &[String]) -> Result<i32, Error> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["convert", input, output] => {
+            SubtitleFile::read(Path::new(input))?.write(Path::new(output))?;
+        }
+        ["shift", input, output, millis] => {
+            let millis: i64 = argument(millis, "milliseconds")?;
+            let mut file = SubtitleFile::read(Path::new(input))?;
+            for (row, line) in file.lines.iter_mut().enumerate() {
+                let (start, end) = times(row, line)?;
+                line.set_times(start.offset(millis), end.offset(millis));
+            }
+            file.write(Path::new(output))?;
+        }
+        ["fps", input, output, from, to] => fps(input, output, from, to, Conversion::KeepFrames)?,
+        ["fps", input, output, from, to, "--keep-times"] => {
+            fps(input, output, from, to, Conversion::KeepTimes)?
+        }
+        ["resample", input, output, width, height] => {
+            let width: u32 = argument(width, "width")?;
+            let height: u32 = argument(height, "height")?;
+            let mut file = SubtitleFile::read(Path::new(input))?;
+            resample(&mut file, width, height);
+            file.write(Path::new(output))?;
+        }
+        ["lint", input] => {
+            let file = SubtitleFile::read(Path::new(input))?;
+            let issues = lint(&file);
+            for (row, issue) in &issues {
+                println!("{}: line {}: {}", input, row + 1, issue);
+            }
+            return Ok(if issues.is_empty() { 0 } else { 1 });
+        }
+        ["render", input, time, output] => render(input, time, output, None)?,
+        ["render", input, time, output, width, height] => {
+            let size = (argument(width, "width")?, argument(height, "height")?);
+            render(input, time, output, Some(size))?;
+        }
+        ["help"] | ["--help"] | ["-h"] => println!("{}", USAGE),
+        [command, ..] => {
+            return Err(Error::Usage(format!(
+                "Unknown command or wrong arguments for `{}`",
+                command
+            )))
+        }
+        [] => return Err(Error::Usage("No command".to_owned())),
+    }
+    Ok(0)
+}
+
+fn argument<T>(value: &str, name: &'static str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value.parse().map_err(|error: T::Err| Error::Argument {
+        name,
+        reason: error.to_string(),
+    })
+}
+
+// Times of the line at `row`, unreadable ones stop the command
+fn times(row: usize, line: &SubsControlsValues) -> Result<(Time, Time), Error> {
+    let invalid = |time: &str| Error::InvalidTime {
+        line: row + 1,
+        time: time.to_owned(),
+    };
+    let start = line.start().ok_or_else(|| invalid(&line.start_time))?;
+    let end = line.end().ok_or_else(|| invalid(&line.end_time))?;
+    Ok((start, end))
+}
+
+// A frame rate or the path of a timecodes file
+fn timebase(value: &str) -> Result<Timebase, Error> {
+    Timebase::read(value).map_err(|error| Error::Argument {
+        name: "frame rate",
+        reason: error.to_string(),
+    })
+}
+
+fn fps(
+    input: &str,
+    output: &str,
+    from: &str,
+    to: &str,
+    conversion: Conversion,
+) -> Result<(), Error> {
+    let (from, to) = (timebase(from)?, timebase(to)?);
+    let mut file = SubtitleFile::read(Path::new(input))?;
+    for (row, line) in file.lines.iter_mut().enumerate() {
+        let (start, end) = times(row, line)?;
+        line.set_times(
+            conversion.convert(start, &from, &to),
+            conversion.convert(end, &from, &to),
+        );
+    }
+    file.write(Path::new(output))?;
+    Ok(())
+}
+
+// Issues of the dialogue lines, by row, with the editor default settings
+fn lint(file: &SubtitleFile) -> Vec<(usize, String)> {
+    let timing = TimingSettings::default().check(&file.lines);
+    let cps = CpsSettings::default();
+    let mut issues = Vec::new();
+
+    for (row, (line, timing)) in file.lines.iter().zip(timing).enumerate() {
+        if line.is_comment {
+            continue;
+        }
+        issues.extend(timing.into_iter().map(|issue| (row, issue.to_string())));
+
+        // SRT files have no styles
+        if !file.styles.is_empty() && !file.styles.iter().any(|style| style.name == line.style) {
+            issues.push((row, format!("Unknown style {}", line.style)));
+        }
+
+        let duration = line
+            .end()
+            .unwrap_or_default()
+            .saturating_sub(line.start().unwrap_or_default());
+        if let Some(speed) = cps.cps(&line.text, duration) {
+            if cps.level(speed) == CpsLevel::Exceeded {
+                issues.push((
+                    row,
+                    format!("{:.1} characters per second, over {}", speed, cps.maximum),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+fn render(input: &str, time: &str, output: &str, size: Option<(u32, u32)>) -> Result<(), Error> {
+    let file = SubtitleFile::read(Path::new(input))?;
+    let time: Time = argument(time, "time")?;
+    let (width, height) = size
+        .or_else(|| file.resolution())
+        .unwrap_or(DEFAULT_RESOLUTION);
+
+    let mut renderer = AssRenderer::new()?;
+    renderer.set_source(width as usize, height as usize)?;
+    let pixels =
+        renderer.render_frame(&file.to_ass(), time.as_millis() as i64, RENDER_BACKGROUND)?;
+
+    let writer = BufWriter::new(File::create(output)?);
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
@@ -1,6 +1,10 @@
-//mod ass_renderer;
+mod ass_renderer;
+mod cli;
 mod subs_viewer;
 
+use std::env;
+use std::process;
+
 use iced::{
     executor, keyboard, pane_grid, Application, Command, Container, Element, Length, PaneGrid, Row,
     Settings, Subscription, Text,
@@ -8,9 +12,15 @@ use iced::{
 
 use iced_native::{event, subscription, Event};
 
-use subs_viewer::SubsViewer;
+use subs_viewer::{HistoryMessage, KanjiTimerMessage, LineAction, SubsViewer};
 
 pub fn main() -> iced::Result {
+    // Commands run without opening the editor
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map_or(false, |arg| cli::is_command(arg)) {
+        process::exit(cli::run(&args));
+    }
+
     SpiritSub::run(Settings {
         antialiasing: true,
         ..Settings::default()
@@ -29,6 +39,8 @@ enum Message {
     Dragged(pane_grid::DragEvent),
     Resized(pane_grid::ResizeEvent),
     SubsViewer(subs_viewer::Message, pane_grid::Pane),
+    // Shortcuts sent to the focused subs viewer
+    Hotkey(subs_viewer::Message),
 }
 
 impl Application for SpiritSub {
@@ -79,24 +91,31 @@ impl Application for SpiritSub {
                     subs.update(message);
                 }
             }
+            Message::Hotkey(message) => {
+                if let Some(PaneState::SubsViewer(subs)) = self.panes_state.get_mut(&self.focus) {
+                    subs.update(message);
+                }
+            }
         }
 
         Command::none()
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        subscription::events_with(|event, status| {
-            if let event::Status::Captured = status {
-                return None;
-            }
-
-            match event {
-                Event::Keyboard(keyboard::Event::KeyPressed {
-                    modifiers,
-                    key_code,
-                }) if modifiers.is_command_pressed() => handle_hotkey(key_code),
-                _ => None,
+        subscription::events_with(|event, status| match event {
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                modifiers,
+                key_code,
+            }) => {
+                // A focused text input captures every key, shortcuts with a
+                // modifier must work while typing anyway
+                let has_modifier = modifiers.is_command_pressed() || modifiers.alt;
+                match status {
+                    event::Status::Captured if !has_modifier => None,
+                    _ => handle_hotkey(key_code, modifiers),
+                }
             }
+            _ => None,
         })
     }
 
@@ -135,17 +154,73 @@ impl Application for SpiritSub {
     }
 }
 
-fn handle_hotkey(key_code: keyboard::KeyCode) -> Option<Message> {
+fn handle_hotkey(key_code: keyboard::KeyCode, modifiers: keyboard::Modifiers) -> Option<Message> {
     use keyboard::KeyCode;
     use pane_grid::Direction;
 
-    let direction = match key_code {
-        KeyCode::Up => Some(Direction::Up),
-        KeyCode::Down => Some(Direction::Down),
-        _ => None,
+    if modifiers.is_command_pressed() {
+        let message = match key_code {
+            KeyCode::Up => Message::FocusAdjacent(Direction::Up),
+            KeyCode::Down => Message::FocusAdjacent(Direction::Down),
+            KeyCode::Z if modifiers.shift => {
+                Message::Hotkey(subs_viewer::Message::History(HistoryMessage::Redo))
+            }
+            KeyCode::Z => Message::Hotkey(subs_viewer::Message::History(HistoryMessage::Undo)),
+            _ => return None,
+        };
+
+        return Some(message);
+    }
+
+    // Ignored unless the kanji timer is shown
+    if !modifiers.alt {
+        let message = match key_code {
+            KeyCode::Left if modifiers.shift => Some(KanjiTimerMessage::FewerSyllables),
+            KeyCode::Right if modifiers.shift => Some(KanjiTimerMessage::MoreSyllables),
+            KeyCode::Left => Some(KanjiTimerMessage::FewerCharacters),
+            KeyCode::Right => Some(KanjiTimerMessage::MoreCharacters),
+            KeyCode::Enter => Some(KanjiTimerMessage::Accept),
+            KeyCode::Space => Some(KanjiTimerMessage::Skip),
+            KeyCode::Backspace => Some(KanjiTimerMessage::Undo),
+            _ => None,
+        };
+        if let Some(message) = message {
+            return Some(Message::Hotkey(subs_viewer::Message::KanjiTimer(message)));
+        }
+    }
+
+    let action = if modifiers.alt {
+        match key_code {
+            KeyCode::Up => Some(LineAction::MoveUp),
+            KeyCode::Down => Some(LineAction::MoveDown),
+            KeyCode::B => Some(LineAction::InsertBefore),
+            KeyCode::A => Some(LineAction::InsertAfter),
+            KeyCode::D => Some(LineAction::Duplicate),
+            KeyCode::Delete => Some(LineAction::Delete),
+            KeyCode::S => Some(LineAction::SplitAtCursor),
+            KeyCode::J => Some(LineAction::Join),
+            KeyCode::Enter => Some(LineAction::CommitAndNext),
+            _ => None,
+        }
+    } else if modifiers.shift {
+        match key_code {
+            KeyCode::Up => Some(LineAction::ExtendPrevious),
+            KeyCode::Down => Some(LineAction::ExtendNext),
+            _ => None,
+        }
+    } else {
+        match key_code {
+            KeyCode::Up => Some(LineAction::Previous),
+            KeyCode::Down => Some(LineAction::Next),
+            KeyCode::PageUp => Some(LineAction::PageUp),
+            KeyCode::PageDown => Some(LineAction::PageDown),
+            KeyCode::Home => Some(LineAction::First),
+            KeyCode::End => Some(LineAction::Last),
+            _ => None,
+        }
     };
 
-    direction.map(Message::FocusAdjacent)
+    action.map(|action| Message::Hotkey(subs_viewer::Message::LineAction(action)))
 }
 
 enum PaneState {
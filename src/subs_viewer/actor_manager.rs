@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use iced::{
+    button, scrollable, text_input, Align, Button, Column, Element, Length, Row, Scrollable, Text,
+    TextInput,
+};
+
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const LIST_WIDTH: u16 = 260;
+
+/// Actors used in `lines`, sorted by name, with how many lines they speak.
+pub fn actors(lines: &[SubsControlsValues]) -> BTreeMap<&str, usize> {
+    let mut actors = BTreeMap::new();
+    for line in lines.iter().filter(|line| !line.actor.trim().is_empty()) {
+        *actors.entry(line.actor.as_str()).or_insert(0) += 1;
+    }
+    actors
+}
+
+pub fn actor_names(lines: &[SubsControlsValues]) -> Vec<&str> {
+    actors(lines).into_iter().map(|(actor, _)| actor).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Renamed { from: String, to: String },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ActorSelected(String),
+    NameChanged(String),
+    Rename,
+    Close,
+}
+
+/// Renames an actor on every line. Renaming to an existing actor merges
+/// the two.
+#[derive(Default)]
+pub struct ActorManager {
+    list_scroll: scrollable::State,
+    list_buttons: Vec<button::State>,
+    name: text_input::State,
+    rename: button::State,
+    close: button::State,
+    selected: Option<String>,
+    name_input: String,
+}
+
+impl ActorManager {
+    /// Shows the actor called `name`, if any.
+    pub fn open(&mut self, name: &str) {
+        if name.trim().is_empty() {
+            self.selected = None;
+            self.name_input.clear();
+        } else {
+            self.selected = Some(name.to_owned());
+            self.name_input = name.to_owned();
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        match message {
+            Message::ActorSelected(actor) => self.open(&actor),
+            Message::NameChanged(name) => self.name_input = name,
+            Message::Rename => {
+                // Commas separate the fields of the event lines
+                let to = self.name_input.trim().to_owned();
+                let from = self.selected.clone()?;
+                if to.is_empty() || to.contains(',') || to == from {
+                    return None;
+                }
+                self.selected = Some(to.clone());
+                return Some(Event::Renamed { from, to });
+            }
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self, lines: &[SubsControlsValues]) -> Element<Message> {
+        let actors = actors(lines);
+        self.list_buttons
+            .resize_with(actors.len(), button::State::default);
+
+        let selected = self.selected.as_deref();
+        let list = self.list_buttons.iter_mut().zip(actors).fold(
+            Column::new().width(Length::Fill),
+            |column, (state, (actor, count))| {
+                let marker = if selected == Some(actor) { "> " } else { "" };
+                let label = format!("{}{} ({} lines)", marker, actor, count);
+                column.push(
+                    Button::new(state, Text::new(label).size(TEXT_SIZE))
+                        .width(Length::Fill)
+                        .on_press(Message::ActorSelected(actor.to_owned())),
+                )
+            },
+        );
+
+        let mut rename = Button::new(&mut self.rename, Text::new("Rename").size(TEXT_SIZE));
+        if self.selected.is_some() {
+            rename = rename.on_press(Message::Rename);
+        }
+
+        let editor = Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(Text::new("Rename the selected actor on every line").size(TEXT_SIZE))
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .align_items(Align::Center)
+                    .push(
+                        TextInput::new(
+                            &mut self.name,
+                            "Actor name",
+                            &self.name_input,
+                            Message::NameChanged,
+                        )
+                        .on_submit(Message::Rename)
+                        .size(TEXT_SIZE),
+                    )
+                    .push(rename),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            );
+
+        Row::new()
+            .spacing(ROW_SPACING)
+            .push(
+                Scrollable::new(&mut self.list_scroll)
+                    .width(Length::Units(LIST_WIDTH))
+                    .height(Length::Fill)
+                    .push(Text::new("Actors"))
+                    .push(list),
+            )
+            .push(editor)
+            .into()
+    }
+}
@@ -0,0 +1,728 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use iced::{
+    button, scrollable, text_input, Align, Button, Column, Element, Length, Row, Scrollable, Text,
+    TextInput,
+};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Map, Scope, AST, INT};
+use thiserror::Error;
+
+use super::tags::{Item, Line, NodeKind};
+use super::templater;
+use super::time::Time;
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const LIST_WIDTH: u16 = 260;
+const LABEL_WIDTH: u16 = 140;
+
+const SCRIPT_EXTENSION: &str = "rhai";
+
+// Errors of the functions called by scripts
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// A looping script must not freeze the editor
+const MAX_OPERATIONS: u64 = 50_000_000;
+// Nesting allowed in scripts and in their functions, debug builds would
+// otherwise allow half of it
+const MAX_EXPR_DEPTHS: (usize, usize) = (64, 32);
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("No configuration directory available")]
+    NoDirectory,
+    #[error("Script I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Parse(#[from] rhai::ParseError),
+    #[error("{0}")]
+    Eval(#[from] Box<EvalAltResult>),
+}
+
+/// Directory containing the scripts, created when missing.
+pub fn directory() -> Result<PathBuf, Error> {
+    let directory = dirs::config_dir()
+        .ok_or(Error::NoDirectory)?
+        .join("spirit_sub")
+        .join("scripts");
+    fs::create_dir_all(&directory)?;
+    Ok(directory)
+}
+
+// A menu entry declared by a script
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    function: String,
+    // Labels and default values of the dialog fields
+    fields: Vec<(String, String)>,
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+    entries: Vec<Entry>,
+}
+
+// Lines shared between the editor and a running script
+#[derive(Debug, Clone)]
+struct Document(Rc<RefCell<DocumentState>>);
+
+#[derive(Debug)]
+struct DocumentState {
+    lines: Vec<SubsControlsValues>,
+    selection: Range<usize>,
+    active: usize,
+}
+
+impl Document {
+    // `row` as an index, past the last line when `is_end_allowed`
+    fn row(&self, row: INT, is_end_allowed: bool) -> ScriptResult<usize> {
+        let len = self.0.borrow().lines.len();
+        let limit = if is_end_allowed { len + 1 } else { len };
+        if row < 0 || row as usize >= limit {
+            return Err(
+                format!("Line {} out of range, the document has {} lines", row, len).into(),
+            );
+        }
+        Ok(row as usize)
+    }
+}
+
+// Commas separate the fields of the event lines, only the text may have some
+fn checked(line: SubsControlsValues) -> ScriptResult<SubsControlsValues> {
+    if line.actor.contains(',') || line.effect.contains(',') {
+        return Err("Actors and effects cannot contain commas".into());
+    }
+    Ok(line)
+}
+
+fn millis(time: Option<Time>) -> INT {
+    time.map_or(0, |time| time.as_millis() as INT)
+}
+
+// Tags of a block, comments have no name
+fn items(source: &str, items: &[Item]) -> Array {
+    items
+        .iter()
+        .map(|item| {
+            let (name, args) = match item {
+                Item::Tag(tag) => (
+                    &source[tag.span.start + 1..tag.args.start],
+                    &source[tag.args.clone()],
+                ),
+                Item::Comment(span) => ("", &source[span.clone()]),
+            };
+            let mut map = Map::new();
+            map.insert("name".into(), name.into());
+            map.insert("args".into(), args.into());
+            Dynamic::from(map)
+        })
+        .collect()
+}
+
+fn parse_tags(text: ImmutableString) -> Array {
+    let text = text.as_str();
+    Line::parse(text)
+        .nodes
+        .iter()
+        .map(|node| {
+            let kind = match &node.kind {
+                NodeKind::Text => "text",
+                NodeKind::Drawing => "drawing",
+                NodeKind::LineBreak { .. } => "break",
+                NodeKind::HardSpace => "space",
+                NodeKind::Block(_) => "block",
+            };
+            let mut map = Map::new();
+            map.insert("kind".into(), kind.into());
+            map.insert("text".into(), text[node.span.clone()].into());
+            if let NodeKind::Block(block) = &node.kind {
+                map.insert("tags".into(), items(text, block).into());
+            }
+            Dynamic::from(map)
+        })
+        .collect()
+}
+
+fn emit_tags(nodes: Array) -> ScriptResult<String> {
+    let field = |map: &Map, name: &str| -> ScriptResult<String> {
+        match map.get(name) {
+            Some(value) => Ok(value.to_string()),
+            None => Err(format!("Missing `{}` in {:?}", name, map).into()),
+        }
+    };
+
+    let mut text = String::new();
+    for node in nodes {
+        let node = node
+            .try_cast::<Map>()
+            .ok_or_else(|| Box::<EvalAltResult>::from("Nodes must be maps"))?;
+        let tags = match node.get("tags") {
+            Some(tags) if field(&node, "kind")? == "block" => tags.clone(),
+            _ => {
+                text.push_str(&field(&node, "text")?);
+                continue;
+            }
+        };
+
+        text.push('{');
+        for tag in tags.try_cast::<Array>().unwrap_or_default() {
+            let tag = tag
+                .try_cast::<Map>()
+                .ok_or_else(|| Box::<EvalAltResult>::from("Tags must be maps"))?;
+            let name = field(&tag, "name")?;
+            if !name.is_empty() {
+                text.push('\\');
+                text.push_str(&name);
+            }
+            text.push_str(&field(&tag, "args")?);
+        }
+        text.push('}');
+    }
+    Ok(text)
+}
+
+/// Engine without any API, bounded so scripts cannot freeze the editor.
+pub fn bounded_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTHS.0, MAX_EXPR_DEPTHS.1);
+    engine
+}
+
+// Engine with the document API, `menu` adding to `registry`
+fn engine(registry: Rc<RefCell<Vec<Entry>>>) -> Engine {
+    let mut engine = bounded_engine();
+
+    let entries = registry.clone();
+    engine.register_fn("menu", move |name: &str, function: &str| {
+        entries.borrow_mut().push(Entry {
+            name: name.to_owned(),
+            function: function.to_owned(),
+            fields: Vec::new(),
+        });
+    });
+    engine.register_fn("menu", move |name: &str, function: &str, fields: Map| {
+        registry.borrow_mut().push(Entry {
+            name: name.to_owned(),
+            function: function.to_owned(),
+            fields: fields
+                .into_iter()
+                .map(|(label, value)| (label.to_string(), value.to_string()))
+                .collect(),
+        });
+    });
+
+    engine
+        .register_type_with_name::<Document>("Document")
+        .register_fn("len", |doc: &mut Document| {
+            doc.0.borrow().lines.len() as INT
+        })
+        .register_fn(
+            "line",
+            |doc: &mut Document, row: INT| -> ScriptResult<SubsControlsValues> {
+                let row = doc.row(row, false)?;
+                Ok(doc.0.borrow().lines[row].clone())
+            },
+        )
+        .register_fn(
+            "set_line",
+            |doc: &mut Document, row: INT, line: SubsControlsValues| -> ScriptResult<()> {
+                let row = doc.row(row, false)?;
+                doc.0.borrow_mut().lines[row] = checked(line)?;
+                Ok(())
+            },
+        )
+        .register_fn(
+            "insert_line",
+            |doc: &mut Document, row: INT, line: SubsControlsValues| -> ScriptResult<()> {
+                let row = doc.row(row, true)?;
+                doc.0.borrow_mut().lines.insert(row, checked(line)?);
+                Ok(())
+            },
+        )
+        .register_fn(
+            "remove_line",
+            |doc: &mut Document, row: INT| -> ScriptResult<()> {
+                let row = doc.row(row, false)?;
+                doc.0.borrow_mut().lines.remove(row);
+                Ok(())
+            },
+        )
+        .register_fn("selection", |doc: &mut Document| {
+            let selection = doc.0.borrow().selection.clone();
+            selection
+                .map(|row| Dynamic::from(row as INT))
+                .collect::<Array>()
+        })
+        .register_fn(
+            "select",
+            |doc: &mut Document, start: INT, end: INT| -> ScriptResult<()> {
+                let start = doc.row(start, false)?;
+                let end = doc.row(end, true)?.max(start + 1);
+                let mut state = doc.0.borrow_mut();
+                state.selection = start..end;
+                state.active = start;
+                Ok(())
+            },
+        )
+        .register_fn("active", |doc: &mut Document| doc.0.borrow().active as INT);
+
+    engine
+        .register_type_with_name::<SubsControlsValues>("Line")
+        .register_fn("new_line", SubsControlsValues::new)
+        .register_get_set(
+            "style",
+            |line: &mut SubsControlsValues| line.style.clone(),
+            |line: &mut SubsControlsValues, style: String| line.style = style,
+        )
+        .register_get_set(
+            "actor",
+            |line: &mut SubsControlsValues| line.actor.clone(),
+            |line: &mut SubsControlsValues, actor: String| line.actor = actor,
+        )
+        .register_get_set(
+            "layer",
+            |line: &mut SubsControlsValues| line.layer as INT,
+            |line: &mut SubsControlsValues, layer: INT| line.layer = layer.max(0) as usize,
+        )
+        .register_get_set(
+            "margin_left",
+            |line: &mut SubsControlsValues| line.margin_left as INT,
+            |line: &mut SubsControlsValues, margin: INT| line.margin_left = margin.max(0) as usize,
+        )
+        .register_get_set(
+            "margin_vertical",
+            |line: &mut SubsControlsValues| line.margin_vertical as INT,
+            |line: &mut SubsControlsValues, margin: INT| {
+                line.margin_vertical = margin.max(0) as usize
+            },
+        )
+        .register_get_set(
+            "margin_right",
+            |line: &mut SubsControlsValues| line.margin_right as INT,
+            |line: &mut SubsControlsValues, margin: INT| line.margin_right = margin.max(0) as usize,
+        )
+        .register_get_set(
+            "effect",
+            |line: &mut SubsControlsValues| line.effect.clone(),
+            |line: &mut SubsControlsValues, effect: String| line.effect = effect,
+        )
+        .register_get_set(
+            "start",
+            |line: &mut SubsControlsValues| millis(line.start()),
+            |line: &mut SubsControlsValues, millis: INT| {
+                let start = Time::from_millis(millis.max(0) as u64);
+                let end = line.end().unwrap_or(start);
+                line.set_times(start, end);
+            },
+        )
+        .register_get_set(
+            "end",
+            |line: &mut SubsControlsValues| millis(line.end()),
+            |line: &mut SubsControlsValues, millis: INT| {
+                let end = Time::from_millis(millis.max(0) as u64);
+                let start = line.start().unwrap_or(end);
+                line.set_times(start, end);
+            },
+        )
+        .register_get_set(
+            "comment",
+            |line: &mut SubsControlsValues| line.is_comment,
+            |line: &mut SubsControlsValues, is_comment: bool| line.is_comment = is_comment,
+        )
+        .register_get_set(
+            "text",
+            |line: &mut SubsControlsValues| line.text.clone(),
+            |line: &mut SubsControlsValues, text: String| line.text = text,
+        )
+        .register_get_set(
+            "notes",
+            |line: &mut SubsControlsValues| line.notes.clone(),
+            |line: &mut SubsControlsValues, notes: String| line.notes = notes,
+        );
+
+    engine
+        .register_fn("parse_tags", parse_tags)
+        .register_fn("emit_tags", emit_tags);
+
+    engine
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The document as left by a script or the templater, `name` naming the
+    /// change.
+    Ran {
+        name: String,
+        lines: Vec<SubsControlsValues>,
+        selection: Range<usize>,
+        active: usize,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    EntrySelected(usize),
+    FieldChanged(usize, String),
+    Run,
+    Reload,
+    ApplyTemplates,
+    RemoveEffects,
+    Close,
+}
+
+// The selection is never empty, and holds the active line
+fn ran(
+    name: String,
+    mut lines: Vec<SubsControlsValues>,
+    selection: &Range<usize>,
+    active: usize,
+) -> Event {
+    if lines.is_empty() {
+        lines.push(SubsControlsValues::new());
+    }
+    let end = selection.end.min(lines.len()).max(1);
+    let start = selection.start.min(end - 1);
+    let active = active.max(start).min(end - 1);
+
+    Event::Ran {
+        name,
+        lines,
+        selection: start..end,
+        active,
+    }
+}
+
+/// Lists the entries of the scripts and runs them on the document.
+pub struct Automation {
+    list_scroll: scrollable::State,
+    list_buttons: Vec<button::State>,
+    fields: Vec<text_input::State>,
+    run: button::State,
+    reload: button::State,
+    apply_templates: button::State,
+    remove_effects: button::State,
+    close: button::State,
+    engine: Engine,
+    registry: Rc<RefCell<Vec<Entry>>>,
+    scripts: Vec<Script>,
+    // Script and entry indices of the selected entry
+    selected: Option<(usize, usize)>,
+    field_inputs: Vec<String>,
+    status: String,
+}
+
+// The engine adds the entries declared by scripts to the registry
+impl Default for Automation {
+    fn default() -> Self {
+        let registry = Rc::new(RefCell::new(Vec::new()));
+        Self {
+            list_scroll: scrollable::State::default(),
+            list_buttons: Vec::new(),
+            fields: Vec::new(),
+            run: button::State::default(),
+            reload: button::State::default(),
+            apply_templates: button::State::default(),
+            remove_effects: button::State::default(),
+            close: button::State::default(),
+            engine: engine(registry.clone()),
+            registry,
+            scripts: Vec::new(),
+            selected: None,
+            field_inputs: Vec::new(),
+            status: String::new(),
+        }
+    }
+}
+
+impl Automation {
+    /// Loads the scripts again, so edits show without restarting.
+    pub fn open(&mut self) {
+        self.scripts.clear();
+        self.selected = None;
+        self.status = match self.load() {
+            Ok(errors) if errors.is_empty() && self.scripts.is_empty() => match directory() {
+                Ok(directory) => format!("No scripts in {}", directory.display()),
+                Err(error) => error.to_string(),
+            },
+            Ok(errors) => errors.join("\n"),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    // Loads every script, returning the errors of the broken ones
+    fn load(&mut self) -> Result<Vec<String>, Error> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(directory()?)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |ext| ext == SCRIPT_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            match self.compile(&path) {
+                Ok((ast, entries)) => self.scripts.push(Script { name, ast, entries }),
+                Err(error) => errors.push(format!("{}: {}", name, error)),
+            }
+        }
+        Ok(errors)
+    }
+
+    fn compile(&self, path: &Path) -> Result<(AST, Vec<Entry>), Error> {
+        let ast = self.engine.compile(&fs::read_to_string(path)?)?;
+        self.registry.borrow_mut().clear();
+        self.engine.run_ast(&ast)?;
+        let entries = self.registry.borrow_mut().drain(..).collect();
+        Ok((ast, entries))
+    }
+
+    // Runs the selected entry on a copy of `lines`
+    fn run(
+        &self,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+        active: usize,
+    ) -> Result<Option<Event>, Error> {
+        let (script, entry) = match self.selected {
+            Some((script, entry)) => (&self.scripts[script], &self.scripts[script].entries[entry]),
+            None => return Ok(None),
+        };
+        let document = Document(Rc::new(RefCell::new(DocumentState {
+            lines: lines.to_vec(),
+            selection: selection.clone(),
+            active,
+        })));
+        let values: Map = entry
+            .fields
+            .iter()
+            .zip(&self.field_inputs)
+            .map(|((label, _), value)| (label.as_str().into(), value.clone().into()))
+            .collect();
+
+        self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            &script.ast,
+            &entry.function,
+            (document.clone(), values),
+        )?;
+
+        let DocumentState {
+            lines,
+            selection,
+            active,
+        } = document.0.replace(DocumentState {
+            lines: Vec::new(),
+            selection: 0..0,
+            active: 0,
+        });
+        Ok(Some(ran(
+            format!("Run {}", entry.name),
+            lines,
+            &selection,
+            active,
+        )))
+    }
+
+    pub fn update(
+        &mut self,
+        message: Message,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+        active: usize,
+    ) -> Option<Event> {
+        match message {
+            Message::EntrySelected(index) => {
+                self.selected = self
+                    .scripts
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(script_index, script)| {
+                        (0..script.entries.len()).map(move |entry| (script_index, entry))
+                    })
+                    .nth(index);
+                if let Some((script, entry)) = self.selected {
+                    let fields = &self.scripts[script].entries[entry].fields;
+                    self.field_inputs = fields.iter().map(|(_, value)| value.clone()).collect();
+                }
+            }
+            Message::FieldChanged(index, value) => {
+                if let Some(input) = self.field_inputs.get_mut(index) {
+                    *input = value;
+                }
+            }
+            Message::Run => match self.run(lines, selection, active) {
+                Ok(event) => {
+                    self.status.clear();
+                    return event;
+                }
+                Err(error) => self.status = error.to_string(),
+            },
+            Message::Reload => self.open(),
+            Message::ApplyTemplates => match templater::apply(lines) {
+                Ok(lines) => {
+                    self.status.clear();
+                    return Some(ran(
+                        "Apply karaoke templates".to_owned(),
+                        lines,
+                        selection,
+                        active,
+                    ));
+                }
+                Err(error) => self.status = error.to_string(),
+            },
+            Message::RemoveEffects => {
+                self.status.clear();
+                return Some(ran(
+                    "Remove karaoke effects".to_owned(),
+                    templater::clean(lines),
+                    selection,
+                    active,
+                ));
+            }
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let labels: Vec<(String, bool)> = self
+            .scripts
+            .iter()
+            .enumerate()
+            .flat_map(|(script_index, script)| {
+                let selected = self.selected;
+                script
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .map(move |(entry_index, entry)| {
+                        let is_selected = selected == Some((script_index, entry_index));
+                        (format!("{}: {}", script.name, entry.name), is_selected)
+                    })
+            })
+            .collect();
+        self.list_buttons
+            .resize_with(labels.len(), button::State::default);
+
+        let list = self.list_buttons.iter_mut().zip(labels).enumerate().fold(
+            Column::new().width(Length::Fill),
+            |column, (index, (state, (label, is_selected)))| {
+                let marker = if is_selected { "> " } else { "" };
+                column.push(
+                    Button::new(
+                        state,
+                        Text::new(format!("{}{}", marker, label)).size(TEXT_SIZE),
+                    )
+                    .width(Length::Fill)
+                    .on_press(Message::EntrySelected(index)),
+                )
+            },
+        );
+
+        let fields: &[(String, String)] = match self.selected {
+            Some((script, entry)) => &self.scripts[script].entries[entry].fields,
+            None => &[],
+        };
+        self.fields
+            .resize_with(fields.len(), text_input::State::default);
+        let dialog = self
+            .fields
+            .iter_mut()
+            .zip(fields.iter().zip(&self.field_inputs))
+            .enumerate()
+            .fold(
+                Column::new().spacing(COLUMN_SPACING),
+                |column, (index, (state, ((label, _), value)))| {
+                    column.push(
+                        Row::new()
+                            .spacing(ROW_SPACING)
+                            .align_items(Align::Center)
+                            .push(
+                                Text::new(label.as_str())
+                                    .size(TEXT_SIZE)
+                                    .width(Length::Units(LABEL_WIDTH)),
+                            )
+                            .push(
+                                TextInput::new(state, label, value, move |value| {
+                                    Message::FieldChanged(index, value)
+                                })
+                                .on_submit(Message::Run)
+                                .size(TEXT_SIZE),
+                            ),
+                    )
+                },
+            );
+
+        let mut run = Button::new(&mut self.run, Text::new("Run").size(TEXT_SIZE));
+        if self.selected.is_some() {
+            run = run.on_press(Message::Run);
+        }
+
+        let editor = Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(dialog)
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(run)
+                    .push(
+                        Button::new(&mut self.reload, Text::new("Reload").size(TEXT_SIZE))
+                            .on_press(Message::Reload),
+                    )
+                    .push(
+                        Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                            .on_press(Message::Close),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(
+                        Button::new(
+                            &mut self.apply_templates,
+                            Text::new("Apply karaoke templates").size(TEXT_SIZE),
+                        )
+                        .on_press(Message::ApplyTemplates),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.remove_effects,
+                            Text::new("Remove karaoke effects").size(TEXT_SIZE),
+                        )
+                        .on_press(Message::RemoveEffects),
+                    ),
+            )
+            .push(Text::new(self.status.as_str()).size(TEXT_SIZE));
+
+        Row::new()
+            .spacing(ROW_SPACING)
+            .push(
+                Scrollable::new(&mut self.list_scroll)
+                    .width(Length::Units(LIST_WIDTH))
+                    .height(Length::Fill)
+                    .push(Text::new("Scripts"))
+                    .push(list),
+            )
+            .push(editor)
+            .into()
+    }
+}
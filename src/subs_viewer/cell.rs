@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::fmt;
+
 use iced_graphics::{
     backend::{self, Backend},
     Primitive, Renderer,
@@ -5,8 +8,71 @@ use iced_graphics::{
 use iced_native::{mouse, HorizontalAlignment, Point, Rectangle, VerticalAlignment};
 use iced_native::{Background, Color};
 
+use super::tags::{Line, NodeKind};
+
 pub type Cell<'a, Message, Backend> = cell::Cell<'a, Message, Renderer<Backend>>;
 
+// Shown in place of the override blocks when they are collapsed
+const TAG_MARKER: &str = "\u{2022}";
+
+/// How the override tags of a cell are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDisplay {
+    Verbatim,
+    Marker,
+    Hidden,
+}
+
+impl From<TagDisplay> for &'static str {
+    fn from(val: TagDisplay) -> Self {
+        match val {
+            TagDisplay::Verbatim => "Verbatim",
+            TagDisplay::Marker => "Marker",
+            TagDisplay::Hidden => "Hidden",
+        }
+    }
+}
+
+pub static ALL_TAG_DISPLAYS: &[TagDisplay] =
+    &[TagDisplay::Verbatim, TagDisplay::Marker, TagDisplay::Hidden];
+
+impl fmt::Display for TagDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+impl TagDisplay {
+    /// `text` with its override blocks and drawings shown as wanted. Each
+    /// run of them makes a single marker.
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        let is_tag = |kind: &NodeKind<'_>| matches!(kind, NodeKind::Block(_) | NodeKind::Drawing);
+        if self == TagDisplay::Verbatim {
+            return Cow::Borrowed(text);
+        }
+        let line = Line::parse(text);
+        if !line.nodes.iter().any(|node| is_tag(&node.kind)) {
+            return Cow::Borrowed(text);
+        }
+
+        let mut shown = String::with_capacity(text.len());
+        let mut is_collapsing = false;
+        for node in &line.nodes {
+            if is_tag(&node.kind) {
+                if self == TagDisplay::Marker && !is_collapsing {
+                    shown.push_str(TAG_MARKER);
+                }
+                is_collapsing = true;
+            } else {
+                shown.push_str(&text[node.span.clone()]);
+                is_collapsing = false;
+            }
+        }
+        Cow::Owned(shown)
+    }
+}
+
 impl<B> cell::Renderer for Renderer<B>
 where
     B: Backend + backend::Text,
@@ -25,6 +91,7 @@ where
         is_header: bool,
         is_highlighted: bool,
         value: &str,
+        tag_display: TagDisplay,
         style: &Self::Style,
     ) -> Self::Output {
         let is_mouse_over = bounds.contains(cursor_position);
@@ -62,7 +129,7 @@ where
         };
 
         let text_value = Primitive::Text {
-            content: value.to_owned(),
+            content: tag_display.apply(value).into_owned(),
             color: styling.text_color,
             font,
             bounds: Rectangle {
@@ -127,6 +194,8 @@ mod cell {
         Point, Rectangle, Size, VerticalAlignment, Widget,
     };
 
+    use super::TagDisplay;
+
     pub struct Cell<'a, Message, Renderer: self::Renderer> {
         row: isize,
         value: String,
@@ -139,6 +208,7 @@ mod cell {
         size: Option<u16>,
         horizontal_alignment: HorizontalAlignment,
         vertical_alignment: VerticalAlignment,
+        tag_display: TagDisplay,
         on_click: Option<Box<dyn Fn(usize) -> Message + 'a>>,
         style: Renderer::Style,
     }
@@ -160,6 +230,7 @@ mod cell {
                 size: None,
                 horizontal_alignment: HorizontalAlignment::Left,
                 vertical_alignment: VerticalAlignment::Center,
+                tag_display: TagDisplay::Verbatim,
                 on_click: None,
                 style: Renderer::Style::default(),
             }
@@ -178,11 +249,20 @@ mod cell {
                 size: None,
                 horizontal_alignment: HorizontalAlignment::Left,
                 vertical_alignment: VerticalAlignment::Center,
+                tag_display: TagDisplay::Verbatim,
                 on_click: None,
                 style: Renderer::Style::default(),
             }
         }
 
+        /// A header cell that reports its `column` index when clicked.
+        pub fn sortable(column: usize, value: &str) -> Self {
+            Self {
+                row: column as isize,
+                ..Self::no_interactive(value)
+            }
+        }
+
         pub fn font(mut self, font: Renderer::Font) -> Self {
             self.font = font;
             self
@@ -218,6 +298,11 @@ mod cell {
             self
         }
 
+        pub fn tag_display(mut self, tag_display: TagDisplay) -> Self {
+            self.tag_display = tag_display;
+            self
+        }
+
         pub fn on_click<F>(mut self, f: F) -> Self
         where
             F: 'a + Fn(usize) -> Message,
@@ -276,7 +361,8 @@ mod cell {
         ) -> event::Status {
             let bounds = layout.bounds();
 
-            if !self.is_header && bounds.contains(cursor_position) {
+            // Headers built with `no_interactive` have a negative row
+            if self.row >= 0 && bounds.contains(cursor_position) {
                 let mut event_status = event::Status::Captured;
                 match event {
                     Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
@@ -323,6 +409,7 @@ mod cell {
                 self.is_header,
                 self.is_highlighted,
                 &self.value,
+                self.tag_display,
                 &self.style,
             )
         }
@@ -354,6 +441,7 @@ mod cell {
             is_header: bool,
             is_highlighted: bool,
             value: &str,
+            tag_display: TagDisplay,
             style: &Self::Style,
         ) -> Self::Output;
     }
@@ -383,6 +471,7 @@ mod cell {
             _: bool,
             _: bool,
             _: &str,
+            _: TagDisplay,
             _: &Self::Style,
         ) -> Self::Output {
         }
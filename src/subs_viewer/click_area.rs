@@ -0,0 +1,100 @@
+use iced_native::{
+    event, layout, mouse, Clipboard, Element, Event, Hasher, Layout, Length, Point, Rectangle,
+    Widget,
+};
+
+/// Wraps `content` and reports where it is clicked, as a fraction of its
+/// width and height.
+pub struct ClickArea<'a, Message, Renderer> {
+    content: Element<'a, Message, Renderer>,
+    on_press: Box<dyn Fn(Point) -> Message + 'a>,
+}
+
+impl<'a, Message, Renderer> ClickArea<'a, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    pub fn new<F>(content: impl Into<Element<'a, Message, Renderer>>, on_press: F) -> Self
+    where
+        F: 'a + Fn(Point) -> Message,
+    {
+        Self {
+            content: content.into(),
+            on_press: Box::new(on_press),
+        }
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for ClickArea<'a, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        self.content.width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.layout(renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        messages: &mut Vec<Message>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if bounds.contains(cursor_position) =>
+            {
+                let position = Point::new(
+                    (cursor_position.x - bounds.x) / bounds.width,
+                    (cursor_position.y - bounds.y) / bounds.height,
+                );
+                messages.push((self.on_press)(position));
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        self.content
+            .draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        use std::hash::Hash;
+        struct Marker;
+        std::any::TypeId::of::<Marker>().hash(state);
+
+        self.content.hash_layout(state);
+    }
+}
+
+impl<'a, Message, Renderer> From<ClickArea<'a, Message, Renderer>>
+    for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: 'a + iced_native::Renderer,
+{
+    fn from(area: ClickArea<'a, Message, Renderer>) -> Self {
+        Element::new(area)
+    }
+}
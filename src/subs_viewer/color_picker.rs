@@ -0,0 +1,682 @@
+use iced::canvas::{self, Cache, Canvas, Cursor, Geometry, Path, Program, Stroke};
+use iced::{
+    button, mouse, slider, text_input, Align, Button, Color, Column, Container, Element, Image,
+    Length, Point, Rectangle, Row, Size, Slider, Text, TextInput,
+};
+
+use super::click_area::ClickArea;
+use super::preview::PreviewFrame;
+use super::styles::AssColor;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const LABEL_WIDTH: u16 = 80;
+const SLIDER_WIDTH: u16 = 200;
+const SWATCH_SIZE: u16 = 20;
+const MAX_RECENT: usize = 10;
+
+// Layout of the saturation/value square and of the hue bar beside it
+const SQUARE_SIZE: f32 = 180.0;
+const HUE_SPACING: f32 = 8.0;
+const HUE_WIDTH: f32 = 20.0;
+// The canvas has no gradients, the areas are drawn as cells
+const SQUARE_CELLS: usize = 30;
+const HUE_CELLS: usize = 60;
+
+/// Hue in degrees, saturation and value between 0 and 1.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Hsv {
+    hue: f32,
+    saturation: f32,
+    value: f32,
+}
+
+impl Hsv {
+    fn from_color(color: AssColor) -> Self {
+        let (red, green, blue) = unit_rgb(color);
+        let max = red.max(green).max(blue);
+        let delta = max - red.min(green).min(blue);
+
+        Self {
+            hue: hue(red, green, blue, max, delta),
+            saturation: if max > 0.0 { delta / max } else { 0.0 },
+            value: max,
+        }
+    }
+
+    fn to_color(self, alpha: u8) -> AssColor {
+        let chroma = self.value * self.saturation;
+        let (red, green, blue) = from_chroma(self.hue, chroma, self.value - chroma);
+        to_color(red, green, blue, alpha)
+    }
+}
+
+/// Hue in degrees, saturation and lightness between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hsl {
+    hue: f32,
+    saturation: f32,
+    lightness: f32,
+}
+
+impl Hsl {
+    fn from_color(color: AssColor) -> Self {
+        let (red, green, blue) = unit_rgb(color);
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let delta = max - min;
+        let lightness = (max + min) / 2.0;
+
+        Self {
+            hue: hue(red, green, blue, max, delta),
+            saturation: if delta > 0.0 {
+                delta / (1.0 - (2.0 * lightness - 1.0).abs())
+            } else {
+                0.0
+            },
+            lightness,
+        }
+    }
+
+    fn to_color(self, alpha: u8) -> AssColor {
+        let chroma = (1.0 - (2.0 * self.lightness - 1.0).abs()) * self.saturation;
+        let (red, green, blue) = from_chroma(self.hue, chroma, self.lightness - chroma / 2.0);
+        to_color(red, green, blue, alpha)
+    }
+}
+
+#[inline(always)]
+fn unit_rgb(color: AssColor) -> (f32, f32, f32) {
+    (
+        f32::from(color.red) / 255.0,
+        f32::from(color.green) / 255.0,
+        f32::from(color.blue) / 255.0,
+    )
+}
+
+#[inline(always)]
+fn to_color(red: f32, green: f32, blue: f32, alpha: u8) -> AssColor {
+    let channel = |value: f32| (value * 255.0).round().max(0.0).min(255.0) as u8;
+    AssColor::new(channel(red), channel(green), channel(blue), alpha)
+}
+
+fn hue(red: f32, green: f32, blue: f32, max: f32, delta: f32) -> f32 {
+    if delta <= 0.0 {
+        0.0
+    } else if (max - red).abs() < f32::EPSILON {
+        60.0 * ((green - blue) / delta).rem_euclid(6.0)
+    } else if (max - green).abs() < f32::EPSILON {
+        60.0 * ((blue - red) / delta + 2.0)
+    } else {
+        60.0 * ((red - green) / delta + 4.0)
+    }
+}
+
+// Red, green and blue of a hue with the given chroma, `offset` is added to
+// every channel
+fn from_chroma(hue: f32, chroma: f32, offset: f32) -> (f32, f32, f32) {
+    let sector = (hue / 60.0).rem_euclid(6.0);
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (red, green, blue) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    (red + offset, green + offset, blue + offset)
+}
+
+// An `alpha` of 255 is transparent in ASS
+#[inline(always)]
+fn to_iced(color: AssColor) -> Color {
+    Color::from_rgba8(
+        color.red,
+        color.green,
+        color.blue,
+        f32::from(255 - color.alpha) / 255.0,
+    )
+}
+
+/// Area being dragged with the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drag {
+    Square,
+    Hue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Picked(AssColor),
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // Position inside the square or the hue bar, in pixels
+    Dragged(Drag, Point),
+    DragEnded,
+    RedPicked(f32),
+    GreenPicked(f32),
+    BluePicked(f32),
+    HuePicked(f32),
+    SaturationPicked(f32),
+    LightnessPicked(f32),
+    AlphaPicked(f32),
+    HexChanged(String),
+    AssChanged(String),
+    RecentPicked(AssColor),
+    EyedropperToggled,
+    // Position inside the frame, as a fraction of its size
+    Sampled(Point),
+    Apply,
+    Cancel,
+}
+
+#[derive(Default)]
+pub struct ColorPicker {
+    square: Cache,
+    red: slider::State,
+    green: slider::State,
+    blue: slider::State,
+    hue: slider::State,
+    saturation: slider::State,
+    lightness: slider::State,
+    alpha: slider::State,
+    hex: text_input::State,
+    ass: text_input::State,
+    recent_buttons: Vec<button::State>,
+    eyedropper: button::State,
+    apply: button::State,
+    cancel: button::State,
+    color: AssColor,
+    // Kept apart from `color`, so that the hue survives greys and black
+    hsv: Hsv,
+    // Text being typed, applied only once valid
+    hex_input: String,
+    ass_input: String,
+    recent: Vec<AssColor>,
+    dragging: Option<Drag>,
+    frame: Option<PreviewFrame>,
+    is_sampling: bool,
+}
+
+impl ColorPicker {
+    /// Starts picking from `color`. Colors can be sampled from `frame`.
+    pub fn open(&mut self, color: AssColor, frame: Option<PreviewFrame>) {
+        self.frame = frame;
+        self.is_sampling = false;
+        self.dragging = None;
+        self.set_color(color);
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        let alpha = self.color.alpha;
+
+        match message {
+            Message::Dragged(drag, position) => {
+                let unit = |value: f32| (value / SQUARE_SIZE).max(0.0).min(1.0);
+                self.dragging = Some(drag);
+                match drag {
+                    Drag::Square => {
+                        self.hsv.saturation = unit(position.x);
+                        self.hsv.value = 1.0 - unit(position.y);
+                    }
+                    Drag::Hue => self.set_hue(unit(position.y) * 360.0),
+                }
+                self.set_hsv(self.hsv);
+            }
+            Message::DragEnded => self.dragging = None,
+            Message::RedPicked(red) => self.set_color(AssColor {
+                red: red as u8,
+                ..self.color
+            }),
+            Message::GreenPicked(green) => self.set_color(AssColor {
+                green: green as u8,
+                ..self.color
+            }),
+            Message::BluePicked(blue) => self.set_color(AssColor {
+                blue: blue as u8,
+                ..self.color
+            }),
+            Message::HuePicked(hue) => {
+                self.set_hue(hue);
+                self.set_hsv(self.hsv);
+            }
+            Message::SaturationPicked(saturation) => self.set_hsl(Hsl {
+                saturation,
+                ..self.hsl()
+            }),
+            Message::LightnessPicked(lightness) => self.set_hsl(Hsl {
+                lightness,
+                ..self.hsl()
+            }),
+            Message::AlphaPicked(alpha) => self.set_color(AssColor {
+                alpha: alpha as u8,
+                ..self.color
+            }),
+            Message::HexChanged(value) => {
+                if let Some(color) = parse_hex(&value, alpha) {
+                    self.set_color(color);
+                }
+                self.hex_input = value;
+            }
+            Message::AssChanged(value) => {
+                if let Ok(color) = value.parse() {
+                    self.set_color(color);
+                }
+                self.ass_input = value;
+            }
+            Message::RecentPicked(color) => self.set_color(color),
+            Message::EyedropperToggled => self.is_sampling = !self.is_sampling,
+            Message::Sampled(position) => {
+                let pixel = self.frame.as_ref().and_then(|frame| {
+                    let x = (position.x * frame.width as f32) as u32;
+                    let y = (position.y * frame.height as f32) as u32;
+                    frame.pixel(x, y)
+                });
+                if let Some([blue, green, red, _]) = pixel {
+                    self.set_color(AssColor::new(red, green, blue, alpha));
+                }
+                self.is_sampling = false;
+            }
+            Message::Apply => {
+                let picked = self.color;
+                self.recent.retain(|&color| color != picked);
+                self.recent.insert(0, picked);
+                self.recent.truncate(MAX_RECENT);
+                return Some(Event::Picked(picked));
+            }
+            Message::Cancel => return Some(Event::Cancelled),
+        }
+
+        None
+    }
+
+    fn hsl(&self) -> Hsl {
+        Hsl {
+            hue: self.hsv.hue,
+            ..Hsl::from_color(self.color)
+        }
+    }
+
+    fn set_hue(&mut self, hue: f32) {
+        if (hue - self.hsv.hue).abs() > f32::EPSILON {
+            self.hsv.hue = hue;
+            self.square.clear();
+        }
+    }
+
+    fn set_hsv(&mut self, hsv: Hsv) {
+        self.hsv = hsv;
+        self.color = hsv.to_color(self.color.alpha);
+        self.load();
+    }
+
+    fn set_hsl(&mut self, hsl: Hsl) {
+        self.color = hsl.to_color(self.color.alpha);
+        self.hsv = Hsv {
+            hue: hsl.hue,
+            ..Hsv::from_color(self.color)
+        };
+        self.load();
+    }
+
+    fn set_color(&mut self, color: AssColor) {
+        let hsv = Hsv::from_color(color);
+        self.color = color;
+        // Greys have no hue, keep the one being edited
+        if hsv.saturation > 0.0 {
+            self.set_hue(hsv.hue);
+        }
+        self.hsv = Hsv {
+            hue: self.hsv.hue,
+            ..hsv
+        };
+        self.load();
+    }
+
+    // Resets the typed text to the current color
+    fn load(&mut self) {
+        self.hex_input = format!(
+            "#{:02X}{:02X}{:02X}",
+            self.color.red, self.color.green, self.color.blue
+        );
+        self.ass_input = format!("{}&", self.color);
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let color = self.color;
+        let hsl = self.hsl();
+
+        let area = Canvas::new(HsvArea {
+            hsv: self.hsv,
+            dragging: self.dragging,
+            square: &self.square,
+        })
+        .width(Length::Units(
+            (SQUARE_SIZE + HUE_SPACING + HUE_WIDTH) as u16,
+        ))
+        .height(Length::Units(SQUARE_SIZE as u16));
+
+        let sliders = Column::new()
+            .spacing(COLUMN_SPACING)
+            .push(Self::slider(
+                "Red",
+                &mut self.red,
+                255.0,
+                f32::from(color.red),
+                Message::RedPicked,
+            ))
+            .push(Self::slider(
+                "Green",
+                &mut self.green,
+                255.0,
+                f32::from(color.green),
+                Message::GreenPicked,
+            ))
+            .push(Self::slider(
+                "Blue",
+                &mut self.blue,
+                255.0,
+                f32::from(color.blue),
+                Message::BluePicked,
+            ))
+            .push(Self::slider(
+                "Hue",
+                &mut self.hue,
+                360.0,
+                hsl.hue,
+                Message::HuePicked,
+            ))
+            .push(Self::slider(
+                "Saturation",
+                &mut self.saturation,
+                1.0,
+                hsl.saturation,
+                Message::SaturationPicked,
+            ))
+            .push(Self::slider(
+                "Lightness",
+                &mut self.lightness,
+                1.0,
+                hsl.lightness,
+                Message::LightnessPicked,
+            ))
+            .push(Self::slider(
+                "Alpha",
+                &mut self.alpha,
+                255.0,
+                f32::from(color.alpha),
+                Message::AlphaPicked,
+            ))
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .align_items(Align::Center)
+                    .push(Self::swatch(color))
+                    .push(
+                        TextInput::new(
+                            &mut self.hex,
+                            "#RRGGBB",
+                            &self.hex_input,
+                            Message::HexChanged,
+                        )
+                        .size(TEXT_SIZE)
+                        .width(Length::Units(90)),
+                    )
+                    .push(
+                        TextInput::new(
+                            &mut self.ass,
+                            "&HAABBGGRR&",
+                            &self.ass_input,
+                            Message::AssChanged,
+                        )
+                        .size(TEXT_SIZE)
+                        .width(Length::Units(120)),
+                    ),
+            );
+
+        self.recent_buttons
+            .resize_with(self.recent.len(), button::State::default);
+        let recent = self.recent_buttons.iter_mut().zip(&self.recent).fold(
+            Row::new()
+                .spacing(ROW_SPACING)
+                .align_items(Align::Center)
+                .push(Text::new("Recent").size(TEXT_SIZE)),
+            |row, (state, &color)| {
+                row.push(
+                    Button::new(state, Self::swatch(color))
+                        .padding(2)
+                        .on_press(Message::RecentPicked(color)),
+                )
+            },
+        );
+
+        let mut eyedropper = Button::new(
+            &mut self.eyedropper,
+            Text::new(if self.is_sampling {
+                "Click the frame"
+            } else {
+                "Eyedropper"
+            })
+            .size(TEXT_SIZE),
+        );
+        if self.frame.is_some() {
+            eyedropper = eyedropper.on_press(Message::EyedropperToggled);
+        }
+
+        let buttons = Row::new()
+            .spacing(ROW_SPACING)
+            .push(eyedropper)
+            .push(
+                Button::new(&mut self.apply, Text::new("Apply").size(TEXT_SIZE))
+                    .on_press(Message::Apply),
+            )
+            .push(
+                Button::new(&mut self.cancel, Text::new("Cancel").size(TEXT_SIZE))
+                    .on_press(Message::Cancel),
+            );
+
+        let mut column = Column::new()
+            .spacing(COLUMN_SPACING)
+            .push(Row::new().spacing(ROW_SPACING * 2).push(area).push(sliders))
+            .push(recent)
+            .push(buttons);
+
+        if let (Some(frame), true) = (&self.frame, self.is_sampling) {
+            column = column.push(ClickArea::new(
+                Image::new(frame.handle.clone())
+                    .width(Length::Units(frame.width as u16))
+                    .height(Length::Units(frame.height as u16)),
+                Message::Sampled,
+            ));
+        }
+
+        column.into()
+    }
+
+    #[inline(always)]
+    fn slider<'a>(
+        label: &str,
+        state: &'a mut slider::State,
+        max: f32,
+        value: f32,
+        message: fn(f32) -> Message,
+    ) -> Element<'a, Message> {
+        // Channels are whole numbers, fractions get two decimals
+        let (step, shown) = if max > 1.0 {
+            (1.0, format!("{:.0}", value))
+        } else {
+            (0.01, format!("{:.2}", value))
+        };
+
+        Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Text::new(label)
+                    .size(TEXT_SIZE)
+                    .width(Length::Units(LABEL_WIDTH)),
+            )
+            .push(
+                Slider::new(state, 0.0..=max, value, message)
+                    .step(step)
+                    .width(Length::Units(SLIDER_WIDTH)),
+            )
+            .push(Text::new(shown).size(TEXT_SIZE))
+            .into()
+    }
+
+    #[inline(always)]
+    fn swatch<'a>(color: AssColor) -> Element<'a, Message> {
+        Container::new(Text::new(""))
+            .width(Length::Units(SWATCH_SIZE))
+            .height(Length::Units(SWATCH_SIZE))
+            .style(style::Swatch(to_iced(color)))
+            .into()
+    }
+}
+
+// Accepts `#RRGGBB` and `RRGGBB`
+fn parse_hex(value: &str, alpha: u8) -> Option<AssColor> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [_, red, green, blue] = value.to_be_bytes();
+    Some(AssColor::new(red, green, blue, alpha))
+}
+
+// Saturation/value square with the hue bar on its right
+struct HsvArea<'a> {
+    hsv: Hsv,
+    dragging: Option<Drag>,
+    square: &'a Cache,
+}
+
+impl<'a> Program<Message> for HsvArea<'a> {
+    fn update(
+        &mut self,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (canvas::event::Status, Option<Message>) {
+        // Dragging goes on outside the bounds, the position is clamped
+        let position = match cursor.position() {
+            Some(position) => Point::new(position.x - bounds.x, position.y - bounds.y),
+            None => return (canvas::event::Status::Ignored, None),
+        };
+
+        let message = match (event, self.dragging) {
+            (canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)), None)
+                if cursor.is_over(&bounds) =>
+            {
+                if position.x <= SQUARE_SIZE {
+                    Message::Dragged(Drag::Square, position)
+                } else if position.x >= SQUARE_SIZE + HUE_SPACING {
+                    Message::Dragged(Drag::Hue, position)
+                } else {
+                    return (canvas::event::Status::Ignored, None);
+                }
+            }
+            (canvas::Event::Mouse(mouse::Event::CursorMoved { .. }), Some(drag)) => {
+                Message::Dragged(drag, position)
+            }
+            (canvas::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)), Some(_)) => {
+                Message::DragEnded
+            }
+            _ => return (canvas::event::Status::Ignored, None),
+        };
+
+        (canvas::event::Status::Captured, Some(message))
+    }
+
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let hue = self.hsv.hue;
+        let areas = self.square.draw(bounds.size(), |frame| {
+            let cell = SQUARE_SIZE / SQUARE_CELLS as f32;
+            for column in 0..SQUARE_CELLS {
+                for row in 0..SQUARE_CELLS {
+                    let hsv = Hsv {
+                        hue,
+                        saturation: (column as f32 + 0.5) / SQUARE_CELLS as f32,
+                        value: 1.0 - (row as f32 + 0.5) / SQUARE_CELLS as f32,
+                    };
+                    frame.fill_rectangle(
+                        Point::new(column as f32 * cell, row as f32 * cell),
+                        // Overlapping a bit hides the seams between cells
+                        Size::new(cell + 0.5, cell + 0.5),
+                        to_iced(hsv.to_color(0)),
+                    );
+                }
+            }
+
+            let cell = SQUARE_SIZE / HUE_CELLS as f32;
+            for row in 0..HUE_CELLS {
+                let hsv = Hsv {
+                    hue: (row as f32 + 0.5) / HUE_CELLS as f32 * 360.0,
+                    saturation: 1.0,
+                    value: 1.0,
+                };
+                frame.fill_rectangle(
+                    Point::new(SQUARE_SIZE + HUE_SPACING, row as f32 * cell),
+                    Size::new(HUE_WIDTH, cell + 0.5),
+                    to_iced(hsv.to_color(0)),
+                );
+            }
+        });
+
+        let mut markers = canvas::Frame::new(bounds.size());
+        let marker = Path::circle(
+            Point::new(
+                self.hsv.saturation * SQUARE_SIZE,
+                (1.0 - self.hsv.value) * SQUARE_SIZE,
+            ),
+            5.0,
+        );
+        // Black on light colors, white on dark ones
+        let marker_color = if self.hsv.value > 0.5 {
+            Color::BLACK
+        } else {
+            Color::WHITE
+        };
+        markers.stroke(
+            &marker,
+            Stroke::default().with_color(marker_color).with_width(2.0),
+        );
+        markers.stroke(
+            &Path::rectangle(
+                Point::new(
+                    SQUARE_SIZE + HUE_SPACING - 1.0,
+                    hue / 360.0 * SQUARE_SIZE - 2.0,
+                ),
+                Size::new(HUE_WIDTH + 2.0, 4.0),
+            ),
+            Stroke::default().with_color(Color::BLACK).with_width(2.0),
+        );
+
+        vec![areas, markers.into_geometry()]
+    }
+}
+
+mod style {
+    use iced::{container, Color};
+
+    pub struct Swatch(pub Color);
+
+    impl container::StyleSheet for Swatch {
+        fn style(&self) -> container::Style {
+            container::Style {
+                background: Some(self.0.into()),
+                border_width: 1.0,
+                border_color: Color::BLACK,
+                ..container::Style::default()
+            }
+        }
+    }
+}
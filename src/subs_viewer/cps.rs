@@ -0,0 +1,188 @@
+use iced::{Align, Checkbox, Element, Row, Text};
+
+use iced_aw::number_input::{self, NumberInput};
+
+use super::tags::Line;
+use super::time::Time;
+
+const ROW_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+
+/// Reading speed of a line, compared against the configured thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpsLevel {
+    Normal,
+    Warning,
+    Exceeded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpsSettings {
+    /// Lines above this speed are tinted as a warning
+    pub warning: f32,
+    /// Lines above this speed are tinted as an error
+    pub maximum: f32,
+    pub ignore_whitespace: bool,
+    pub ignore_punctuation: bool,
+    /// How many characters a CJK ideograph or kana counts as
+    pub cjk_weight: f32,
+}
+
+impl Default for CpsSettings {
+    fn default() -> Self {
+        Self {
+            warning: 15.0,
+            maximum: 20.0,
+            ignore_whitespace: true,
+            ignore_punctuation: true,
+            // Full-width glyphs take about two columns of latin text
+            cjk_weight: 2.0,
+        }
+    }
+}
+
+impl CpsSettings {
+    /// Characters per second of `text` shown for `duration`, `None` when the
+    /// line has no positive duration.
+    pub fn cps(&self, text: &str, duration: Time) -> Option<f32> {
+        if duration == Time::ZERO {
+            return None;
+        }
+
+        let seconds = duration.as_millis() as f32 / 1000.0;
+        Some(self.count_chars(text) / seconds)
+    }
+
+    pub fn level(&self, cps: f32) -> CpsLevel {
+        if cps > self.maximum {
+            CpsLevel::Exceeded
+        } else if cps > self.warning {
+            CpsLevel::Warning
+        } else {
+            CpsLevel::Normal
+        }
+    }
+
+    /// Weighted number of characters a viewer has to read in `text`.
+    pub fn count_chars(&self, text: &str) -> f32 {
+        Line::parse(text)
+            .visible_chars()
+            .filter(|c| !(self.ignore_whitespace && c.is_whitespace()))
+            .filter(|c| !(self.ignore_punctuation && is_punctuation(*c)))
+            .map(|c| if is_cjk(c) { self.cjk_weight } else { 1.0 })
+            .sum()
+    }
+}
+
+#[inline(always)]
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '\u{2000}'..='\u{206f}' // General punctuation
+                | '\u{3000}'..='\u{303f}' // CJK symbols and punctuation
+                | '\u{ff01}'..='\u{ff0f}' // Full-width punctuation
+                | '\u{ff1a}'..='\u{ff20}'
+                | '¡'
+                | '¿'
+                | '«'
+                | '»'
+        )
+}
+
+#[inline(always)]
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30ff}' // Hiragana and katakana
+            | '\u{3400}'..='\u{4dbf}' // CJK extension A
+            | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+            | '\u{ac00}'..='\u{d7af}' // Hangul syllables
+            | '\u{f900}'..='\u{faff}' // CJK compatibility ideographs
+            | '\u{ff66}'..='\u{ff9f}' // Half-width katakana
+    )
+}
+
+#[derive(Default)]
+pub struct CpsControls {
+    warning: number_input::State,
+    maximum: number_input::State,
+    cjk_weight: number_input::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    WarningPicked(f32),
+    MaximumPicked(f32),
+    WhitespaceToggled(bool),
+    PunctuationToggled(bool),
+    CjkWeightPicked(f32),
+}
+
+impl CpsControls {
+    pub fn update(&mut self, message: Message, settings: &mut CpsSettings) {
+        match message {
+            Message::WarningPicked(warning) => settings.warning = warning,
+            Message::MaximumPicked(maximum) => settings.maximum = maximum,
+            Message::WhitespaceToggled(ignore) => settings.ignore_whitespace = ignore,
+            Message::PunctuationToggled(ignore) => settings.ignore_punctuation = ignore,
+            Message::CjkWeightPicked(cjk_weight) => settings.cjk_weight = cjk_weight,
+        }
+    }
+
+    pub fn view(&mut self, settings: &CpsSettings) -> Element<Message> {
+        Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("CPS warning").size(TEXT_SIZE))
+            .push(
+                NumberInput::new(
+                    &mut self.warning,
+                    settings.warning,
+                    99.0,
+                    Message::WarningPicked,
+                )
+                .step(0.5)
+                .min(0.0),
+            )
+            .push(Text::new("maximum").size(TEXT_SIZE))
+            .push(
+                NumberInput::new(
+                    &mut self.maximum,
+                    settings.maximum,
+                    99.0,
+                    Message::MaximumPicked,
+                )
+                .step(0.5)
+                .min(0.0),
+            )
+            .push(
+                Checkbox::new(
+                    settings.ignore_whitespace,
+                    "Ignore whitespace",
+                    Message::WhitespaceToggled,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(
+                    settings.ignore_punctuation,
+                    "Ignore punctuation",
+                    Message::PunctuationToggled,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(Text::new("CJK weight").size(TEXT_SIZE))
+            .push(
+                NumberInput::new(
+                    &mut self.cjk_weight,
+                    settings.cjk_weight,
+                    9.0,
+                    Message::CjkWeightPicked,
+                )
+                .step(0.5)
+                .min(0.0),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,647 @@
+use std::fmt;
+use std::ops::Range;
+
+use iced::{button, pick_list, Align, Button, Element, PickList, Point, Row, Text};
+
+use super::formatting;
+use super::handles::{Drag, Shape};
+use super::styles::AssStyle;
+use super::tags::{Item, Line, NodeKind, Tag, TagKind};
+use super::typesetting::{self, Placement};
+
+const ROW_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+
+// Grid lines closer than this, in script pixels, are not drawn
+const MIN_GRID_STEP: f32 = 8.0;
+
+/// Kind of the segments added by clicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Line,
+    Bezier,
+    Spline,
+}
+
+impl From<Segment> for &'static str {
+    fn from(val: Segment) -> Self {
+        match val {
+            Segment::Line => "Lines",
+            Segment::Bezier => "Bézier curves",
+            Segment::Spline => "B-spline",
+        }
+    }
+}
+
+pub static ALL_SEGMENTS: &[Segment] = &[Segment::Line, Segment::Bezier, Segment::Spline];
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+/// Size of the grid points snap to, in drawing units. 0 disables it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid(pub u16);
+
+pub static ALL_GRIDS: &[Grid] = &[Grid(0), Grid(1), Grid(5), Grid(10), Grid(20), Grid(50)];
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "No grid"),
+            size => write!(f, "Grid {}", size),
+        }
+    }
+}
+
+impl Grid {
+    fn snap(self, point: Point) -> Point {
+        if self.0 == 0 {
+            return point;
+        }
+        let size = self.0 as f32;
+        Point::new(
+            (point.x / size).round() * size,
+            (point.y / size).round() * size,
+        )
+    }
+
+    // Grid lines over the preview of a script of `resolution`, when they are
+    // far enough apart
+    fn lines(self, transform: &Transform, resolution: (u32, u32)) -> Vec<Shape> {
+        let size = self.0 as f32;
+        let (step_x, step_y) = (size * transform.scale_x, size * transform.scale_y);
+        if self.0 == 0 || step_x.min(step_y) < MIN_GRID_STEP {
+            return Vec::new();
+        }
+
+        let (width, height) = (resolution.0 as f32, resolution.1 as f32);
+        let at = |point: Point| typesetting::fraction(point, resolution);
+        let origin = transform.origin;
+        let steps = |origin: f32, step: f32, length: f32| {
+            let first = (-origin / step).ceil() as i32;
+            let last = ((length - origin) / step).floor() as i32;
+            (first..=last).map(move |index| origin + index as f32 * step)
+        };
+
+        let columns = steps(origin.x, step_x, width)
+            .map(|x| Shape::Line(at(Point::new(x, 0.0)), at(Point::new(x, height))));
+        let rows = steps(origin.y, step_y, height)
+            .map(|y| Shape::Line(at(Point::new(0.0, y)), at(Point::new(width, y))));
+        columns.chain(rows).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+    Move,
+    MoveOpen,
+    Line,
+    Bezier,
+    Spline,
+    ExtendSpline,
+    CloseSpline,
+}
+
+impl CommandKind {
+    fn from_letter(letter: &str) -> Option<Self> {
+        match letter {
+            "m" => Some(CommandKind::Move),
+            "n" => Some(CommandKind::MoveOpen),
+            "l" => Some(CommandKind::Line),
+            "b" => Some(CommandKind::Bezier),
+            "s" => Some(CommandKind::Spline),
+            "p" => Some(CommandKind::ExtendSpline),
+            "c" => Some(CommandKind::CloseSpline),
+            _ => None,
+        }
+    }
+
+    fn letter(self) -> &'static str {
+        match self {
+            CommandKind::Move => "m",
+            CommandKind::MoveOpen => "n",
+            CommandKind::Line => "l",
+            CommandKind::Bezier => "b",
+            CommandKind::Spline => "s",
+            CommandKind::ExtendSpline => "p",
+            CommandKind::CloseSpline => "c",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Command {
+    kind: CommandKind,
+    points: Vec<Point>,
+}
+
+/// Commands of a `\p` drawing or of a vector `\clip`, in drawing units.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Drawing {
+    commands: Vec<Command>,
+}
+
+impl Drawing {
+    /// Unknown tokens and numbers before the first command are skipped.
+    pub fn parse(source: &str) -> Self {
+        let mut commands: Vec<Command> = Vec::new();
+        // X coordinate waiting for its y
+        let mut pending = None;
+
+        for token in source.split_whitespace() {
+            if let Some(kind) = CommandKind::from_letter(token) {
+                commands.push(Command {
+                    kind,
+                    points: Vec::new(),
+                });
+                pending = None;
+            } else if let (Ok(value), Some(command)) = (token.parse(), commands.last_mut()) {
+                match pending.take() {
+                    Some(x) => command.points.push(Point::new(x, value)),
+                    None => pending = Some(value),
+                }
+            }
+        }
+
+        Self { commands }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        self.commands
+            .iter()
+            .flat_map(|command| command.points.iter().copied())
+    }
+
+    pub fn point_mut(&mut self, index: usize) -> Option<&mut Point> {
+        self.commands
+            .iter_mut()
+            .flat_map(|command| command.points.iter_mut())
+            .nth(index)
+    }
+
+    // Smallest and largest coordinates of the points, if any
+    fn bounds(&self) -> Option<(Point, Point)> {
+        self.points().fold(None, |bounds, point| {
+            let (min, max) = bounds.unwrap_or((point, point));
+            Some((
+                Point::new(min.x.min(point.x), min.y.min(point.y)),
+                Point::new(max.x.max(point.x), max.y.max(point.y)),
+            ))
+        })
+    }
+
+    /// Moves every point, control points included.
+    pub fn map_points(&mut self, map: impl Fn(Point) -> Point) {
+        for point in self
+            .commands
+            .iter_mut()
+            .flat_map(|command| command.points.iter_mut())
+        {
+            *point = map(*point);
+        }
+    }
+
+    /// Adds a segment to `point`, or starts a new shape there.
+    pub fn push(&mut self, segment: Segment, point: Point, is_new_shape: bool) {
+        let last = self.points().last();
+        let from = match last {
+            Some(from) if !is_new_shape => from,
+            _ => {
+                self.commands.push(Command {
+                    kind: CommandKind::Move,
+                    points: vec![point],
+                });
+                return;
+            }
+        };
+
+        let (kind, points) = match segment {
+            Segment::Line => (CommandKind::Line, vec![point]),
+            Segment::Bezier => {
+                // Control points start on the straight line
+                let along = |fraction: f32| {
+                    Point::new(
+                        from.x + (point.x - from.x) * fraction,
+                        from.y + (point.y - from.y) * fraction,
+                    )
+                };
+                (
+                    CommandKind::Bezier,
+                    vec![along(1.0 / 3.0), along(2.0 / 3.0), point],
+                )
+            }
+            Segment::Spline => (CommandKind::Spline, vec![point]),
+        };
+
+        match self.commands.last_mut() {
+            Some(last)
+                if last.kind == kind
+                    || (kind == CommandKind::Spline && last.kind == CommandKind::ExtendSpline) =>
+            {
+                last.points.extend(points)
+            }
+            _ => self.commands.push(Command { kind, points }),
+        }
+    }
+
+    // Closes a B-spline ending the shape
+    fn end_shape(&mut self) -> bool {
+        let is_spline = matches!(
+            self.commands.last(),
+            Some(Command {
+                kind: CommandKind::Spline,
+                ..
+            }) | Some(Command {
+                kind: CommandKind::ExtendSpline,
+                ..
+            })
+        );
+        if is_spline {
+            self.commands.push(Command {
+                kind: CommandKind::CloseSpline,
+                points: Vec::new(),
+            });
+        }
+        is_spline
+    }
+
+    // Removes the last point, with its control points for a curve
+    fn remove_last(&mut self) -> bool {
+        let command = match self.commands.last_mut() {
+            Some(command) => command,
+            None => return false,
+        };
+
+        let count = match command.kind {
+            CommandKind::Bezier => 3,
+            _ => 1,
+        };
+        let length = command.points.len().saturating_sub(count);
+        command.points.truncate(length);
+        if command.points.is_empty() {
+            self.commands.pop();
+        }
+        true
+    }
+}
+
+impl fmt::Display for Drawing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let commands: Vec<String> = self
+            .commands
+            .iter()
+            .map(|command| {
+                let coordinates = command.points.iter().flat_map(|point| {
+                    vec![typesetting::number(point.x), typesetting::number(point.y)]
+                });
+                std::iter::once(command.kind.letter().to_owned())
+                    .chain(coordinates)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        write!(f, "{}", commands.join(" "))
+    }
+}
+
+// Span of the first drawing of the line, with its `\p` scale
+fn locate(text: &str) -> Option<(Range<usize>, u32)> {
+    let line = Line::parse(text);
+    let mut scale = 0;
+
+    for node in &line.nodes {
+        match &node.kind {
+            NodeKind::Block(items) => {
+                let drawing_scale = scale;
+                for item in items {
+                    if let Item::Tag(Tag {
+                        kind: TagKind::Drawing(drawing),
+                        ..
+                    }) = item
+                    {
+                        scale = *drawing;
+                    }
+                }
+                // Drawing mode turned off before anything was drawn
+                if drawing_scale > 0 && scale == 0 {
+                    let start = node.span.start;
+                    return Some((start..start, drawing_scale));
+                }
+            }
+            NodeKind::Drawing => return Some((node.span.clone(), scale)),
+            _ => {}
+        }
+    }
+
+    // A drawing mode left on at the end starts an empty drawing
+    if scale > 0 {
+        Some((text.len()..text.len(), scale))
+    } else {
+        None
+    }
+}
+
+// The line with an empty drawing started on its own line before the text,
+// after the block starting the line so its tags stay first
+fn started(text: &str) -> String {
+    let line = Line::parse(text);
+    let has_text = line
+        .nodes
+        .iter()
+        .any(|node| !matches!(node.kind, NodeKind::Block(_)));
+    let mut text = text.to_owned();
+    if has_text {
+        let at = match line.nodes.first() {
+            Some(node) if matches!(node.kind, NodeKind::Block(_)) => node.span.end,
+            _ => 0,
+        };
+        text.insert_str(at, "{\\p1}{\\p0}\\N");
+    } else {
+        text.push_str("{\\p1}");
+    }
+    text
+}
+
+fn replaced(text: &str, span: Range<usize>, drawing: &Drawing) -> String {
+    format!("{}{}{}", &text[..span.start], drawing, &text[span.end..])
+}
+
+// Maps drawing units to script pixels. The line alignment places the box of
+// the drawing at the line position as libass does: the box is as wide as the
+// drawing from its own x origin, and starts at its top. Text around the
+// drawing is left out.
+struct Transform {
+    origin: Point,
+    scale_x: f32,
+    scale_y: f32,
+}
+
+impl Transform {
+    fn new(
+        text: &str,
+        style: &AssStyle,
+        drawing: &Drawing,
+        scale: u32,
+        resolution: (u32, u32),
+    ) -> Self {
+        let placement = Placement::read(text, style, resolution);
+        let factor = 2f32.powi(scale.max(1) as i32 - 1);
+        let scale_x = placement.scale_x / 100.0 / factor;
+        let scale_y = placement.scale_y / 100.0 / factor;
+
+        let alignment = formatting::line_alignment(text).unwrap_or(style.alignment);
+        let numpad = alignment.0.max(1) - 1;
+        let (min, max) = drawing.bounds().unwrap_or((Point::ORIGIN, Point::ORIGIN));
+        // Drawing units of the box put at the position
+        let x = match numpad % 3 {
+            0 => 0.0,
+            1 => (max.x - min.x) / 2.0,
+            _ => max.x - min.x,
+        };
+        let y = match numpad / 3 {
+            0 => max.y,
+            1 => (min.y + max.y) / 2.0,
+            _ => min.y,
+        };
+
+        Self {
+            origin: Point::new(
+                placement.position.x - x * scale_x,
+                placement.position.y - y * scale_y,
+            ),
+            scale_x,
+            scale_y,
+        }
+    }
+
+    fn to_script(&self, point: Point) -> Point {
+        Point::new(
+            self.origin.x + point.x * self.scale_x,
+            self.origin.y + point.y * self.scale_y,
+        )
+    }
+
+    fn to_drawing(&self, point: Point) -> Point {
+        Point::new(
+            (point.x - self.origin.x) / self.scale_x.max(f32::EPSILON),
+            (point.y - self.origin.y) / self.scale_y.max(f32::EPSILON),
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    SegmentPicked(Segment),
+    GridPicked(Grid),
+    StartDrawing,
+    NewShape,
+    RemovePoint,
+}
+
+/// Edits the first `\p` drawing of the active line on its preview.
+#[derive(Default)]
+pub struct DrawingEditor {
+    segments: pick_list::State<Segment>,
+    grids: pick_list::State<Grid>,
+    start_drawing: button::State,
+    new_shape: button::State,
+    remove_point: button::State,
+    segment: Option<Segment>,
+    grid: Option<Grid>,
+    // The next point starts a shape with `m`
+    is_new_shape: bool,
+    // Index of the dragged point
+    grab: Option<usize>,
+}
+
+impl DrawingEditor {
+    fn segment(&self) -> Segment {
+        self.segment.unwrap_or(Segment::Line)
+    }
+
+    fn grid(&self) -> Grid {
+        self.grid.unwrap_or(Grid(0))
+    }
+
+    /// Returns the edited `text`, if any.
+    pub fn update(&mut self, message: Message, text: &str) -> Option<String> {
+        let edit = |change: fn(&mut Drawing) -> bool| {
+            let (span, _) = locate(text)?;
+            let mut drawing = Drawing::parse(&text[span.clone()]);
+            if change(&mut drawing) {
+                Some(replaced(text, span, &drawing))
+            } else {
+                None
+            }
+        };
+
+        match message {
+            Message::SegmentPicked(segment) => {
+                self.segment = Some(segment);
+                None
+            }
+            Message::GridPicked(grid) => {
+                self.grid = Some(grid);
+                None
+            }
+            Message::StartDrawing if locate(text).is_none() => {
+                self.is_new_shape = false;
+                Some(started(text))
+            }
+            Message::StartDrawing => None,
+            Message::NewShape => {
+                self.is_new_shape = true;
+                edit(Drawing::end_shape)
+            }
+            Message::RemovePoint => edit(Drawing::remove_last),
+        }
+    }
+
+    /// Clicking away from the points adds one, points are moved by dragging.
+    /// Lines without a drawing are left as they are.
+    pub fn drag(
+        &mut self,
+        drag: Drag,
+        text: &str,
+        style: &AssStyle,
+        resolution: (u32, u32),
+    ) -> Option<String> {
+        match drag {
+            Drag::Pressed(at) => {
+                let (span, scale) = locate(text)?;
+                let mut drawing = Drawing::parse(&text[span.clone()]);
+                let transform = Transform::new(text, style, &drawing, scale, resolution);
+                let at = typesetting::script(at, resolution);
+
+                let grabbed = drawing
+                    .points()
+                    .position(|point| typesetting::is_near(transform.to_script(point), at));
+                let index = match grabbed {
+                    Some(index) => index,
+                    None => {
+                        let point = self.grid().snap(transform.to_drawing(at));
+                        drawing.push(self.segment(), point, self.is_new_shape);
+                        self.is_new_shape = false;
+                        drawing.points().count() - 1
+                    }
+                };
+
+                self.grab = Some(index);
+                Some(replaced(text, span, &drawing))
+            }
+            Drag::Moved(at) => {
+                let index = self.grab?;
+                let (span, scale) = locate(text)?;
+                let mut drawing = Drawing::parse(&text[span.clone()]);
+                let transform = Transform::new(text, style, &drawing, scale, resolution);
+                let at = typesetting::script(at, resolution);
+
+                *drawing.point_mut(index)? = self.grid().snap(transform.to_drawing(at));
+                Some(replaced(text, span, &drawing))
+            }
+            Drag::Released => {
+                self.grab = None;
+                None
+            }
+        }
+    }
+
+    /// Guides of the drawing of `text`, in fractions of the preview.
+    pub fn shapes(&self, text: &str, style: &AssStyle, resolution: (u32, u32)) -> Vec<Shape> {
+        let (span, scale) = match locate(text) {
+            Some(located) => located,
+            None => return Vec::new(),
+        };
+        let drawing = Drawing::parse(&text[span]);
+        let transform = Transform::new(text, style, &drawing, scale, resolution);
+        let at = |point: Point| typesetting::fraction(transform.to_script(point), resolution);
+
+        let mut shapes = self.grid().lines(&transform, resolution);
+        // Last point drawn to
+        let mut from: Option<Point> = None;
+
+        for command in &drawing.commands {
+            match command.kind {
+                // Control points are linked to the ends of their curve
+                CommandKind::Bezier => {
+                    for curve in command.points.chunks_exact(3) {
+                        if let Some(start) = from {
+                            shapes.push(Shape::Line(at(start), at(curve[0])));
+                        }
+                        shapes.push(Shape::Line(at(curve[1]), at(curve[2])));
+                        from = Some(curve[2]);
+                    }
+                }
+                // Splines show their control polygon
+                CommandKind::Spline | CommandKind::ExtendSpline => {
+                    for &point in &command.points {
+                        if let Some(start) = from {
+                            shapes.push(Shape::Line(at(start), at(point)));
+                        }
+                        from = Some(point);
+                    }
+                }
+                _ => from = command.points.last().copied().or(from),
+            }
+        }
+
+        shapes.extend(drawing.points().map(|point| Shape::Handle(at(point))));
+        shapes
+    }
+
+    /// What a click does on the preview of `text`.
+    pub fn hint(text: &str) -> &'static str {
+        if locate(text).is_some() {
+            "Click to add points, drag them to move them"
+        } else {
+            "Start a drawing to add points"
+        }
+    }
+
+    pub fn controls(&mut self, text: &str) -> Element<Message> {
+        let segment = self.segment();
+        let grid = self.grid();
+
+        let mut start_drawing = Button::new(
+            &mut self.start_drawing,
+            Text::new("Start drawing").size(TEXT_SIZE),
+        );
+        if locate(text).is_none() {
+            start_drawing = start_drawing.on_press(Message::StartDrawing);
+        }
+
+        Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.segments,
+                    ALL_SEGMENTS,
+                    Some(segment),
+                    Message::SegmentPicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                PickList::new(&mut self.grids, ALL_GRIDS, Some(grid), Message::GridPicked)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(start_drawing)
+            .push(
+                Button::new(&mut self.new_shape, Text::new("New shape").size(TEXT_SIZE))
+                    .on_press(Message::NewShape),
+            )
+            .push(
+                Button::new(
+                    &mut self.remove_point,
+                    Text::new("Remove point").size(TEXT_SIZE),
+                )
+                .on_press(Message::RemovePoint),
+            )
+            .into()
+    }
+}
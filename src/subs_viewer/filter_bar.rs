@@ -0,0 +1,216 @@
+use iced::pick_list;
+use iced::{text_input, Align, Element, Length, PickList, Row, Text, TextInput};
+
+use regex::{Regex, RegexBuilder};
+
+use super::time::Time;
+use super::SubsControlsValues;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentFilter {
+    All,
+    Dialogue,
+    Comments,
+}
+
+impl Default for CommentFilter {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl From<CommentFilter> for &'static str {
+    fn from(val: CommentFilter) -> Self {
+        match val {
+            CommentFilter::All => "All lines",
+            CommentFilter::Dialogue => "Dialogue",
+            CommentFilter::Comments => "Comments",
+        }
+    }
+}
+
+static ALL_COMMENT_FILTERS: &[CommentFilter] = &[
+    CommentFilter::All,
+    CommentFilter::Dialogue,
+    CommentFilter::Comments,
+];
+
+impl std::fmt::Display for CommentFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+const ROW_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+
+#[derive(Debug, Default)]
+pub struct FilterValues {
+    pub style: String,
+    pub actor: String,
+    pub comment: CommentFilter,
+    pub text: String,
+    pub from_time: String,
+    pub to_time: String,
+    text_regex: Option<Regex>,
+}
+
+impl FilterValues {
+    /// Returns whether the text filter holds a pattern that does not compile.
+    pub fn is_text_invalid(&self) -> bool {
+        !self.text.is_empty() && self.text_regex.is_none()
+    }
+
+    pub fn matches(&self, sub: &SubsControlsValues) -> bool {
+        let comment = match self.comment {
+            CommentFilter::All => true,
+            CommentFilter::Dialogue => !sub.is_comment,
+            CommentFilter::Comments => sub.is_comment,
+        };
+
+        comment
+            && Self::contains(&sub.style, &self.style)
+            && Self::contains(&sub.actor, &self.actor)
+            && self
+                .text_regex
+                .as_ref()
+                .map_or(true, |regex| regex.is_match(&sub.text))
+            && self.matches_time(sub)
+    }
+
+    // A line is kept when it intersects the [from, to] range, bounds that
+    // are empty or malformed are ignored
+    fn matches_time(&self, sub: &SubsControlsValues) -> bool {
+        let from = self.from_time.parse::<Time>().ok();
+        let to = self.to_time.parse::<Time>().ok();
+
+        let after_from = match (from, sub.end()) {
+            (Some(from), Some(end)) => end >= from,
+            _ => true,
+        };
+        let before_to = match (to, sub.start()) {
+            (Some(to), Some(start)) => start <= to,
+            _ => true,
+        };
+
+        after_from && before_to
+    }
+
+    #[inline(always)]
+    fn contains(value: &str, filter: &str) -> bool {
+        filter.is_empty() || value.to_lowercase().contains(&filter.to_lowercase())
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text_regex = if text.is_empty() {
+            None
+        } else {
+            RegexBuilder::new(&text).case_insensitive(true).build().ok()
+        };
+        self.text = text;
+    }
+}
+
+#[derive(Default)]
+pub struct FilterBar {
+    style: text_input::State,
+    actor: text_input::State,
+    comment: pick_list::State<CommentFilter>,
+    text: text_input::State,
+    from_time: text_input::State,
+    to_time: text_input::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    StyleChanged(String),
+    ActorChanged(String),
+    CommentPicked(CommentFilter),
+    TextChanged(String),
+    FromTimeChanged(String),
+    ToTimeChanged(String),
+}
+
+impl FilterBar {
+    pub fn update(&mut self, message: Message, filter: &mut FilterValues) {
+        match message {
+            Message::StyleChanged(style) => filter.style = style,
+            Message::ActorChanged(actor) => filter.actor = actor,
+            Message::CommentPicked(comment) => filter.comment = comment,
+            Message::TextChanged(text) => filter.set_text(text),
+            Message::FromTimeChanged(from_time) => filter.from_time = from_time,
+            Message::ToTimeChanged(to_time) => filter.to_time = to_time,
+        }
+    }
+
+    pub fn view(&mut self, values: &FilterValues) -> Element<Message> {
+        let row = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("Filter").size(TEXT_SIZE))
+            .push(
+                TextInput::new(
+                    &mut self.style,
+                    "Style",
+                    &values.style,
+                    Message::StyleChanged,
+                )
+                .size(TEXT_SIZE),
+            )
+            .push(
+                TextInput::new(
+                    &mut self.actor,
+                    "Actor",
+                    &values.actor,
+                    Message::ActorChanged,
+                )
+                .size(TEXT_SIZE),
+            )
+            .push(
+                PickList::new(
+                    &mut self.comment,
+                    ALL_COMMENT_FILTERS,
+                    Some(values.comment),
+                    Message::CommentPicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                TextInput::new(
+                    &mut self.text,
+                    "Text regex",
+                    &values.text,
+                    Message::TextChanged,
+                )
+                .size(TEXT_SIZE)
+                .width(Length::FillPortion(3)),
+            );
+
+        let row = if values.is_text_invalid() {
+            row.push(Text::new("Invalid regex").size(TEXT_SIZE))
+        } else {
+            row
+        };
+
+        row.push(
+            TextInput::new(
+                &mut self.from_time,
+                "From 00:00:00:00",
+                &values.from_time,
+                Message::FromTimeChanged,
+            )
+            .size(TEXT_SIZE),
+        )
+        .push(
+            TextInput::new(
+                &mut self.to_time,
+                "To 00:00:00:00",
+                &values.to_time,
+                Message::ToTimeChanged,
+            )
+            .size(TEXT_SIZE),
+        )
+        .into()
+    }
+}
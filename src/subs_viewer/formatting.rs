@@ -0,0 +1,435 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::styles::{Alignment, AssColor, AssStyle};
+use super::subs_controls::ColorTag;
+use super::tags::{Item, Line, NodeKind, Tag, TagKind};
+
+/// Formats turned on and off by the toolbar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    Bold,
+    Italic,
+    Underline,
+    StrikeOut,
+}
+
+impl From<Toggle> for &'static str {
+    fn from(val: Toggle) -> Self {
+        match val {
+            Toggle::Bold => "B",
+            Toggle::Italic => "I",
+            Toggle::Underline => "U",
+            Toggle::StrikeOut => "S",
+        }
+    }
+}
+
+pub static ALL_TOGGLES: &[Toggle] = &[
+    Toggle::Bold,
+    Toggle::Italic,
+    Toggle::Underline,
+    Toggle::StrikeOut,
+];
+
+impl fmt::Display for Toggle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+impl Toggle {
+    pub fn name(self) -> &'static str {
+        match self {
+            Toggle::Bold => "bold",
+            Toggle::Italic => "italic",
+            Toggle::Underline => "underline",
+            Toggle::StrikeOut => "strikeout",
+        }
+    }
+
+    fn tag(self, is_on: bool) -> String {
+        let name = match self {
+            Toggle::Bold => "b",
+            Toggle::Italic => "i",
+            Toggle::Underline => "u",
+            Toggle::StrikeOut => "s",
+        };
+        format!("\\{}{}", name, is_on as u8)
+    }
+}
+
+/// A format applied from the toolbar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    Toggle(Toggle),
+    FontName(String),
+    FontSize(f32),
+    Color(ColorTag, AssColor),
+    /// Applies to the whole line.
+    Alignment(Alignment),
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Toggle(toggle) => write!(f, "{}", toggle.name()),
+            Format::FontName(name) => write!(f, "font {}", name),
+            Format::FontSize(size) => write!(f, "font size {}", size),
+            Format::Color(tag, _) => write!(f, "{} color", tag),
+            Format::Alignment(alignment) => write!(f, "alignment {}", alignment.0),
+        }
+    }
+}
+
+impl Format {
+    // Whether `kind` sets what the format sets
+    fn is_set_by(&self, kind: &TagKind<'_>) -> bool {
+        match (self, kind) {
+            (Format::Toggle(Toggle::Bold), TagKind::Bold(_))
+            | (Format::Toggle(Toggle::Italic), TagKind::Italic(_))
+            | (Format::Toggle(Toggle::Underline), TagKind::Underline(_))
+            | (Format::Toggle(Toggle::StrikeOut), TagKind::StrikeOut(_))
+            | (Format::FontName(_), TagKind::FontName(_))
+            | (Format::FontSize(_), TagKind::FontSize(_))
+            | (Format::Alignment(_), TagKind::Alignment(_))
+            | (Format::Alignment(_), TagKind::LegacyAlignment(_)) => true,
+            (Format::Color(tag, _), TagKind::Color(index, _)) => tag.index() == *index,
+            _ => false,
+        }
+    }
+
+    // Tags giving the text the attributes of `attributes` for this format
+    fn tags(&self, attributes: &Attributes) -> String {
+        match self {
+            Format::Toggle(toggle) => toggle.tag(attributes.is_on(*toggle)),
+            Format::FontName(_) => format!("\\fn{}", attributes.font_name),
+            Format::FontSize(_) => format!("\\fs{}", attributes.font_size),
+            Format::Color(tag, _) => tag.tags(attributes.colors[usize::from(tag.index() - 1)]),
+            Format::Alignment(alignment) => format!("\\an{}", alignment.0),
+        }
+    }
+
+    // Attributes once the format is applied over `attributes`
+    fn applied(&self, attributes: &Attributes) -> Attributes {
+        let mut applied = attributes.clone();
+        match self {
+            Format::Toggle(toggle) => {
+                let is_on = !attributes.is_on(*toggle);
+                match toggle {
+                    Toggle::Bold => applied.bold = is_on,
+                    Toggle::Italic => applied.italic = is_on,
+                    Toggle::Underline => applied.underline = is_on,
+                    Toggle::StrikeOut => applied.strike_out = is_on,
+                }
+            }
+            Format::FontName(name) => applied.font_name = name.clone(),
+            Format::FontSize(size) => applied.font_size = *size,
+            Format::Color(tag, color) => applied.colors[usize::from(tag.index() - 1)] = *color,
+            Format::Alignment(_) => {}
+        }
+        applied
+    }
+}
+
+/// Text attributes the override tags can change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attributes {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike_out: bool,
+    pub font_name: String,
+    pub font_size: f32,
+    /// Primary, secondary, outline and shadow colors.
+    pub colors: [AssColor; 4],
+}
+
+impl Attributes {
+    pub fn from_style(style: &AssStyle) -> Self {
+        Self {
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+            strike_out: style.strike_out,
+            font_name: style.font_name.clone(),
+            font_size: style.font_size,
+            colors: [
+                style.primary_color,
+                style.secondary_color,
+                style.outline_color,
+                style.back_color,
+            ],
+        }
+    }
+
+    /// Attributes of the text at `offset` in a line of the style `style`.
+    /// `\r` with a style name looks it up in `styles`.
+    pub fn at(text: &str, offset: usize, style: &AssStyle, styles: &[AssStyle]) -> Self {
+        let mut attributes = Self::from_style(style);
+        let line = Line::parse(text);
+        let tags = line
+            .nodes
+            .iter()
+            .take_while(|node| node.span.end <= offset)
+            .flat_map(|node| match &node.kind {
+                NodeKind::Block(items) => items.as_slice(),
+                _ => &[],
+            })
+            .filter_map(|item| match item {
+                Item::Tag(tag) => Some(tag),
+                Item::Comment(_) => None,
+            });
+
+        for tag in tags {
+            match &tag.kind {
+                // Bold is a weight above the regular 400
+                TagKind::Bold(weight) => attributes.bold = *weight == 1 || *weight > 400,
+                TagKind::Italic(is_on) => attributes.italic = *is_on,
+                TagKind::Underline(is_on) => attributes.underline = *is_on,
+                TagKind::StrikeOut(is_on) => attributes.strike_out = *is_on,
+                TagKind::FontName(name) => attributes.font_name = (*name).to_owned(),
+                TagKind::FontSize(size) => attributes.font_size = *size,
+                TagKind::Color(index, color) if (1..=4).contains(index) => {
+                    let slot = &mut attributes.colors[usize::from(*index - 1)];
+                    *slot = AssColor {
+                        alpha: slot.alpha,
+                        ..*color
+                    };
+                }
+                TagKind::Alpha(index, alpha) => {
+                    for (slot, color) in attributes.colors.iter_mut().enumerate() {
+                        if index.map_or(true, |index| usize::from(index) == slot + 1) {
+                            color.alpha = *alpha;
+                        }
+                    }
+                }
+                TagKind::Reset(name) => {
+                    let reset = name
+                        .and_then(|name| styles.iter().find(|style| style.name == name))
+                        .unwrap_or(style);
+                    attributes = Self::from_style(reset);
+                }
+                _ => {}
+            }
+        }
+
+        attributes
+    }
+
+    pub fn is_on(&self, toggle: Toggle) -> bool {
+        match toggle {
+            Toggle::Bold => self.bold,
+            Toggle::Italic => self.italic,
+            Toggle::Underline => self.underline,
+            Toggle::StrikeOut => self.strike_out,
+        }
+    }
+}
+
+/// Alignment set by the line tags, the first `\an` wins.
+pub fn line_alignment(text: &str) -> Option<Alignment> {
+    Line::parse(text)
+        .nodes
+        .iter()
+        .filter_map(|node| match &node.kind {
+            NodeKind::Block(items) => Some(items),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|item| match item {
+            Item::Tag(Tag {
+                kind: TagKind::Alignment(alignment),
+                ..
+            }) => Some(Alignment(*alignment)),
+            _ => None,
+        })
+}
+
+/// Applies `format` to `selection` of `text`, or at the cursor when the
+/// selection is empty. Tags are inserted before the selection and the
+/// previous attributes are restored after it, tags of the same kind inside
+/// the selection are removed. Returns the new text and selection.
+pub fn apply(
+    text: &str,
+    selection: Range<usize>,
+    format: &Format,
+    style: &AssStyle,
+    styles: &[AssStyle],
+) -> (String, Range<usize>) {
+    let line = Line::parse(text);
+    let mut edits = Edits::default();
+
+    if let Format::Alignment(_) = format {
+        let attributes = Attributes::from_style(style);
+        let target = insert_tags(&line, 0, &format.tags(&attributes), false, &mut edits);
+        let kept: Vec<_> = target.into_iter().collect();
+        remove_tags(&line, 0..text.len(), format, &kept, &mut edits);
+        let selection = edits.map(selection.start, true)..edits.map(selection.end, true);
+        return (edits.apply(text), selection);
+    }
+
+    // The blocks starting the selection apply to its text
+    let first = snap(&line, selection.start);
+    let start = line
+        .nodes
+        .iter()
+        .filter(|node| node.span.start >= first && matches!(node.kind, NodeKind::Block(_)))
+        .try_fold(first, |start, node| {
+            if node.span.start == start {
+                Ok(node.span.end)
+            } else {
+                Err(start)
+            }
+        })
+        .unwrap_or_else(|start| start);
+    let end = snap(&line, selection.end).max(first);
+    let before = Attributes::at(text, start, style, styles);
+    let applied = format.applied(&before);
+
+    if start >= end {
+        insert_tags(&line, start, &format.tags(&applied), true, &mut edits);
+        let cursor = edits.map(start, true);
+        return (edits.apply(text), cursor..cursor);
+    }
+
+    // Read before anything is removed from the selection
+    let after = Attributes::at(text, end, style, styles);
+    let restore = format.tags(&after);
+    let end_target = if end < text.len() && restore != format.tags(&applied) {
+        insert_tags(&line, end, &restore, false, &mut edits)
+    } else {
+        None
+    };
+    let start_target = insert_tags(&line, start, &format.tags(&applied), true, &mut edits);
+
+    let kept: Vec<_> = start_target.into_iter().chain(end_target).collect();
+    remove_tags(&line, first..end, format, &kept, &mut edits);
+
+    let selection = edits.map(first, true)..edits.map(end, false);
+    (edits.apply(text), selection)
+}
+
+// Tags are never inserted inside a tag or a drawing
+fn snap(line: &Line<'_>, offset: usize) -> usize {
+    line.node_at(offset)
+        .filter(|node| node.kind != NodeKind::Text)
+        .map_or(offset, |node| node.span.end)
+}
+
+// Inserts `tags` at `at`, in the block ending or starting there when there
+// is one, the one ending there first when `prefer_before`. Returns the span
+// of the block inserted into.
+fn insert_tags(
+    line: &Line<'_>,
+    at: usize,
+    tags: &str,
+    prefer_before: bool,
+    edits: &mut Edits,
+) -> Option<Range<usize>> {
+    let block = |is_before: bool| {
+        line.nodes.iter().find(|node| {
+            let edge = if is_before {
+                node.span.end
+            } else {
+                node.span.start
+            };
+            edge == at && matches!(node.kind, NodeKind::Block(_))
+        })
+    };
+
+    let target = match (block(true), block(false)) {
+        (Some(before), Some(_)) if prefer_before => Some((before, true)),
+        (_, Some(after)) => Some((after, false)),
+        (Some(before), None) => Some((before, true)),
+        (None, None) => None,
+    };
+
+    match target {
+        Some((block, true)) => {
+            let end = block.span.end - 1;
+            edits.push(end..end, tags.to_owned());
+            Some(block.span.clone())
+        }
+        Some((block, false)) => {
+            let start = block.span.start + 1;
+            edits.push(start..start, tags.to_owned());
+            Some(block.span.clone())
+        }
+        None => {
+            edits.push(at..at, format!("{{{}}}", tags));
+            None
+        }
+    }
+}
+
+// Removes the tags set by `format` from the blocks inside `range`. Blocks
+// left empty go too, but for the `kept` ones which get new tags.
+fn remove_tags(
+    line: &Line<'_>,
+    range: Range<usize>,
+    format: &Format,
+    kept: &[Range<usize>],
+    edits: &mut Edits,
+) {
+    let blocks = line.nodes.iter().filter_map(|node| match &node.kind {
+        NodeKind::Block(items) if range.start <= node.span.start && node.span.end <= range.end => {
+            Some((&node.span, items))
+        }
+        _ => None,
+    });
+
+    for (span, items) in blocks {
+        let removed: Vec<&Range<usize>> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Tag(tag) if format.is_set_by(&tag.kind) => Some(&tag.span),
+                _ => None,
+            })
+            .collect();
+
+        if removed.len() == items.len() && !kept.contains(span) {
+            edits.push(span.clone(), String::new());
+        } else {
+            for tag in removed {
+                edits.push(tag.clone(), String::new());
+            }
+        }
+    }
+}
+
+// Replacements of the line text, they never overlap
+#[derive(Default)]
+struct Edits(Vec<(Range<usize>, String)>);
+
+impl Edits {
+    fn push(&mut self, range: Range<usize>, with: String) {
+        self.0.push((range, with));
+    }
+
+    fn apply(mut self, text: &str) -> String {
+        // From the end, a removal goes before an insertion at its start
+        self.0.sort_by_key(|(range, _)| {
+            (std::cmp::Reverse(range.start), std::cmp::Reverse(range.end))
+        });
+
+        let mut text = text.to_owned();
+        for (range, with) in &self.0 {
+            text.replace_range(range.clone(), with);
+        }
+        text
+    }
+
+    // Where `offset` ends up, `after` puts it after what is inserted there
+    fn map(&self, offset: usize, after: bool) -> usize {
+        self.0
+            .iter()
+            .filter(|(range, _)| {
+                range.end < offset || (range.end == offset && (after || !range.is_empty()))
+            })
+            .fold(offset, |offset, (range, with)| {
+                offset + with.len() - range.len()
+            })
+    }
+}
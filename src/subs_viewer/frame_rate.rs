@@ -0,0 +1,60 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::time::Time;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("Frame rate must be a positive number or a fraction like 24000/1001")]
+    Invalid,
+}
+
+/// A constant frame rate, in frames per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRate(f64);
+
+impl FrameRate {
+    /// First frame shown at or after `time`. A line starting at `time` first
+    /// shows on it, a line ending at `time` last shows on the frame before.
+    pub fn frame_at(self, time: Time) -> u64 {
+        // Times of exact frame starts may be off by a rounding error
+        (time.as_millis() as f64 * self.0 / 1000.0 - 1e-6)
+            .ceil()
+            .max(0.0) as u64
+    }
+
+    /// Time of the centisecond starting `frame`, the one lines use.
+    pub fn time_of(self, frame: u64) -> Time {
+        let centiseconds = (frame as f64 * 100.0 / self.0).floor() as u64;
+        Time::from_millis(centiseconds * 10)
+    }
+}
+
+impl FromStr for FrameRate {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let number = |value: &str| value.trim().parse::<f64>().map_err(|_| Error::Invalid);
+        let mut parts = value.splitn(2, '/');
+        let numerator = number(parts.next().unwrap_or_default())?;
+        let rate = match parts.next() {
+            Some(denominator) => numerator / number(denominator)?,
+            None => numerator,
+        };
+
+        if rate.is_finite() && rate > 0.0 {
+            Ok(Self(rate))
+        } else {
+            Err(Error::Invalid)
+        }
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rounded = (self.0 * 1000.0).round() / 1000.0;
+        write!(f, "{}", rounded)
+    }
+}
@@ -0,0 +1,321 @@
+use iced_graphics::{
+    backend::Backend,
+    canvas::{Frame, Path, Stroke},
+    Primitive, Renderer,
+};
+use iced_native::{mouse, Color, Point, Rectangle, Size, Vector};
+
+pub use handles::State;
+
+pub type Handles<'a, Message, Backend> = handles::Handles<'a, Message, Renderer<Backend>>;
+
+// Size of the square of a handle, in pixels
+const HANDLE_SIZE: f32 = 8.0;
+const GUIDE_WIDTH: f32 = 1.5;
+
+/// A guide drawn over the content. Points are fractions of its size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// A point that can be grabbed.
+    Handle(Point),
+    Line(Point, Point),
+    /// A circle, its radius is a fraction of the width.
+    Circle(Point, f32),
+    /// A closed polygon.
+    Polygon(Vec<Point>),
+}
+
+/// A drag on the content, positions are fractions of its size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Drag {
+    Pressed(Point),
+    Moved(Point),
+    Released,
+}
+
+impl<B> handles::Renderer for Renderer<B>
+where
+    B: Backend,
+{
+    type Style = Box<dyn StyleSheet>;
+
+    fn draw(
+        &mut self,
+        bounds: Rectangle,
+        cursor_position: Point,
+        content: Self::Output,
+        shapes: &[Shape],
+        style: &Self::Style,
+    ) -> Self::Output {
+        let (content, _) = content;
+        let styling = style.active();
+
+        let mut frame = Frame::new(bounds.size());
+        let at = |point: &Point| Point::new(point.x * bounds.width, point.y * bounds.height);
+        let guide = Stroke {
+            color: styling.guide_color,
+            width: GUIDE_WIDTH,
+            ..Stroke::default()
+        };
+
+        for shape in shapes {
+            match shape {
+                Shape::Handle(point) => {
+                    let center = at(point);
+                    let corner = center - Vector::new(HANDLE_SIZE / 2.0, HANDLE_SIZE / 2.0);
+                    let size = Size::new(HANDLE_SIZE, HANDLE_SIZE);
+                    frame.fill_rectangle(corner, size, styling.handle_color);
+                }
+                Shape::Line(from, to) => frame.stroke(&Path::line(at(from), at(to)), guide),
+                Shape::Circle(center, radius) => {
+                    frame.stroke(&Path::circle(at(center), radius * bounds.width), guide)
+                }
+                Shape::Polygon(points) => {
+                    let path = Path::new(|builder| {
+                        let mut points = points.iter();
+                        if let Some(first) = points.next() {
+                            builder.move_to(at(first));
+                            points.for_each(|point| builder.line_to(at(point)));
+                            builder.close();
+                        }
+                    });
+                    frame.stroke(&path, guide);
+                }
+            }
+        }
+
+        let guides = Primitive::Translate {
+            translation: Vector::new(bounds.x, bounds.y),
+            content: Box::new(frame.into_geometry().into_primitive()),
+        };
+
+        let interaction = if bounds.contains(cursor_position) {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        };
+
+        (
+            Primitive::Group {
+                primitives: vec![content, guides],
+            },
+            interaction,
+        )
+    }
+}
+
+mod handles {
+    use iced_native::{
+        event, layout, mouse, Clipboard, Element, Event, Hasher, Layout, Length, Point, Rectangle,
+        Widget,
+    };
+
+    use super::{Drag, Shape};
+
+    /// Whether a drag started on the content.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct State {
+        is_dragging: bool,
+    }
+
+    /// Draws `shapes` over `content` and reports the drags on it.
+    pub struct Handles<'a, Message, Renderer: self::Renderer> {
+        state: &'a mut State,
+        content: Element<'a, Message, Renderer>,
+        shapes: Vec<Shape>,
+        on_drag: Box<dyn Fn(Drag) -> Message + 'a>,
+        style: Renderer::Style,
+    }
+
+    impl<'a, Message, Renderer> Handles<'a, Message, Renderer>
+    where
+        Renderer: self::Renderer,
+    {
+        pub fn new<F>(
+            state: &'a mut State,
+            content: impl Into<Element<'a, Message, Renderer>>,
+            shapes: Vec<Shape>,
+            on_drag: F,
+        ) -> Self
+        where
+            F: 'a + Fn(Drag) -> Message,
+        {
+            Self {
+                state,
+                content: content.into(),
+                shapes,
+                on_drag: Box::new(on_drag),
+                style: Renderer::Style::default(),
+            }
+        }
+
+        pub fn style(mut self, style: impl Into<Renderer::Style>) -> Self {
+            self.style = style.into();
+            self
+        }
+    }
+
+    impl<'a, Message, Renderer> Widget<Message, Renderer> for Handles<'a, Message, Renderer>
+    where
+        Renderer: self::Renderer,
+    {
+        fn width(&self) -> Length {
+            self.content.width()
+        }
+
+        fn height(&self) -> Length {
+            self.content.height()
+        }
+
+        fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+            self.content.layout(renderer, limits)
+        }
+
+        fn on_event(
+            &mut self,
+            event: Event,
+            layout: Layout<'_>,
+            cursor_position: Point,
+            _renderer: &Renderer,
+            _clipboard: &mut dyn Clipboard,
+            messages: &mut Vec<Message>,
+        ) -> event::Status {
+            let bounds = layout.bounds();
+            let position = |point: Point| {
+                Point::new(
+                    (point.x - bounds.x) / bounds.width,
+                    (point.y - bounds.y) / bounds.height,
+                )
+            };
+
+            let drag = match event {
+                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                    if bounds.contains(cursor_position) =>
+                {
+                    self.state.is_dragging = true;
+                    Drag::Pressed(position(cursor_position))
+                }
+                Event::Mouse(mouse::Event::CursorMoved { position: moved })
+                    if self.state.is_dragging =>
+                {
+                    Drag::Moved(position(moved))
+                }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                    if self.state.is_dragging =>
+                {
+                    self.state.is_dragging = false;
+                    Drag::Released
+                }
+                _ => return event::Status::Ignored,
+            };
+
+            messages.push((self.on_drag)(drag));
+            event::Status::Captured
+        }
+
+        fn draw(
+            &self,
+            renderer: &mut Renderer,
+            defaults: &Renderer::Defaults,
+            layout: Layout<'_>,
+            cursor_position: Point,
+            viewport: &Rectangle,
+        ) -> Renderer::Output {
+            let content = self
+                .content
+                .draw(renderer, defaults, layout, cursor_position, viewport);
+
+            self::Renderer::draw(
+                renderer,
+                layout.bounds(),
+                cursor_position,
+                content,
+                &self.shapes,
+                &self.style,
+            )
+        }
+
+        fn hash_layout(&self, state: &mut Hasher) {
+            use std::hash::Hash;
+            struct Marker;
+            std::any::TypeId::of::<Marker>().hash(state);
+
+            self.content.hash_layout(state);
+        }
+    }
+
+    pub trait Renderer: iced_native::Renderer + Sized {
+        type Style: Default;
+
+        fn draw(
+            &mut self,
+            bounds: Rectangle,
+            cursor_position: Point,
+            content: Self::Output,
+            shapes: &[Shape],
+            style: &Self::Style,
+        ) -> Self::Output;
+    }
+
+    impl<'a, Message, Renderer> From<Handles<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+    where
+        Message: 'a,
+        Renderer: 'a + self::Renderer,
+    {
+        fn from(handles: Handles<'a, Message, Renderer>) -> Self {
+            Element::new(handles)
+        }
+    }
+
+    impl Renderer for iced_native::renderer::Null {
+        type Style = ();
+
+        fn draw(
+            &mut self,
+            _: Rectangle,
+            _: Point,
+            _: Self::Output,
+            _: &[Shape],
+            _: &Self::Style,
+        ) -> Self::Output {
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Style {
+    pub handle_color: Color,
+
+    pub guide_color: Color,
+}
+
+pub trait StyleSheet {
+    fn active(&self) -> Style;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Default;
+
+impl StyleSheet for Default {
+    fn active(&self) -> Style {
+        Style {
+            handle_color: Color::from_rgb(1.0, 0.8, 0.0),
+            guide_color: Color::from_rgba(1.0, 1.0, 1.0, 0.8),
+        }
+    }
+}
+
+impl std::default::Default for Box<dyn StyleSheet> {
+    fn default() -> Self {
+        Box::new(Default)
+    }
+}
+
+impl<T> From<T> for Box<dyn StyleSheet>
+where
+    T: 'static + StyleSheet,
+{
+    fn from(style: T) -> Self {
+        Box::new(style)
+    }
+}
@@ -0,0 +1,416 @@
+use std::ops::Range;
+
+use iced::{button, scrollable, Button, Column, Element, Length, Scrollable, Text};
+
+use super::styles::AssStyle;
+use super::{SubsControlsValues, SubsViewer};
+
+// Oldest entries are dropped past this limit
+const MAX_ENTRIES: usize = 500;
+
+const TEXT_SIZE: u16 = 14;
+const ENTRY_PADDING: u16 = 2;
+
+// Selected lines and active line
+type Selection = (Range<usize>, usize);
+
+/// Styles changed like lines are: `removed` styles starting at `start` are
+/// replaced by `inserted` ones.
+#[derive(Debug)]
+struct StyleChange {
+    start: usize,
+    removed: Vec<AssStyle>,
+    inserted: Vec<AssStyle>,
+}
+
+impl StyleChange {
+    // The styles differing between `before` and `after`, if any
+    fn between(before: &[AssStyle], after: &[AssStyle]) -> Option<Self> {
+        let start = before
+            .iter()
+            .zip(after)
+            .take_while(|(before, after)| before == after)
+            .count();
+        if start == before.len() && start == after.len() {
+            return None;
+        }
+
+        let end = before[start..]
+            .iter()
+            .rev()
+            .zip(after[start..].iter().rev())
+            .take_while(|(before, after)| before == after)
+            .count();
+        Some(Self {
+            start,
+            removed: before[start..before.len() - end].to_vec(),
+            inserted: after[start..after.len() - end].to_vec(),
+        })
+    }
+}
+
+/// A reversible change of the document: `removed` lines starting at `start`
+/// are replaced by `inserted` ones.
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    start: usize,
+    removed: Vec<SubsControlsValues>,
+    inserted: Vec<SubsControlsValues>,
+    selection_before: Selection,
+    selection_after: Selection,
+    // Styles the change touched, if any
+    styles: Option<StyleChange>,
+    // Row and field being typed in, consecutive keystrokes are merged
+    typing: Option<(usize, &'static str)>,
+}
+
+impl Entry {
+    fn apply(&self, lines: &mut Vec<SubsControlsValues>, styles: &mut Vec<AssStyle>) -> Selection {
+        let range = self.start..self.start + self.removed.len();
+        lines.splice(range, self.inserted.iter().cloned());
+        if let Some(change) = &self.styles {
+            let range = change.start..change.start + change.removed.len();
+            styles.splice(range, change.inserted.iter().cloned());
+        }
+        self.selection_after.clone()
+    }
+
+    fn revert(&self, lines: &mut Vec<SubsControlsValues>, styles: &mut Vec<AssStyle>) -> Selection {
+        let range = self.start..self.start + self.inserted.len();
+        lines.splice(range, self.removed.iter().cloned());
+        if let Some(change) = &self.styles {
+            let range = change.start..change.start + change.inserted.len();
+            styles.splice(range, change.removed.iter().cloned());
+        }
+        self.selection_before.clone()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    // Number of entries currently applied to the document
+    position: usize,
+    // Typing in the same field is no longer merged into the last entry
+    sealed: bool,
+}
+
+impl History {
+    fn push(&mut self, entry: Entry) {
+        self.entries.truncate(self.position);
+
+        if let Some(last) = self.entries.last_mut() {
+            let same_styles = match (&last.styles, &entry.styles) {
+                (Some(last), Some(change)) => {
+                    last.start == change.start && last.inserted.len() == change.removed.len()
+                }
+                (last, change) => last.is_none() && change.is_none(),
+            };
+            let same_typing = !self.sealed
+                && entry.typing.is_some()
+                && last.typing == entry.typing
+                && last.start == entry.start
+                && last.inserted.len() == entry.removed.len()
+                && same_styles;
+            if same_typing {
+                last.inserted = entry.inserted;
+                last.selection_after = entry.selection_after;
+                if let (Some(last), Some(change)) = (&mut last.styles, entry.styles) {
+                    last.inserted = change.inserted;
+                }
+                return;
+            }
+        }
+
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len();
+        self.sealed = false;
+    }
+
+    /// Stops merging keystrokes into the last entry.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.entries.len()
+    }
+
+    fn undo(
+        &mut self,
+        lines: &mut Vec<SubsControlsValues>,
+        styles: &mut Vec<AssStyle>,
+    ) -> Option<Selection> {
+        if !self.can_undo() {
+            return None;
+        }
+
+        self.position -= 1;
+        self.sealed = true;
+        Some(self.entries[self.position].revert(lines, styles))
+    }
+
+    fn redo(
+        &mut self,
+        lines: &mut Vec<SubsControlsValues>,
+        styles: &mut Vec<AssStyle>,
+    ) -> Option<Selection> {
+        if !self.can_redo() {
+            return None;
+        }
+
+        self.position += 1;
+        self.sealed = true;
+        Some(self.entries[self.position - 1].apply(lines, styles))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Undo,
+    Redo,
+    // Undoes or redoes until `position` entries are applied
+    Jump(usize),
+}
+
+impl SubsViewer {
+    /// Runs `edit`, which may only change the `affected` lines and the
+    /// styles, and records the change in the history under `name`.
+    pub(super) fn record<F>(
+        &mut self,
+        name: String,
+        typing: Option<&'static str>,
+        affected: Range<usize>,
+        edit: F,
+    ) where
+        F: FnOnce(&mut Self),
+    {
+        let old_len = self.controls_values.len();
+        let removed = self.controls_values[affected.clone()].to_vec();
+        let selection_before = (self.focused_subs.clone(), self.active_sub);
+        let styles_before = self.styles.clone();
+
+        edit(self);
+
+        let end = affected.end + self.controls_values.len() - old_len;
+        let inserted = self.controls_values[affected.start..end].to_vec();
+        let styles = StyleChange::between(&styles_before, &self.styles);
+        if removed == inserted && styles.is_none() {
+            return;
+        }
+
+        self.history.push(Entry {
+            name,
+            start: affected.start,
+            removed,
+            inserted,
+            selection_before,
+            selection_after: (self.focused_subs.clone(), self.active_sub),
+            styles,
+            typing: typing.map(|field| (affected.start, field)),
+        });
+    }
+
+    pub(super) fn update_history(&mut self, message: Message) {
+        let selection = match message {
+            Message::Undo => self
+                .history
+                .undo(&mut self.controls_values, &mut self.styles),
+            Message::Redo => self
+                .history
+                .redo(&mut self.controls_values, &mut self.styles),
+            Message::Jump(position) => {
+                let mut selection = None;
+                while self.history.position > position {
+                    selection = self
+                        .history
+                        .undo(&mut self.controls_values, &mut self.styles);
+                }
+                while self.history.position < position {
+                    selection = self
+                        .history
+                        .redo(&mut self.controls_values, &mut self.styles);
+                }
+                selection
+            }
+        };
+
+        if let Some((focused_subs, active_sub)) = selection {
+            self.focused_subs = focused_subs;
+            self.active_sub = active_sub;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HistoryPanel {
+    scroll: scrollable::State,
+    entries: Vec<button::State>,
+}
+
+impl HistoryPanel {
+    pub fn view(&mut self, history: &History) -> Element<Message> {
+        // One more button to go back to the unmodified document
+        self.entries
+            .resize_with(history.entries.len() + 1, button::State::default);
+
+        let names = std::iter::once("Original document")
+            .chain(history.entries.iter().map(|entry| entry.name.as_str()));
+
+        let column = self.entries.iter_mut().zip(names).enumerate().fold(
+            Column::new().width(Length::Fill),
+            |column, (position, (state, name))| {
+                let label = if position == history.position {
+                    format!("> {}", name)
+                } else {
+                    name.to_owned()
+                };
+                column.push(
+                    Button::new(state, Text::new(label).size(TEXT_SIZE))
+                        .padding(ENTRY_PADDING)
+                        .width(Length::Fill)
+                        .on_press(Message::Jump(position)),
+                )
+            },
+        );
+
+        Scrollable::new(&mut self.scroll)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .push(Text::new("History"))
+            .push(column)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sets the text of `row`, as typed in the text field
+    fn type_text(viewer: &mut SubsViewer, row: usize, text: &str) {
+        let text = text.to_owned();
+        viewer.record(
+            "Edit text".to_owned(),
+            Some("text"),
+            row..row + 1,
+            |viewer| viewer.controls_values[row].text = text,
+        );
+    }
+
+    #[test]
+    fn keystrokes_are_merged_until_sealed() {
+        let mut viewer = SubsViewer::new();
+        type_text(&mut viewer, 0, "a");
+        type_text(&mut viewer, 0, "ab");
+        assert_eq!(viewer.history.entries.len(), 1);
+
+        // Another field or another line starts a new entry
+        type_text(&mut viewer, 1, "c");
+        assert_eq!(viewer.history.entries.len(), 2);
+
+        viewer.history.seal();
+        type_text(&mut viewer, 1, "cd");
+        assert_eq!(viewer.history.entries.len(), 3);
+
+        viewer.update_history(Message::Undo);
+        viewer.update_history(Message::Undo);
+        assert_eq!(viewer.controls_values[1].text, "");
+        assert_eq!(viewer.controls_values[0].text, "ab");
+        viewer.update_history(Message::Undo);
+        assert_eq!(viewer.controls_values[0].text, "");
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped() {
+        let mut viewer = SubsViewer::new();
+        for edit in 0..MAX_ENTRIES + 10 {
+            viewer.history.seal();
+            type_text(&mut viewer, 0, &edit.to_string());
+        }
+        assert_eq!(viewer.history.entries.len(), MAX_ENTRIES);
+        assert_eq!(viewer.history.position, MAX_ENTRIES);
+
+        viewer.update_history(Message::Jump(0));
+        assert_eq!(viewer.controls_values[0].text, "9");
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_selection() {
+        let mut viewer = SubsViewer::new();
+        viewer.select(0);
+        viewer.record("Edit text".to_owned(), None, 2..3, |viewer| {
+            viewer.controls_values[2].text = "changed".to_owned();
+            viewer.focused_subs = 1..3;
+            viewer.active_sub = 2;
+        });
+
+        viewer.update_history(Message::Undo);
+        assert_eq!(viewer.controls_values[2].text, "");
+        assert_eq!((viewer.focused_subs.clone(), viewer.active_sub), (0..1, 0));
+        assert!(!viewer.history.can_undo());
+
+        viewer.update_history(Message::Redo);
+        assert_eq!(viewer.controls_values[2].text, "changed");
+        assert_eq!((viewer.focused_subs.clone(), viewer.active_sub), (1..3, 2));
+        assert!(!viewer.history.can_redo());
+    }
+
+    #[test]
+    fn a_new_edit_clears_the_redo() {
+        let mut viewer = SubsViewer::new();
+        type_text(&mut viewer, 0, "a");
+        viewer.history.seal();
+        type_text(&mut viewer, 0, "ab");
+        viewer.update_history(Message::Undo);
+        assert!(viewer.history.can_redo());
+
+        type_text(&mut viewer, 1, "c");
+        assert!(!viewer.history.can_redo());
+        assert_eq!(viewer.history.entries.len(), 2);
+        assert_eq!(viewer.history.entries[1].name, "Edit text");
+        assert_eq!(viewer.controls_values[0].text, "a");
+    }
+
+    #[test]
+    fn only_the_changed_styles_are_kept() {
+        let mut viewer = SubsViewer::new();
+        viewer.styles = vec![AssStyle::new("A"), AssStyle::new("B"), AssStyle::new("C")];
+        viewer.record("Edit style B".to_owned(), None, 0..0, |viewer| {
+            viewer.styles[1].font_size = 40.0
+        });
+
+        let change = viewer.history.entries[0].styles.as_ref().unwrap();
+        assert_eq!(change.start, 1);
+        assert_eq!(change.removed, vec![AssStyle::new("B")]);
+        assert_eq!(change.inserted.len(), 1);
+
+        viewer.update_history(Message::Undo);
+        assert_eq!(viewer.styles[1], AssStyle::new("B"));
+        viewer.update_history(Message::Redo);
+        assert_eq!(viewer.styles[1].font_size, 40.0);
+    }
+
+    #[test]
+    fn added_and_removed_styles_are_undone() {
+        let before = vec![AssStyle::new("A"), AssStyle::new("C")];
+        let after = vec![AssStyle::new("A"), AssStyle::new("B"), AssStyle::new("C")];
+        let change = StyleChange::between(&before, &after).unwrap();
+        assert_eq!((change.start, change.removed.len()), (1, 0));
+        assert_eq!(change.inserted, vec![AssStyle::new("B")]);
+
+        let change = StyleChange::between(&after, &before).unwrap();
+        assert_eq!(change.removed, vec![AssStyle::new("B")]);
+        assert!(change.inserted.is_empty());
+
+        assert!(StyleChange::between(&before, &before).is_none());
+    }
+}
@@ -0,0 +1,368 @@
+use std::iter;
+
+use iced::{button, Align, Button, Column, Container, Element, Length, Row, Text};
+
+use super::karaoke::{self, Syllables};
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const UNIT_PADDING: u16 = 4;
+const LABEL_WIDTH: u16 = 90;
+
+// Syllables timed together and the characters they time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Group {
+    syllables: usize,
+    chars: usize,
+}
+
+// Romaji and translations are grouped by words, kanji one by one
+fn is_word(char: &str) -> bool {
+    char.chars().all(|c| c.is_ascii_alphanumeric() || c == '\'')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Text of the destination line, timed by the source line.
+    Timed(String),
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    FewerSyllables,
+    MoreSyllables,
+    FewerCharacters,
+    MoreCharacters,
+    Accept,
+    /// Accepts the proposed syllables without characters, keeping their time
+    /// as a gap.
+    Skip,
+    Undo,
+    Close,
+}
+
+/// Copies the karaoke timing of a line to another one, usually its kanji
+/// or its translation, by grouping its syllables with characters.
+#[derive(Default)]
+pub struct KanjiTimer {
+    fewer_syllables: button::State,
+    more_syllables: button::State,
+    fewer_chars: button::State,
+    more_chars: button::State,
+    accept: button::State,
+    skip: button::State,
+    undo: button::State,
+    close: button::State,
+    // Source and destination texts the groups were made for
+    lines: (String, String),
+    groups: Vec<Group>,
+    proposal: Group,
+}
+
+impl KanjiTimer {
+    // Starts over when either line changed
+    fn follow(&mut self, source: &str, destination: &str, syllable_count: usize, chars: &[String]) {
+        if self.lines.0 != source || self.lines.1 != destination {
+            self.lines = (source.to_owned(), destination.to_owned());
+            self.groups.clear();
+            self.propose(syllable_count, chars);
+        }
+    }
+
+    fn used(&self) -> Group {
+        self.groups
+            .iter()
+            .fold(Group::default(), |used, group| Group {
+                syllables: used.syllables + group.syllables,
+                chars: used.chars + group.chars,
+            })
+    }
+
+    // A syllable with the next word or character, and the spaces after it
+    fn propose(&mut self, syllable_count: usize, chars: &[String]) {
+        let used = self.used();
+        let rest = &chars[used.chars..];
+        let mut count = match rest.first() {
+            Some(first) if is_word(first) => rest.iter().take_while(|c| is_word(c)).count(),
+            Some(_) => 1,
+            None => 0,
+        };
+        count += rest[count..]
+            .iter()
+            .take_while(|c| c.trim().is_empty())
+            .count();
+
+        self.proposal = Group {
+            syllables: (syllable_count - used.syllables).min(1),
+            chars: count,
+        };
+    }
+
+    /// `lines` are the source and destination lines, if selected.
+    pub fn update(
+        &mut self,
+        message: Message,
+        lines: Option<(&SubsControlsValues, &SubsControlsValues)>,
+    ) -> Option<Event> {
+        if let Message::Close = message {
+            return Some(Event::Closed);
+        }
+        let (source, destination) = lines?;
+        let syllables = Syllables::parse(&source.text, karaoke::line_duration(source));
+        let syllable_count = syllables.syllables().len();
+        let chars = karaoke::characters(&destination.text);
+        self.follow(&source.text, &destination.text, syllable_count, &chars);
+
+        let used = self.used();
+        let left = Group {
+            syllables: syllable_count - used.syllables,
+            chars: chars.len() - used.chars,
+        };
+        let proposal = &mut self.proposal;
+
+        match message {
+            Message::FewerSyllables => proposal.syllables = proposal.syllables.saturating_sub(1),
+            Message::MoreSyllables => {
+                proposal.syllables = left.syllables.min(proposal.syllables + 1)
+            }
+            Message::FewerCharacters => proposal.chars = proposal.chars.saturating_sub(1),
+            Message::MoreCharacters => proposal.chars = left.chars.min(proposal.chars + 1),
+            Message::Accept | Message::Skip => {
+                let group = match message {
+                    Message::Skip => Group {
+                        chars: 0,
+                        ..*proposal
+                    },
+                    _ => *proposal,
+                };
+                if group == Group::default() {
+                    return None;
+                }
+                self.groups.push(group);
+
+                let used = self.used();
+                if used.syllables == syllable_count && used.chars == chars.len() {
+                    let mut durations = syllables.syllables().iter().map(|s| s.duration);
+                    let groups: Vec<(u32, usize)> = self
+                        .groups
+                        .iter()
+                        .map(|group| (durations.by_ref().take(group.syllables).sum(), group.chars))
+                        .collect();
+                    let text = karaoke::timed(&destination.text, syllables.effect(), &groups);
+
+                    // The timed line can be timed again from scratch
+                    self.lines.1 = text.clone();
+                    self.groups.clear();
+                    self.propose(syllable_count, &chars);
+                    return Some(Event::Timed(text));
+                }
+                self.propose(syllable_count, &chars);
+            }
+            Message::Undo => {
+                if let Some(group) = self.groups.pop() {
+                    self.proposal = group;
+                }
+            }
+            Message::Close => {}
+        }
+        None
+    }
+
+    pub fn view(
+        &mut self,
+        lines: Option<(&SubsControlsValues, &SubsControlsValues)>,
+    ) -> Element<Message> {
+        let (source, destination) = match lines {
+            Some(lines) => lines,
+            None => {
+                return Column::new()
+                    .spacing(COLUMN_SPACING)
+                    .push(
+                        Text::new("Select the timed line, then the line to time below it")
+                            .size(TEXT_SIZE),
+                    )
+                    .push(
+                        Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                            .on_press(Message::Close),
+                    )
+                    .into()
+            }
+        };
+
+        let syllables = Syllables::parse(&source.text, karaoke::line_duration(source));
+        let syllable_count = syllables.syllables().len();
+        let chars = karaoke::characters(&destination.text);
+        self.follow(&source.text, &destination.text, syllable_count, &chars);
+
+        let syllable_units = units(
+            self.groups.iter().map(|group| group.syllables),
+            self.proposal.syllables,
+            syllable_count,
+        );
+        let syllable_row = syllables.syllables().iter().zip(syllable_units).fold(
+            Row::new().spacing(ROW_SPACING / 2),
+            |row, (syllable, unit)| {
+                row.push(
+                    Container::new(
+                        Column::new()
+                            .align_items(Align::Center)
+                            .push(Text::new(karaoke::visible(&syllable.text)).size(TEXT_SIZE))
+                            .push(Text::new(syllable.duration.to_string()).size(TEXT_SIZE)),
+                    )
+                    .padding(UNIT_PADDING)
+                    .style(unit),
+                )
+            },
+        );
+
+        let char_units = units(
+            self.groups.iter().map(|group| group.chars),
+            self.proposal.chars,
+            chars.len(),
+        );
+        let char_row = chars.iter().zip(char_units).fold(
+            Row::new().spacing(ROW_SPACING / 2),
+            |row, (char, unit)| {
+                // Spaces would not show
+                let label = if char.trim().is_empty() { "␣" } else { char };
+                row.push(
+                    Container::new(Text::new(label).size(TEXT_SIZE))
+                        .padding(UNIT_PADDING)
+                        .style(unit),
+                )
+            },
+        );
+
+        let toolbar = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Button::new(&mut self.accept, Text::new("Accept").size(TEXT_SIZE))
+                    .on_press(Message::Accept),
+            )
+            .push(
+                Button::new(&mut self.skip, Text::new("Skip").size(TEXT_SIZE))
+                    .on_press(Message::Skip),
+            )
+            .push(
+                Button::new(&mut self.undo, Text::new("Undo").size(TEXT_SIZE))
+                    .on_press(Message::Undo),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            );
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(
+                Text::new("Copy the syllable timing of the first selected line to the second")
+                    .size(TEXT_SIZE),
+            )
+            .push(toolbar)
+            .push(
+                resizer(
+                    &mut self.fewer_syllables,
+                    &mut self.more_syllables,
+                    "Syllables",
+                    Message::FewerSyllables,
+                    Message::MoreSyllables,
+                )
+                .push(syllable_row),
+            )
+            .push(
+                resizer(
+                    &mut self.fewer_chars,
+                    &mut self.more_chars,
+                    "Characters",
+                    Message::FewerCharacters,
+                    Message::MoreCharacters,
+                )
+                .push(char_row),
+            )
+            .push(
+                Text::new(
+                    "Enter accepts the highlighted group, Space skips its syllables, \
+                     Backspace undoes. Left and Right resize the characters, \
+                     with Shift the syllables.",
+                )
+                .size(TEXT_SIZE),
+            )
+            .into()
+    }
+}
+
+// Label and buttons resizing the proposed group
+fn resizer<'a>(
+    fewer: &'a mut button::State,
+    more: &'a mut button::State,
+    label: &str,
+    on_fewer: Message,
+    on_more: Message,
+) -> Row<'a, Message> {
+    Row::new()
+        .spacing(ROW_SPACING)
+        .align_items(Align::Center)
+        .push(
+            Text::new(label)
+                .size(TEXT_SIZE)
+                .width(Length::Units(LABEL_WIDTH)),
+        )
+        .push(Button::new(fewer, Text::new("-").size(TEXT_SIZE)).on_press(on_fewer))
+        .push(Button::new(more, Text::new("+").size(TEXT_SIZE)).on_press(on_more))
+}
+
+// Style of each of `total` units, grouped by `groups` then `proposed`
+fn units(groups: impl Iterator<Item = usize>, proposed: usize, total: usize) -> Vec<style::Unit> {
+    let mut units: Vec<style::Unit> = groups
+        .enumerate()
+        .flat_map(|(index, size)| {
+            iter::repeat(style::Unit::Grouped {
+                is_odd: index % 2 == 1,
+            })
+            .take(size)
+        })
+        .collect();
+    units.extend(iter::repeat(style::Unit::Proposed).take(proposed));
+    units.resize(total, style::Unit::Left);
+    units
+}
+
+mod style {
+    use iced::{container, Color};
+
+    const EVEN_BG: Color = Color::from_rgb(0.85, 0.92, 0.85);
+    const ODD_BG: Color = Color::from_rgb(0.75, 0.86, 0.75);
+    const PROPOSED_BG: Color = Color::from_rgb(1.0, 0.85, 0.4);
+    const LEFT_BG: Color = Color::from_rgb(0.93, 0.93, 0.93);
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum Unit {
+        /// In an accepted group, alternating colors.
+        Grouped {
+            is_odd: bool,
+        },
+        Proposed,
+        Left,
+    }
+
+    impl container::StyleSheet for Unit {
+        fn style(&self) -> container::Style {
+            let background = match self {
+                Unit::Grouped { is_odd: false } => EVEN_BG,
+                Unit::Grouped { is_odd: true } => ODD_BG,
+                Unit::Proposed => PROPOSED_BG,
+                Unit::Left => LEFT_BG,
+            };
+            container::Style {
+                text_color: Some(Color::BLACK),
+                background: Some(background.into()),
+                ..container::Style::default()
+            }
+        }
+    }
+}
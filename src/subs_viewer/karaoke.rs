@@ -0,0 +1,723 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter;
+use std::mem;
+
+use iced::{
+    button, pick_list, Align, Button, Column, Container, Element, Length, PickList, Point, Row,
+    Text,
+};
+
+use super::handles::{self, Drag, Handles, Shape};
+use super::tags::{Item, Karaoke, Line, NodeKind, Tag, TagKind};
+use super::time::Time;
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const CHAR_PADDING: u16 = 2;
+const TIMELINE_HEIGHT: u16 = 60;
+
+// Fraction of the timeline under which a boundary is grabbed
+const GRAB_FRACTION: f32 = 0.02;
+
+pub static ALL_EFFECTS: &[Karaoke] = &[Karaoke::Fill, Karaoke::Sweep, Karaoke::Outline];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syllable {
+    /// In centiseconds.
+    pub duration: u32,
+    /// Text with its override blocks, without the karaoke tag.
+    pub text: String,
+}
+
+// A visible character of a syllable, as shown for manual splitting
+struct Char {
+    syllable: usize,
+    // Where the syllable is split to start at this character, before the
+    // blocks applying to it
+    split_at: usize,
+    // Whether the character starts its syllable
+    is_first: bool,
+    label: String,
+}
+
+/// A line split into karaoke syllables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Syllables {
+    effect: Karaoke,
+    // Text before the first karaoke tag
+    prefix: String,
+    syllables: Vec<Syllable>,
+}
+
+impl Syllables {
+    /// Reads the syllables of `text`. A line without karaoke tags is a single
+    /// syllable lasting `duration` centiseconds.
+    pub fn parse(text: &str, duration: u32) -> Self {
+        let line = Line::parse(text);
+        let mut effect = None;
+        let mut prefix = String::new();
+        let mut syllables: Vec<Syllable> = Vec::new();
+
+        // Text goes to the last syllable started
+        fn target<'a>(prefix: &'a mut String, syllables: &'a mut [Syllable]) -> &'a mut String {
+            match syllables.last_mut() {
+                Some(syllable) => &mut syllable.text,
+                None => prefix,
+            }
+        }
+        fn push_block(target: &mut String, items: &mut String) {
+            if !items.is_empty() {
+                target.push('{');
+                target.push_str(items);
+                target.push('}');
+                items.clear();
+            }
+        }
+
+        for node in &line.nodes {
+            let items = match &node.kind {
+                NodeKind::Block(items) => items,
+                _ => {
+                    target(&mut prefix, &mut syllables).push_str(&text[node.span.clone()]);
+                    continue;
+                }
+            };
+
+            // Other items of the block, kept in a block of their own
+            let mut others = String::new();
+            for item in items {
+                match item {
+                    Item::Tag(Tag {
+                        kind: TagKind::Karaoke(karaoke, duration),
+                        ..
+                    }) => {
+                        push_block(target(&mut prefix, &mut syllables), &mut others);
+                        effect.get_or_insert(*karaoke);
+                        syllables.push(Syllable {
+                            duration: *duration,
+                            text: String::new(),
+                        });
+                    }
+                    Item::Tag(tag) => others.push_str(&text[tag.span.clone()]),
+                    Item::Comment(span) => others.push_str(&text[span.clone()]),
+                }
+            }
+            push_block(target(&mut prefix, &mut syllables), &mut others);
+        }
+
+        match effect {
+            Some(effect) => Self {
+                effect,
+                prefix,
+                syllables,
+            },
+            None => Self {
+                effect: Karaoke::Fill,
+                prefix: String::new(),
+                syllables: vec![Syllable {
+                    duration,
+                    text: text.to_owned(),
+                }],
+            },
+        }
+    }
+
+    pub fn effect(&self) -> Karaoke {
+        self.effect
+    }
+
+    pub fn syllables(&self) -> &[Syllable] {
+        &self.syllables
+    }
+
+    // Sum of the syllable durations
+    fn total(&self) -> u32 {
+        self.syllables
+            .iter()
+            .map(|syllable| syllable.duration)
+            .sum()
+    }
+
+    // Ends of the syllables but the last, from the start of the line
+    fn boundaries(&self) -> Vec<u32> {
+        let count = self.syllables.len().saturating_sub(1);
+        self.syllables[..count]
+            .iter()
+            .scan(0, |end, syllable| {
+                *end += syllable.duration;
+                Some(*end)
+            })
+            .collect()
+    }
+
+    /// Splits the whole text into romaji syllables sharing `duration`.
+    fn split_romaji(&mut self, duration: u32) {
+        let text: String = self
+            .syllables
+            .iter()
+            .map(|syllable| syllable.text.as_str())
+            .collect();
+        let texts = split_romaji(&text);
+        if texts.is_empty() {
+            return;
+        }
+
+        let count = texts.len() as u32;
+        self.syllables = texts
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| Syllable {
+                // The remainder goes to the first syllables
+                duration: duration / count + u32::from((index as u32) < duration % count),
+                text,
+            })
+            .collect();
+    }
+
+    fn chars(&self) -> Vec<Char> {
+        let mut chars = Vec::new();
+        for (index, syllable) in self.syllables.iter().enumerate() {
+            let line = Line::parse(&syllable.text);
+            // Start of the blocks before the next character
+            let mut blocks_start = None;
+
+            for node in &line.nodes {
+                let span = node.span.clone();
+                let labels: Vec<(usize, &str)> = match node.kind {
+                    NodeKind::Block(_) | NodeKind::Drawing => {
+                        blocks_start.get_or_insert(span.start);
+                        continue;
+                    }
+                    NodeKind::Text => syllable.text[span.clone()]
+                        .char_indices()
+                        .map(|(offset, c)| {
+                            let start = span.start + offset;
+                            (start, &syllable.text[start..start + c.len_utf8()])
+                        })
+                        .collect(),
+                    NodeKind::LineBreak { .. } | NodeKind::HardSpace => {
+                        vec![(span.start, &syllable.text[span])]
+                    }
+                };
+
+                for (start, label) in labels {
+                    chars.push(Char {
+                        syllable: index,
+                        split_at: blocks_start.take().unwrap_or(start),
+                        is_first: chars
+                            .last()
+                            .map_or(true, |last: &Char| last.syllable != index),
+                        label: label.to_owned(),
+                    });
+                }
+            }
+        }
+        chars
+    }
+
+    /// Starts a syllable at the `index`th visible character, or joins it to
+    /// the previous one if it already starts one.
+    fn toggle_split(&mut self, index: usize) {
+        let chars = self.chars();
+        let char = match chars.get(index) {
+            Some(char) => char,
+            None => return,
+        };
+        let syllable = char.syllable;
+
+        if char.is_first {
+            if syllable > 0 {
+                let next = self.syllables.remove(syllable);
+                let previous = &mut self.syllables[syllable - 1];
+                previous.duration += next.duration;
+                previous.text.push_str(&next.text);
+            }
+            return;
+        }
+
+        // The duration is shared by character count
+        let count = chars.iter().filter(|c| c.syllable == syllable).count() as u32;
+        let head = chars[..index]
+            .iter()
+            .filter(|c| c.syllable == syllable)
+            .count() as u32;
+        let current = &mut self.syllables[syllable];
+        let head_duration = current.duration * head / count;
+        let tail = Syllable {
+            duration: current.duration - head_duration,
+            text: current.text.split_off(char.split_at),
+        };
+        current.duration = head_duration;
+        self.syllables.insert(syllable + 1, tail);
+    }
+
+    // Moves the end of the `index`th syllable to `time`, keeping the end of
+    // the next one
+    fn move_boundary(&mut self, index: usize, time: u32) {
+        let start: u32 = self.syllables[..index]
+            .iter()
+            .map(|syllable| syllable.duration)
+            .sum();
+        let end = start + self.syllables[index].duration + self.syllables[index + 1].duration;
+        let time = time.max(start).min(end);
+
+        self.syllables[index].duration = time - start;
+        self.syllables[index + 1].duration = end - time;
+    }
+}
+
+impl fmt::Display for Syllables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.prefix)?;
+        for syllable in &self.syllables {
+            // The karaoke tag joins the block starting the syllable
+            match syllable.text.strip_prefix('{') {
+                Some(rest) => write!(f, "{{\\{}{}{}", self.effect, syllable.duration, rest)?,
+                None => write!(
+                    f,
+                    "{{\\{}{}}}{}",
+                    self.effect, syllable.duration, syllable.text
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits `text` into romaji syllables: consonants up to a vowel, a lone
+/// `n`, or the first of doubled consonants. Other scripts are split by
+/// character. Spaces and punctuation end the syllable before them, override
+/// blocks start the one after them.
+pub fn split_romaji(text: &str) -> Vec<String> {
+    let line = Line::parse(text);
+    // Source of each piece, with its character when visible
+    let mut pieces: Vec<(&str, Option<char>)> = Vec::new();
+    for node in &line.nodes {
+        let span = node.span.clone();
+        match node.kind {
+            NodeKind::Text => {
+                pieces.extend(text[span.clone()].char_indices().map(|(offset, c)| {
+                    let start = span.start + offset;
+                    (&text[start..start + c.len_utf8()], Some(c))
+                }))
+            }
+            NodeKind::LineBreak { .. } | NodeKind::HardSpace => {
+                pieces.push((&text[span], Some(' ')))
+            }
+            NodeKind::Block(_) | NodeKind::Drawing => pieces.push((&text[span], None)),
+        }
+    }
+
+    let is_vowel = |c: char| "aeiou".contains(c);
+    // Whether only pieces without letters were gathered
+    let mut has_letters = false;
+    let mut current = String::new();
+    let mut syllables: Vec<String> = Vec::new();
+
+    for (index, &(source, c)) in pieces.iter().enumerate() {
+        let c = match c {
+            Some(c) => c,
+            None => {
+                current.push_str(source);
+                continue;
+            }
+        };
+        let next = pieces[index + 1..]
+            .iter()
+            .find_map(|&(_, c)| c)
+            .map(|c| c.to_ascii_lowercase());
+
+        let ends_syllable = if !c.is_alphanumeric() {
+            match syllables.last_mut() {
+                Some(last) if !has_letters && current.is_empty() => {
+                    last.push_str(source);
+                    continue;
+                }
+                _ => has_letters,
+            }
+        } else if !c.is_ascii_alphabetic() {
+            // Small kana extend the previous syllable
+            match syllables.last_mut() {
+                Some(last) if "ぁぃぅぇぉゃゅょァィゥェォャュョ".contains(c) && !has_letters =>
+                {
+                    last.push_str(source);
+                    continue;
+                }
+                _ => true,
+            }
+        } else {
+            let c = c.to_ascii_lowercase();
+            is_vowel(c)
+                || (c == 'n' && !next.map_or(false, |next| is_vowel(next) || next == 'y'))
+                || (c != 'n' && next == Some(c))
+        };
+
+        current.push_str(source);
+        has_letters |= c.is_alphanumeric();
+        if ends_syllable {
+            syllables.push(mem::take(&mut current));
+            has_letters = false;
+        }
+    }
+
+    if !current.is_empty() {
+        match syllables.last_mut() {
+            Some(last) if !has_letters => last.push_str(&current),
+            _ => syllables.push(current),
+        }
+    }
+    syllables
+}
+
+// `text` without its karaoke tags
+fn untimed(text: &str) -> String {
+    let syllables = Syllables::parse(text, 0);
+    iter::once(syllables.prefix.as_str())
+        .chain(
+            syllables
+                .syllables
+                .iter()
+                .map(|syllable| syllable.text.as_str()),
+        )
+        .collect()
+}
+
+/// Visible characters of `text`, its karaoke tags removed.
+pub fn characters(text: &str) -> Vec<String> {
+    Syllables::parse(&untimed(text), 0)
+        .chars()
+        .into_iter()
+        .map(|char| char.label)
+        .collect()
+}
+
+/// Times `text` with a syllable per group of `(duration, character count)`,
+/// the last one taking the characters left.
+pub fn timed(text: &str, effect: Karaoke, groups: &[(u32, usize)]) -> String {
+    let text = untimed(text);
+    let chars = Syllables::parse(&text, 0).chars();
+    let mut start = 0;
+    let mut count = 0;
+
+    let syllables = groups
+        .iter()
+        .enumerate()
+        .map(|(index, &(duration, chars_in_group))| {
+            count += chars_in_group;
+            let end = match chars.get(count) {
+                Some(char) if index + 1 < groups.len() => char.split_at,
+                _ => text.len(),
+            };
+            let syllable = Syllable {
+                duration,
+                text: text[start..end].to_owned(),
+            };
+            start = end;
+            syllable
+        })
+        .collect();
+
+    Syllables {
+        effect,
+        prefix: String::new(),
+        syllables,
+    }
+    .to_string()
+}
+
+/// Duration of `line` in centiseconds.
+pub fn line_duration(line: &SubsControlsValues) -> u32 {
+    let start = line.start().unwrap_or(Time::ZERO);
+    let end = line.end().unwrap_or(start);
+    (end.saturating_sub(start).as_millis() / 10) as u32
+}
+
+/// Text shown for a syllable, without its blocks.
+pub fn visible(text: &str) -> String {
+    Line::parse(text)
+        .nodes
+        .iter()
+        .filter(|node| !matches!(node.kind, NodeKind::Block(_) | NodeKind::Drawing))
+        .map(|node| &text[node.span.clone()])
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The text of the line, with the name of the edit for the history.
+    Edited {
+        name: String,
+        text: String,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    EffectPicked(Karaoke),
+    SplitRomaji,
+    ToggleSplit(usize),
+    Drag(Drag),
+    Close,
+}
+
+/// Splits the active line into karaoke syllables and times them.
+#[derive(Default)]
+pub struct KaraokeEditor {
+    effects: pick_list::State<Karaoke>,
+    split_romaji: button::State,
+    close: button::State,
+    chars: Vec<button::State>,
+    handles: handles::State,
+    // Index of the dragged boundary
+    grab: Option<usize>,
+}
+
+impl KaraokeEditor {
+    pub fn update(&mut self, message: Message, line: &SubsControlsValues) -> Option<Event> {
+        let duration = line_duration(line);
+        let mut syllables = Syllables::parse(&line.text, duration);
+        let total = syllables.total().max(1) as f32;
+
+        let name = match message {
+            Message::EffectPicked(effect) => {
+                syllables.effect = effect;
+                "Set karaoke effect"
+            }
+            Message::SplitRomaji => {
+                syllables.split_romaji(duration);
+                "Split syllables"
+            }
+            Message::ToggleSplit(index) => {
+                syllables.toggle_split(index);
+                "Split syllables"
+            }
+            Message::Drag(Drag::Pressed(at)) => {
+                let nearest = syllables
+                    .boundaries()
+                    .into_iter()
+                    .map(|end| (end as f32 / total - at.x).abs())
+                    .enumerate()
+                    .min_by(|(_, first), (_, second)| {
+                        first.partial_cmp(second).unwrap_or(Ordering::Equal)
+                    })
+                    .filter(|&(_, distance)| distance <= GRAB_FRACTION);
+                self.grab = Some(nearest?.0);
+                return None;
+            }
+            Message::Drag(Drag::Moved(at)) => {
+                let time = (at.x.max(0.0).min(1.0) * total).round() as u32;
+                syllables.move_boundary(self.grab?, time);
+                "Time syllables"
+            }
+            Message::Drag(Drag::Released) => {
+                self.grab = None;
+                return None;
+            }
+            Message::Close => return Some(Event::Closed),
+        };
+
+        Some(Event::Edited {
+            name: name.to_owned(),
+            text: syllables.to_string(),
+        })
+    }
+
+    pub fn view(&mut self, line: &SubsControlsValues) -> Element<Message> {
+        let duration = line_duration(line);
+        let syllables = Syllables::parse(&line.text, duration);
+        let total = syllables.total();
+
+        // Timers see at once when the syllables do not fill the line
+        let status = if total == duration {
+            format!("{} syllables", syllables.syllables.len())
+        } else {
+            format!(
+                "{} syllables lasting {} cs, the line lasts {} cs",
+                syllables.syllables.len(),
+                total,
+                duration
+            )
+        };
+
+        let toolbar = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("Karaoke tag").size(TEXT_SIZE))
+            .push(
+                PickList::new(
+                    &mut self.effects,
+                    ALL_EFFECTS,
+                    Some(syllables.effect),
+                    Message::EffectPicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                Button::new(
+                    &mut self.split_romaji,
+                    Text::new("Split romaji").size(TEXT_SIZE),
+                )
+                .on_press(Message::SplitRomaji),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(status).size(TEXT_SIZE));
+
+        // A bar marks the characters starting a syllable
+        let chars = syllables.chars();
+        self.chars.resize_with(chars.len(), button::State::default);
+        let splitter = self.chars.iter_mut().zip(chars).enumerate().fold(
+            Row::new().align_items(Align::Center),
+            |row, (index, (state, char))| {
+                let label = if char.is_first && char.syllable > 0 {
+                    format!("|{}", char.label)
+                } else {
+                    char.label
+                };
+                row.push(
+                    Button::new(state, Text::new(label).size(TEXT_SIZE))
+                        .padding(CHAR_PADDING)
+                        .on_press(Message::ToggleSplit(index)),
+                )
+            },
+        );
+
+        let cells = syllables.syllables.iter().enumerate().fold(
+            Row::new().width(Length::Fill),
+            |row, (index, syllable)| {
+                let portion = syllable.duration.max(1).min(u16::MAX as u32) as u16;
+                row.push(
+                    Container::new(
+                        Column::new()
+                            .push(Text::new(visible(&syllable.text)).size(TEXT_SIZE))
+                            .push(Text::new(syllable.duration.to_string()).size(TEXT_SIZE)),
+                    )
+                    .width(Length::FillPortion(portion))
+                    .height(Length::Units(TIMELINE_HEIGHT))
+                    .padding(CHAR_PADDING)
+                    .style(style::Syllable {
+                        is_odd: index % 2 == 1,
+                    }),
+                )
+            },
+        );
+
+        let shapes = syllables
+            .boundaries()
+            .into_iter()
+            .flat_map(|end| {
+                let x = end as f32 / total.max(1) as f32;
+                vec![
+                    Shape::Line(Point::new(x, 0.0), Point::new(x, 1.0)),
+                    Shape::Handle(Point::new(x, 0.5)),
+                ]
+            })
+            .collect();
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(toolbar)
+            .push(Text::new("Click a character to start or join a syllable").size(TEXT_SIZE))
+            .push(splitter)
+            .push(
+                Text::new("Drag the boundaries to time the syllables, in centiseconds")
+                    .size(TEXT_SIZE),
+            )
+            .push(
+                Handles::new(&mut self.handles, cells, shapes, Message::Drag)
+                    .style(style::Timeline),
+            )
+            .into()
+    }
+}
+
+mod style {
+    use iced::{container, Color};
+
+    use super::super::handles;
+
+    const EVEN_BG: Color = Color::from_rgb(0.93, 0.95, 0.98);
+    const ODD_BG: Color = Color::from_rgb(0.85, 0.89, 0.95);
+    const BOUNDARY: Color = Color::from_rgb(0.1, 0.3, 0.7);
+
+    pub struct Syllable {
+        pub is_odd: bool,
+    }
+
+    impl container::StyleSheet for Syllable {
+        fn style(&self) -> container::Style {
+            container::Style {
+                text_color: Some(Color::BLACK),
+                background: Some(if self.is_odd { ODD_BG } else { EVEN_BG }.into()),
+                ..container::Style::default()
+            }
+        }
+    }
+
+    pub struct Timeline;
+
+    impl handles::StyleSheet for Timeline {
+        fn active(&self) -> handles::Style {
+            handles::Style {
+                handle_color: BOUNDARY,
+                guide_color: BOUNDARY,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn romaji_is_split_into_syllables() {
+        assert_eq!(
+            split_romaji("konnichiwa"),
+            vec!["ko", "n", "ni", "chi", "wa"]
+        );
+        assert_eq!(split_romaji("sakki"), vec!["sa", "k", "ki"]);
+        assert_eq!(split_romaji("kanya"), vec!["ka", "nya"]);
+        assert_eq!(split_romaji("Sora, umi"), vec!["So", "ra, ", "u", "mi"]);
+    }
+
+    #[test]
+    fn blocks_start_the_syllable_after_them() {
+        assert_eq!(
+            split_romaji("{\\i1}ka{\\i0}na"),
+            vec!["{\\i1}ka", "{\\i0}na"]
+        );
+    }
+
+    #[test]
+    fn other_scripts_are_split_by_character() {
+        assert_eq!(split_romaji("きょうは"), vec!["きょ", "う", "は"]);
+    }
+
+    #[test]
+    fn empty_lines_are_left_as_they_are() {
+        assert!(split_romaji("").is_empty());
+
+        let mut syllables = Syllables::parse("", 100);
+        syllables.split_romaji(100);
+        assert_eq!(syllables, Syllables::parse("", 100));
+
+        syllables.syllables.clear();
+        assert!(syllables.boundaries().is_empty());
+    }
+
+    #[test]
+    fn split_syllables_share_the_duration() {
+        let mut syllables = Syllables::parse("sakura", 100);
+        syllables.split_romaji(100);
+        assert_eq!(syllables.to_string(), "{\\k34}sa{\\k33}ku{\\k33}ra");
+        assert_eq!(syllables.boundaries(), vec![34, 67]);
+    }
+}
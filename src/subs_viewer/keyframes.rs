@@ -0,0 +1,101 @@
+use std::io;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::frame_rate::FrameRate;
+
+const AEGISUB_HEADER: &str = "# keyframe format v1";
+const XVID_HEADER: &str = "# XviD 2pass stat file";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot read the keyframes: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {0}: invalid keyframe")]
+    InvalidLine(usize),
+    #[error("The file has no keyframe")]
+    Empty,
+}
+
+/// Frames starting a scene, from Aegisub keyframes, XviD first pass stats, as
+/// written by SCXviD, or a list of frame numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    /// Frame rate the keyframes were written with, if known.
+    pub frame_rate: Option<FrameRate>,
+    // Increasing
+    frames: Vec<u64>,
+}
+
+impl Keyframes {
+    /// Keyframe nearest to `frame`, at most `before` frames earlier or `after`
+    /// frames later.
+    pub fn nearest(&self, frame: u64, before: u64, after: u64) -> Option<u64> {
+        let index = self.frames.partition_point(|&keyframe| keyframe < frame);
+        let earlier = index
+            .checked_sub(1)
+            .map(|index| self.frames[index])
+            .filter(|&keyframe| frame - keyframe <= before);
+        let later = self
+            .frames
+            .get(index)
+            .copied()
+            .filter(|&keyframe| keyframe - frame <= after);
+
+        match (earlier, later) {
+            (Some(earlier), Some(later)) if frame - earlier < later - frame => Some(earlier),
+            (earlier, later) => later.or(earlier),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl FromStr for Keyframes {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .peekable();
+        let header = lines.peek().map(|&(_, line)| line).unwrap_or_default();
+        let is_xvid = header.eq_ignore_ascii_case(XVID_HEADER);
+        let mut frame_rate = None;
+        let mut frames = Vec::new();
+
+        if is_xvid {
+            // One line per frame, keyframes being intra frames
+            let types = lines.filter(|(_, line)| !line.starts_with('#'));
+            for (frame, (_, line)) in types.enumerate() {
+                if line.starts_with('i') {
+                    frames.push(frame as u64);
+                }
+            }
+        } else {
+            for (number, line) in lines.filter(|(_, line)| !line.starts_with('#')) {
+                // Aegisub keyframes give their frame rate, 0 when unknown
+                if header.eq_ignore_ascii_case(AEGISUB_HEADER) && line.starts_with("fps") {
+                    frame_rate = line[3..].parse::<FrameRate>().ok();
+                    continue;
+                }
+                let frame = line
+                    .parse::<u64>()
+                    .map_err(|_| Error::InvalidLine(number))?;
+                frames.push(frame);
+            }
+        }
+
+        if frames.is_empty() {
+            return Err(Error::Empty);
+        }
+        frames.sort_unstable();
+        frames.dedup();
+        Ok(Self { frame_rate, frames })
+    }
+}
@@ -0,0 +1,544 @@
+use std::ops::Range;
+
+use super::tags::{Line, NodeKind};
+use super::time::Time;
+use super::{SubsControlsValues, SubsViewer};
+
+// Number of rows skipped by PageUp and PageDown
+const PAGE_ROWS: usize = 10;
+
+// Duration given to the lines inserted before or after another one
+const INSERTED_DURATION: u64 = 2000;
+
+/// Keyboard driven operations on the table rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineAction {
+    Previous,
+    Next,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    ExtendPrevious,
+    ExtendNext,
+    CommitAndNext,
+    InsertBefore,
+    InsertAfter,
+    Duplicate,
+    Delete,
+    SplitAtCursor,
+    Join,
+    MoveUp,
+    MoveDown,
+}
+
+impl SubsControlsValues {
+    pub fn set_times(&mut self, start: Time, end: Time) {
+        self.start_time = start.to_string();
+        self.end_time = end.to_string();
+        self.duration = end.saturating_sub(start).to_string();
+    }
+
+    // An empty line sharing the style and the actor of `self`
+    fn sibling(&self, start: Time, end: Time) -> Self {
+        let mut line = Self {
+            style: self.style.clone(),
+            actor: self.actor.clone(),
+            layer: self.layer,
+            ..Self::new()
+        };
+        line.set_times(start, end);
+        line
+    }
+}
+
+impl SubsViewer {
+    pub(super) fn apply_line_action(&mut self, action: LineAction) {
+        let rows = self.visible_rows();
+        let Range { start, end } = self.focused_subs.clone();
+        let active = self.active_sub;
+
+        // Keystrokes after a line action start a new history entry
+        self.history.seal();
+
+        match action {
+            // Lines hidden by the filter are never edited
+            LineAction::Duplicate
+            | LineAction::Delete
+            | LineAction::SplitAtCursor
+            | LineAction::Join
+            | LineAction::MoveUp
+            | LineAction::MoveDown
+                if !rows.contains(&active) => {}
+            LineAction::Previous => self.step(&rows, -1, false),
+            LineAction::Next => self.step(&rows, 1, false),
+            LineAction::PageUp => self.step(&rows, -(PAGE_ROWS as isize), false),
+            LineAction::PageDown => self.step(&rows, PAGE_ROWS as isize, false),
+            LineAction::First => {
+                if let Some(&row) = rows.first() {
+                    self.select(row);
+                }
+            }
+            LineAction::Last => {
+                if let Some(&row) = rows.last() {
+                    self.select(row);
+                }
+            }
+            LineAction::ExtendPrevious => self.step(&rows, -1, true),
+            LineAction::ExtendNext => self.step(&rows, 1, true),
+            LineAction::CommitAndNext => self.record(
+                Self::lines_name("Insert", 1),
+                None,
+                active + 1..active + 1,
+                |viewer| viewer.commit_and_next(&rows),
+            ),
+            LineAction::InsertBefore => self.record(
+                Self::lines_name("Insert", 1),
+                None,
+                active..active,
+                |viewer| viewer.insert_before(),
+            ),
+            LineAction::InsertAfter => self.record(
+                Self::lines_name("Insert", 1),
+                None,
+                active + 1..active + 1,
+                |viewer| viewer.insert_after(),
+            ),
+            LineAction::Duplicate => self.record(
+                Self::lines_name("Duplicate", end - start),
+                None,
+                end..end,
+                |viewer| viewer.duplicate(),
+            ),
+            LineAction::Delete => self.record(
+                Self::lines_name("Delete", end - start),
+                None,
+                start..end,
+                |viewer| viewer.delete(),
+            ),
+            LineAction::SplitAtCursor => self.record(
+                Self::lines_name("Split", 1),
+                None,
+                active..active + 1,
+                |viewer| viewer.split_at_cursor(),
+            ),
+            LineAction::Join => {
+                if let Some(range) = self.join_range(&rows) {
+                    let name = Self::lines_name("Join", range.len());
+                    self.record(name, None, range.clone(), |viewer| viewer.join(range))
+                }
+            }
+            LineAction::MoveUp => {
+                if start > 0 && rows.contains(&(start - 1)) {
+                    self.record(
+                        Self::lines_name("Move", end - start),
+                        None,
+                        start - 1..end,
+                        |viewer| viewer.move_up(),
+                    )
+                }
+            }
+            LineAction::MoveDown => {
+                if end < self.controls_values.len() && rows.contains(&end) {
+                    self.record(
+                        Self::lines_name("Move", end - start),
+                        None,
+                        start..end + 1,
+                        |viewer| viewer.move_down(),
+                    )
+                }
+            }
+        }
+    }
+
+    // History entry name, as in "Delete 3 lines"
+    fn lines_name(action: &str, lines: usize) -> String {
+        match lines {
+            1 => format!("{} line", action),
+            lines => format!("{} {} lines", action, lines),
+        }
+    }
+
+    /// Focuses a single line.
+    pub(super) fn select(&mut self, row: usize) {
+        self.active_sub = row;
+        self.focused_subs = row..row + 1;
+    }
+
+    // Selects from the line opposite to the active one up to `row`, in display
+    // order. It stops before the lines that would not leave a range of the
+    // document, so the selection only holds lines shown next to each other.
+    fn extend_to(&mut self, rows: &[usize], row: usize) {
+        let selected: Vec<usize> = (0..rows.len())
+            .filter(|&position| self.focused_subs.contains(&rows[position]))
+            .collect();
+        let anchor = match (selected.first(), selected.last()) {
+            (Some(&first), Some(&last)) if rows[first] == self.active_sub => last,
+            (Some(&first), Some(_)) => first,
+            _ => {
+                self.select(row);
+                return;
+            }
+        };
+        let target = rows
+            .iter()
+            .position(|&shown| shown == row)
+            .unwrap_or(anchor);
+
+        let toward: Vec<usize> = if target >= anchor {
+            rows[anchor + 1..=target].to_vec()
+        } else {
+            rows[target..anchor].iter().rev().copied().collect()
+        };
+        let (span, last) = grow(rows[anchor]..rows[anchor] + 1, toward);
+        self.focused_subs = span;
+        self.active_sub = last.unwrap_or(rows[anchor]);
+    }
+
+    /// Shrinks the selection to the visible lines shown next to the active
+    /// one, after the filter, the sort or the history changed it.
+    pub(super) fn clamp_selection(&mut self) {
+        let rows = self.visible_rows();
+        let position = match rows.iter().position(|&row| row == self.active_sub) {
+            Some(position) => position,
+            None => {
+                if let Some(&row) = rows.first() {
+                    self.select(row);
+                }
+                return;
+            }
+        };
+
+        let focused = self.focused_subs.clone();
+        let is_selected = |row: &usize| focused.contains(row);
+        let before = rows[..position]
+            .iter()
+            .rev()
+            .copied()
+            .take_while(is_selected);
+        let (span, _) = grow(self.active_sub..self.active_sub + 1, before);
+        let after = rows[position + 1..].iter().copied().take_while(is_selected);
+        let (span, _) = grow(span, after);
+        self.focused_subs = span;
+    }
+
+    // Moves the active line by `offset` rows in display order
+    fn step(&mut self, rows: &[usize], offset: isize, extend: bool) {
+        if rows.is_empty() {
+            return;
+        }
+
+        let position = match rows.iter().position(|&row| row == self.active_sub) {
+            Some(position) => (position as isize + offset).max(0) as usize,
+            None => 0,
+        };
+        let row = rows[position.min(rows.len() - 1)];
+
+        if extend {
+            self.extend_to(rows, row);
+        } else {
+            self.select(row);
+        }
+    }
+
+    // Jumps to the next line, appending a new one after the last line
+    fn commit_and_next(&mut self, rows: &[usize]) {
+        let is_last = rows.last().map_or(true, |&row| row == self.active_sub);
+        if is_last && self.active_sub + 1 == self.controls_values.len() {
+            self.insert_after();
+        } else {
+            self.step(rows, 1, false);
+        }
+    }
+
+    fn insert_before(&mut self) {
+        let current = &self.controls_values[self.active_sub];
+        let end = current.start().unwrap_or(Time::ZERO);
+        let start = end.saturating_sub(Time::from_millis(INSERTED_DURATION));
+        let line = current.sibling(start, end);
+
+        let row = self.active_sub;
+        self.controls_values.insert(row, line);
+        self.select(row);
+    }
+
+    fn insert_after(&mut self) {
+        let current = &self.controls_values[self.active_sub];
+        let start = current.end().unwrap_or(Time::ZERO);
+        let end = start.saturating_add(Time::from_millis(INSERTED_DURATION));
+        let line = current.sibling(start, end);
+
+        let row = self.active_sub + 1;
+        self.controls_values.insert(row, line);
+        self.select(row);
+    }
+
+    // Copies of the selected lines are placed right after them
+    fn duplicate(&mut self) {
+        let range = self.focused_subs.clone();
+        let copies: Vec<SubsControlsValues> = self.controls_values[range.clone()].to_vec();
+        let len = copies.len();
+        self.controls_values
+            .splice(range.end..range.end, copies.into_iter());
+
+        self.focused_subs = range.end..range.end + len;
+        self.active_sub = range.end;
+    }
+
+    fn delete(&mut self) {
+        let range = self.focused_subs.clone();
+        self.controls_values.drain(range.clone());
+
+        // The editor always needs a line to show
+        if self.controls_values.is_empty() {
+            self.controls_values.push(SubsControlsValues::new());
+        }
+
+        self.select(range.start.min(self.controls_values.len() - 1));
+    }
+
+    // Splits the active line at the text cursor, sharing the time between the
+    // two halves proportionally to their length
+    fn split_at_cursor(&mut self) {
+        let row = self.active_sub;
+        let current = &mut self.controls_values[row];
+        let cursor = self.subs_controls.text_cursor(&current.text);
+        // Never cut an override block or an escape in two
+        let cursor = Line::parse(&current.text)
+            .node_at(cursor)
+            .filter(|node| node.kind != NodeKind::Text)
+            .map_or(cursor, |node| node.span.end);
+
+        let tail = current.text.split_off(cursor);
+        let head_chars = current.text.chars().count() as u64;
+        let total_chars = head_chars + tail.chars().count() as u64;
+
+        let (start, end) = match (current.start(), current.end()) {
+            (Some(start), Some(end)) if end > start => (start, end),
+            (Some(start), _) => (start, start),
+            _ => (Time::ZERO, Time::ZERO),
+        };
+        let split = if total_chars == 0 {
+            start
+        } else {
+            let length = end.saturating_sub(start).as_millis();
+            start.saturating_add(Time::from_millis(length * head_chars / total_chars))
+        };
+
+        let mut second = current.clone();
+        second.text = tail;
+        second.set_times(split, end);
+        current.set_times(start, split);
+
+        self.controls_values.insert(row + 1, second);
+        self.select(row);
+    }
+
+    // Selected lines, or the active line with the one shown after it when
+    // it also follows it in the document
+    fn join_range(&self, rows: &[usize]) -> Option<Range<usize>> {
+        let next = rows
+            .iter()
+            .position(|&row| row == self.active_sub)
+            .and_then(|position| rows.get(position + 1));
+        if self.focused_subs.len() > 1 {
+            Some(self.focused_subs.clone())
+        } else if next == Some(&(self.active_sub + 1)) {
+            Some(self.active_sub..self.active_sub + 2)
+        } else {
+            None
+        }
+    }
+
+    // Merges the `range` lines into the first of them
+    fn join(&mut self, range: Range<usize>) {
+        let joined: Vec<SubsControlsValues> = self
+            .controls_values
+            .drain(range.start + 1..range.end)
+            .collect();
+        let first = &mut self.controls_values[range.start];
+
+        let start = first.start().unwrap_or(Time::ZERO);
+        let mut end = first.end().unwrap_or(start);
+        for line in joined {
+            end = end.max(line.end().unwrap_or(end));
+            first.text = Self::concat(&first.text, &line.text, " ");
+            first.notes = Self::concat(&first.notes, &line.notes, " ");
+        }
+        first.set_times(start, end);
+
+        self.select(range.start);
+    }
+
+    fn move_up(&mut self) {
+        let Range { start, end } = self.focused_subs.clone();
+        if start == 0 {
+            return;
+        }
+
+        self.controls_values[start - 1..end].rotate_left(1);
+        self.focused_subs = start - 1..end - 1;
+        self.active_sub -= 1;
+    }
+
+    fn move_down(&mut self) {
+        let Range { start, end } = self.focused_subs.clone();
+        if end >= self.controls_values.len() {
+            return;
+        }
+
+        self.controls_values[start..=end].rotate_right(1);
+        self.focused_subs = start + 1..end + 1;
+        self.active_sub += 1;
+    }
+
+    #[inline(always)]
+    fn concat(first: &str, second: &str, separator: &str) -> String {
+        match (first.is_empty(), second.is_empty()) {
+            (_, true) => first.to_owned(),
+            (true, false) => second.to_owned(),
+            (false, false) => format!("{}{}{}", first, separator, second),
+        }
+    }
+}
+
+// Adds `rows` to `span` one at a time, stopping at the first one that would
+// not leave a range of the document. Also gives the last row added.
+fn grow(
+    mut span: Range<usize>,
+    rows: impl IntoIterator<Item = usize>,
+) -> (Range<usize>, Option<usize>) {
+    let mut last = None;
+    for row in rows {
+        if row + 1 == span.start {
+            span.start = row;
+        } else if row == span.end {
+            span.end += 1;
+        } else {
+            break;
+        }
+        last = Some(row);
+    }
+    (span, last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::table_viewer::{Sort, SortColumn};
+    use super::*;
+
+    // A viewer holding one line per text, each one second long and starting
+    // at the given second
+    fn viewer(lines: &[(u64, &str)]) -> SubsViewer {
+        let mut viewer = SubsViewer::new();
+        viewer.controls_values = lines
+            .iter()
+            .map(|&(second, text)| {
+                let mut line = SubsControlsValues::new();
+                let start = Time::from_millis(second * 1000);
+                line.set_times(start, start.saturating_add(Time::from_millis(1000)));
+                line.text = text.to_owned();
+                line
+            })
+            .collect();
+        viewer
+    }
+
+    fn texts(viewer: &SubsViewer) -> Vec<&str> {
+        viewer
+            .controls_values
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_split_at_the_byte_cursor() {
+        let mut viewer = viewer(&[(0, "héllo wörld")]);
+        // After "héllo ", é taking two bytes
+        viewer.subs_controls.select_text(7..7);
+        viewer.apply_line_action(LineAction::SplitAtCursor);
+
+        assert_eq!(texts(&viewer), vec!["héllo ", "wörld"]);
+        // Six characters out of eleven
+        let split = Time::from_millis(545);
+        let (first, second) = (&viewer.controls_values[0], &viewer.controls_values[1]);
+        assert_eq!(
+            (first.start(), first.end()),
+            (Some(Time::ZERO), Some(split))
+        );
+        assert_eq!(
+            (second.start(), second.end()),
+            (Some(split), Some(Time::from_millis(1000)))
+        );
+        assert_eq!(viewer.focused_subs, 0..1);
+    }
+
+    #[test]
+    fn override_blocks_are_not_split() {
+        let mut viewer = viewer(&[(0, "a{\\i1}b")]);
+        viewer.subs_controls.select_text(3..3);
+        viewer.apply_line_action(LineAction::SplitAtCursor);
+
+        assert_eq!(texts(&viewer), vec!["a{\\i1}", "b"]);
+    }
+
+    #[test]
+    fn selected_lines_are_joined() {
+        let mut viewer = viewer(&[(0, "one"), (1, "two"), (3, "three"), (5, "four")]);
+        viewer.focused_subs = 0..3;
+        viewer.active_sub = 0;
+        viewer.apply_line_action(LineAction::Join);
+
+        assert_eq!(texts(&viewer), vec!["one two three", "four"]);
+        let joined = &viewer.controls_values[0];
+        assert_eq!(joined.start(), Some(Time::ZERO));
+        assert_eq!(joined.end(), Some(Time::from_millis(4000)));
+        assert_eq!(viewer.focused_subs, 0..1);
+
+        viewer.update_history(super::super::history::Message::Undo);
+        assert_eq!(texts(&viewer), vec!["one", "two", "three", "four"]);
+    }
+
+    #[test]
+    fn lines_move_in_the_document_order_when_sorted() {
+        let mut viewer = viewer(&[(3, "a"), (1, "b"), (2, "c")]);
+        viewer.sort = Some(Sort {
+            column: SortColumn::Start,
+            descending: false,
+        });
+        viewer.select(2);
+        viewer.apply_line_action(LineAction::MoveUp);
+
+        assert_eq!(texts(&viewer), vec!["a", "c", "b"]);
+        assert_eq!((viewer.focused_subs.clone(), viewer.active_sub), (1..2, 1));
+
+        // The first line of the document has nothing to move above
+        viewer.select(0);
+        viewer.apply_line_action(LineAction::MoveUp);
+        assert_eq!(texts(&viewer), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn selection_is_clamped_to_the_lines_shown_together() {
+        let mut viewer = viewer(&[(1, "a"), (3, "b"), (2, "c")]);
+        viewer.focused_subs = 0..3;
+        viewer.active_sub = 0;
+        viewer.clamp_selection();
+        assert_eq!(viewer.focused_subs, 0..3);
+
+        // Shown as a, c, b: b no longer follows a
+        viewer.sort = Some(Sort {
+            column: SortColumn::Start,
+            descending: false,
+        });
+        viewer.focused_subs = 0..2;
+        viewer.clamp_selection();
+        assert_eq!((viewer.focused_subs.clone(), viewer.active_sub), (0..1, 0));
+
+        // A line past the end falls back to the first one shown
+        viewer.active_sub = 5;
+        viewer.clamp_selection();
+        assert_eq!((viewer.focused_subs.clone(), viewer.active_sub), (0..1, 0));
+    }
+}
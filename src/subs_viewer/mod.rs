@@ -1,26 +1,97 @@
+mod actor_manager;
+mod automation;
 mod cell;
+mod click_area;
+mod color_picker;
+mod cps;
+mod drawing;
+mod filter_bar;
+mod formatting;
+mod frame_rate;
+mod handles;
+mod history;
+mod kanji_timer;
+mod karaoke;
+mod keyframes;
+mod line_actions;
+mod motion;
+mod preview;
+mod reference_sync;
+mod resample;
+mod retime;
+mod style_catalog;
+mod style_manager;
+mod styles;
 mod subs_controls;
-//mod table_viewer;
-mod table_viewer2;
+mod subtitle_file;
+mod table_viewer;
+mod tags;
+mod templater;
+mod text_editor;
+mod time;
+mod time_shift;
+mod timecodes;
+mod timing_check;
+mod timing_processor;
+mod timing_tools;
+mod typesetting;
 
 use std::ops::Range;
 
-use iced::{Align, Column, Container, Element, Length};
+use iced::{Align, Column, Container, Element, Length, Row};
 
-use subs_controls::{ActorList, StyleList, SubsControls};
-use table_viewer2::TableViewer;
+use actor_manager::ActorManager;
+use automation::Automation;
+use cell::TagDisplay;
+use cps::CpsControls;
+use filter_bar::{FilterBar, FilterValues};
+use formatting::Format;
+use history::{History, HistoryPanel};
+use kanji_timer::KanjiTimer;
+use karaoke::KaraokeEditor;
+use motion::MotionImporter;
+use preview::{PreviewFrame, Previewer};
+use reference_sync::ReferenceSync;
+use retime::Retimer;
+use style_manager::StyleManager;
+use styles::{AssStyle, DEFAULT_STYLE};
+use subs_controls::SubsControls;
+use table_viewer::{DisplaySettings, Sort, SortColumn, TableViewer};
+use time_shift::TimeShifter;
+use timing_check::TimingControls;
+use timing_processor::TimingProcessor;
+use typesetting::Typesetter;
+
+pub use cps::{CpsLevel, CpsSettings};
+pub use history::Message as HistoryMessage;
+pub use kanji_timer::Message as KanjiTimerMessage;
+pub use line_actions::LineAction;
+pub use resample::{resample, DEFAULT_RESOLUTION};
+pub use retime::Conversion;
+pub use subtitle_file::{Error as SubtitleFileError, SubtitleFile};
+pub use time::Time;
+pub use timecodes::Timebase;
+pub use timing_check::TimingSettings;
 
 const PADDING: u16 = 10;
 const COLUMN_SPACING: u16 = 40;
+const ROW_SPACING: u16 = 10;
+const HISTORY_WIDTH: u16 = 220;
+
+// Frame the active line is rendered in, to sample colors from and to place
+// it. The whole script is stretched over it.
+const LINE_PREVIEW_WIDTH: u32 = 640;
+const LINE_PREVIEW_HEIGHT: u32 = 360;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct SubsControlsValues {
-    pub style_list: StyleList,
-    pub actor_list: ActorList,
+    pub style: String,
+    pub actor: String,
     pub layer: usize,
     pub margin_left: usize,
     pub margin_vertical: usize,
     pub margin_right: usize,
+    pub effect: String,
     pub start_time: String,
     pub end_time: String,
     pub duration: String,
@@ -33,27 +104,127 @@ pub struct SubsControlsValues {
 impl SubsControlsValues {
     pub fn new() -> Self {
         Self {
+            style: DEFAULT_STYLE.to_owned(),
             start_time: "00:00:00:00".to_owned(),
             end_time: "00:00:00:00".to_owned(),
             duration: "00:00:00:00".to_owned(),
             ..Self::default()
         }
     }
+
+    pub fn start(&self) -> Option<Time> {
+        self.start_time.parse().ok()
+    }
+
+    pub fn end(&self) -> Option<Time> {
+        self.end_time.parse().ok()
+    }
+
+    pub fn duration(&self) -> Option<Time> {
+        self.duration.parse().ok()
+    }
+
+    /// Reading speed computed from the start and end times, the duration
+    /// field is only a hint for the user.
+    pub fn cps(&self, settings: &CpsSettings) -> Option<f32> {
+        let (start, end) = (self.start()?, self.end()?);
+        settings.cps(&self.text, end.saturating_sub(start))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     // Subs Controller events
     SubsControls(subs_controls::Message),
+    // Filter bar events
+    FilterBar(filter_bar::Message),
+    // CPS settings events
+    CpsControls(cps::Message),
+    // Timing check settings events
+    TimingControls(timing_check::Message),
     // Table viewer events
-    TableViewerClicked(usize),
+    TableViewer(table_viewer::Message),
+    // Keyboard shortcuts
+    LineAction(LineAction),
+    // Undo and redo
+    History(history::Message),
+    // Style manager events
+    StyleManager(style_manager::Message),
+    // Actor manager events
+    ActorManager(actor_manager::Message),
+    // Typesetting tools events
+    Typesetter(typesetting::Message),
+    // Motion tracking import events
+    MotionImporter(motion::Message),
+    // Karaoke timing events
+    KaraokeEditor(karaoke::Message),
+    // Kanji timer events
+    KanjiTimer(kanji_timer::Message),
+    // Automation scripts events
+    Automation(automation::Message),
+    // Time shifting events
+    TimeShifter(time_shift::Message),
+    // Frame rate conversion events
+    Retimer(retime::Message),
+    // Timing post-processing events
+    TimingProcessor(timing_processor::Message),
+    // Reference sync events
+    ReferenceSync(reference_sync::Message),
+}
+
+// What is shown below the line editor, the table or a tool replacing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Panel {
+    Table,
+    StyleManager,
+    ActorManager,
+    Typesetter,
+    MotionImporter,
+    KaraokeEditor,
+    KanjiTimer,
+    Automation,
+    TimeShifter,
+    Retimer,
+    TimingProcessor,
+    ReferenceSync,
 }
 
 pub struct SubsViewer {
     subs_controls: SubsControls,
-    table_viewer: table_viewer2::State,
+    filter_bar: FilterBar,
+    cps_controls: CpsControls,
+    timing_controls: TimingControls,
+    table_viewer: TableViewer,
+    history_panel: HistoryPanel,
+    style_manager: StyleManager,
+    actor_manager: ActorManager,
+    typesetter: Typesetter,
+    motion_importer: MotionImporter,
+    karaoke_editor: KaraokeEditor,
+    kanji_timer: KanjiTimer,
+    automation: Automation,
+    time_shifter: TimeShifter,
+    retimer: Retimer,
+    timing_processor: TimingProcessor,
+    reference_sync: ReferenceSync,
+    line_previewer: Previewer,
     controls_values: Vec<SubsControlsValues>,
+    filter_values: FilterValues,
+    cps_settings: CpsSettings,
+    timing_settings: TimingSettings,
+    sort: Option<Sort>,
+    tag_display: TagDisplay,
+    history: History,
+    styles: Vec<AssStyle>,
+    // PlayResX and PlayResY, what positions and sizes refer to
+    resolution: (u32, u32),
+    // Panel shown in place of the table
+    panel: Panel,
+    // Selected lines, never empty. Only lines shown next to each other in the
+    // table are selected together.
     focused_subs: Range<usize>,
+    // Line shown in the subs controls, always inside `focused_subs`
+    active_sub: usize,
 }
 
 impl SubsViewer {
@@ -65,55 +236,577 @@ impl SubsViewer {
         ];
         Self {
             subs_controls: SubsControls::default(),
-            table_viewer: table_viewer2::State::new(),
+            filter_bar: FilterBar::default(),
+            cps_controls: CpsControls::default(),
+            timing_controls: TimingControls::default(),
+            table_viewer: TableViewer::default(),
+            history_panel: HistoryPanel::default(),
+            style_manager: StyleManager::new(),
+            actor_manager: ActorManager::default(),
+            typesetter: Typesetter::default(),
+            motion_importer: MotionImporter::default(),
+            karaoke_editor: KaraokeEditor::default(),
+            kanji_timer: KanjiTimer::default(),
+            automation: Automation::default(),
+            time_shifter: TimeShifter::default(),
+            retimer: Retimer::default(),
+            timing_processor: TimingProcessor::default(),
+            reference_sync: ReferenceSync::default(),
+            line_previewer: Previewer::new(LINE_PREVIEW_WIDTH, LINE_PREVIEW_HEIGHT),
             controls_values,
-            focused_subs: Range { start: 0, end: 0 },
+            filter_values: FilterValues::default(),
+            cps_settings: CpsSettings::default(),
+            timing_settings: TimingSettings::default(),
+            sort: None,
+            tag_display: TagDisplay::Verbatim,
+            history: History::default(),
+            styles: vec![AssStyle::default()],
+            resolution: DEFAULT_RESOLUTION,
+            panel: Panel::Table,
+            focused_subs: Range { start: 0, end: 1 },
+            active_sub: 0,
         }
     }
 
     pub fn update(&mut self, message: Message) {
         match message {
+            Message::SubsControls(subs_controls::Message::TextSubmitted) => {
+                self.apply_line_action(LineAction::CommitAndNext)
+            }
+            Message::SubsControls(subs_controls::Message::ManageStyles) => {
+                let style = &self.controls_values[self.active_sub].style;
+                self.style_manager.open(&self.styles, style);
+                self.panel = Panel::StyleManager;
+            }
+            Message::SubsControls(subs_controls::Message::ManageActors) => {
+                let actor = &self.controls_values[self.active_sub].actor;
+                self.actor_manager.open(actor);
+                self.panel = Panel::ActorManager;
+            }
+            Message::SubsControls(subs_controls::Message::Typeset) => {
+                self.panel = Panel::Typesetter
+            }
+            Message::SubsControls(subs_controls::Message::TrackMotion) => {
+                self.panel = Panel::MotionImporter
+            }
+            Message::SubsControls(subs_controls::Message::EditKaraoke) => {
+                self.panel = Panel::KaraokeEditor
+            }
+            Message::SubsControls(subs_controls::Message::TimeKanji) => {
+                self.panel = Panel::KanjiTimer
+            }
+            Message::SubsControls(subs_controls::Message::Automate) => {
+                self.automation.open();
+                self.panel = Panel::Automation;
+            }
+            Message::SubsControls(subs_controls::Message::ShiftTimes) => {
+                self.panel = Panel::TimeShifter
+            }
+            Message::SubsControls(subs_controls::Message::Retime) => self.panel = Panel::Retimer,
+            Message::SubsControls(subs_controls::Message::ProcessTiming) => {
+                self.panel = Panel::TimingProcessor
+            }
+            Message::SubsControls(subs_controls::Message::SyncToReference) => {
+                self.panel = Panel::ReferenceSync
+            }
+            Message::SubsControls(subs_controls::Message::ActorSubmitted) => {
+                let typed = &self.controls_values[self.active_sub].actor;
+                let actors = actor_manager::actor_names(&self.controls_values);
+                if let Some(actor) = subs_controls::complete_actor(&actors, typed).first() {
+                    let message = subs_controls::Message::ActorPicked(actor.to_string());
+                    self.history.seal();
+                    self.update(Message::SubsControls(message));
+                }
+            }
+            Message::SubsControls(subs_controls::Message::PickTagColor(tag)) => {
+                let style = self.line_style(self.active_sub);
+                let frame = self.line_preview(self.active_sub);
+                self.subs_controls
+                    .open_color_picker(tag, tag.style_color(&style), frame);
+            }
+            Message::SubsControls(subs_controls::Message::ColorPicker(message)) => {
+                if let Some((tag, color)) = self.subs_controls.update_color_picker(message) {
+                    self.apply_format(Format::Color(tag, color));
+                }
+            }
+            Message::SubsControls(message) if message.is_formatting() => {
+                if let Some(format) = self.subs_controls.update_toolbar(message) {
+                    self.apply_format(format);
+                }
+            }
             Message::SubsControls(message) => {
-                let focus_sub = if self.focused_subs.start == self.focused_subs.end {
-                    self.focused_subs.start
+                let affected = if message.applies_to_selection() {
+                    self.focused_subs.clone()
+                } else {
+                    self.active_sub..self.active_sub + 1
+                };
+                let name = match affected.len() {
+                    1 => format!("Change {}", message.field()),
+                    lines => format!("Change {} on {} lines", message.field(), lines),
+                };
+                let typing = if message.is_typing() {
+                    Some(message.field())
                 } else {
-                    self.focused_subs.end
+                    None
                 };
 
-                self.subs_controls
-                    .update(message, &mut self.controls_values[focus_sub])
+                self.record(name, typing, affected.clone(), |viewer| {
+                    for row in affected {
+                        viewer
+                            .subs_controls
+                            .update(message.clone(), &mut viewer.controls_values[row]);
+                    }
+                });
             }
-            Message::TableViewerClicked(row) => {
-                self.focused_subs = Range {
-                    start: row,
-                    end: row,
-                };
+            Message::FilterBar(message) => {
+                self.filter_bar.update(message, &mut self.filter_values);
+                self.clamp_selection();
+            }
+            Message::CpsControls(message) => {
+                self.cps_controls.update(message, &mut self.cps_settings)
+            }
+            Message::TimingControls(message) => self
+                .timing_controls
+                .update(message, &mut self.timing_settings),
+            Message::TableViewer(table_viewer::Message::CellClicked(row)) => {
+                self.history.seal();
+                self.select(row);
+            }
+            Message::TableViewer(table_viewer::Message::HeaderClicked(column)) => {
+                if let Some(column) = SortColumn::from_header(column) {
+                    self.sort = Sort::toggle(self.sort, column);
+                    self.clamp_selection();
+                }
+            }
+            Message::TableViewer(table_viewer::Message::TagDisplayPicked(tag_display)) => {
+                self.tag_display = tag_display
+            }
+            Message::LineAction(action) => self.apply_line_action(action),
+            Message::History(message) => {
+                self.update_history(message);
+                self.clamp_selection();
+                self.style_manager.refresh(&self.styles);
+            }
+            Message::StyleManager(message) => {
+                let event = self.style_manager.update(message, &self.styles);
+                if !matches!(
+                    event,
+                    Some(style_manager::Event::Edited {
+                        typing: Some(_),
+                        ..
+                    })
+                ) {
+                    self.history.seal();
+                }
+                match event {
+                    Some(style_manager::Event::Edited {
+                        name,
+                        styles,
+                        typing,
+                    }) => self.record(name, typing, 0..0, |viewer| viewer.styles = styles),
+                    Some(style_manager::Event::Renamed { from, to }) => {
+                        self.rename_style(&from, &to);
+                        self.style_manager.refresh(&self.styles);
+                    }
+                    Some(style_manager::Event::Deleted(name)) => {
+                        self.delete_style(&name);
+                        self.style_manager.refresh(&self.styles);
+                    }
+                    Some(style_manager::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::ActorManager(message) => match self.actor_manager.update(message) {
+                Some(actor_manager::Event::Renamed { from, to }) => self.rename_actor(&from, &to),
+                Some(actor_manager::Event::Closed) => self.panel = Panel::Table,
+                None => {}
+            },
+            Message::Typesetter(message) => {
+                // A whole drag is undone at once
+                if !matches!(message, typesetting::Message::Drag(handles::Drag::Moved(_))) {
+                    self.history.seal();
+                }
+                let row = self.active_sub;
+                let style = self.line_style(row);
+                let text = &self.controls_values[row].text;
+                match self
+                    .typesetter
+                    .update(message, text, &style, self.resolution)
+                {
+                    Some(typesetting::Event::Edited { name, text }) => {
+                        self.record(name, Some("typesetting"), row..row + 1, |viewer| {
+                            viewer.controls_values[row].text = text
+                        });
+                    }
+                    Some(typesetting::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::MotionImporter(message) => {
+                let row = self.active_sub;
+                let style = self.line_style(row);
+                let line = &self.controls_values[row];
+                match self
+                    .motion_importer
+                    .update(message, line, &style, self.resolution)
+                {
+                    Some(motion::Event::Tracked(lines)) => {
+                        let name = format!("Track motion on {} lines", lines.len());
+                        self.history.seal();
+                        self.record(name, None, row..row + 1, |viewer| {
+                            let count = lines.len();
+                            viewer.controls_values.splice(row..row + 1, lines);
+                            viewer.focused_subs = row..row + count;
+                            viewer.active_sub = row;
+                        });
+                    }
+                    Some(motion::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::KaraokeEditor(message) => {
+                // A whole drag is undone at once
+                if !matches!(message, karaoke::Message::Drag(handles::Drag::Moved(_))) {
+                    self.history.seal();
+                }
+                let row = self.active_sub;
+                match self
+                    .karaoke_editor
+                    .update(message, &self.controls_values[row])
+                {
+                    Some(karaoke::Event::Edited { name, text }) => {
+                        self.record(name, Some("karaoke"), row..row + 1, |viewer| {
+                            viewer.controls_values[row].text = text
+                        });
+                    }
+                    Some(karaoke::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            // Its keys do nothing while it is hidden
+            Message::KanjiTimer(_) if self.panel != Panel::KanjiTimer => {}
+            Message::KanjiTimer(message) => {
+                let rows = self.kanji_timer_rows();
+                let values = &self.controls_values;
+                let lines =
+                    rows.map(|(source, destination)| (&values[source], &values[destination]));
+                match self.kanji_timer.update(message, lines) {
+                    Some(kanji_timer::Event::Timed(text)) => {
+                        if let Some((_, row)) = rows {
+                            self.history.seal();
+                            let name = "Copy karaoke timing".to_owned();
+                            self.record(name, None, row..row + 1, |viewer| {
+                                viewer.controls_values[row].text = text
+                            });
+                        }
+                    }
+                    Some(kanji_timer::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::Automation(message) => {
+                let event = self.automation.update(
+                    message,
+                    &self.controls_values,
+                    &self.focused_subs,
+                    self.active_sub,
+                );
+                match event {
+                    Some(automation::Event::Ran {
+                        name,
+                        lines,
+                        selection,
+                        active,
+                    }) => {
+                        self.history.seal();
+                        let len = self.controls_values.len();
+                        self.record(name, None, 0..len, |viewer| {
+                            viewer.controls_values = lines;
+                            viewer.focused_subs = selection;
+                            viewer.active_sub = active;
+                        });
+                    }
+                    Some(automation::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::TimeShifter(message) => {
+                match self
+                    .time_shifter
+                    .update(message, &self.controls_values, &self.focused_subs)
+                {
+                    Some(time_shift::Event::Shifted { name, rows, lines }) => {
+                        self.history.seal();
+                        self.record(name, None, rows.clone(), |viewer| {
+                            viewer.controls_values[rows].clone_from_slice(&lines)
+                        });
+                    }
+                    Some(time_shift::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::Retimer(message) => {
+                match self.retimer.update(message, &self.controls_values) {
+                    Some(retime::Event::Retimed { name, lines }) => {
+                        self.history.seal();
+                        let len = self.controls_values.len();
+                        self.record(name, None, 0..len, |viewer| viewer.controls_values = lines);
+                    }
+                    Some(retime::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::TimingProcessor(message) => {
+                match self.timing_processor.update(
+                    message,
+                    &self.controls_values,
+                    &self.focused_subs,
+                ) {
+                    Some(timing_processor::Event::Processed { name, rows, lines }) => {
+                        self.history.seal();
+                        self.record(name, None, rows.clone(), |viewer| {
+                            viewer.controls_values[rows].clone_from_slice(&lines)
+                        });
+                    }
+                    Some(timing_processor::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
+            }
+            Message::ReferenceSync(message) => {
+                match self.reference_sync.update(message, &self.controls_values) {
+                    Some(reference_sync::Event::Synced { name, lines }) => {
+                        self.history.seal();
+                        let len = self.controls_values.len();
+                        self.record(name, None, 0..len, |viewer| viewer.controls_values = lines);
+                    }
+                    Some(reference_sync::Event::Closed) => self.panel = Panel::Table,
+                    None => {}
+                }
             }
         }
+
+        // The typesetter follows the active line and its edits
+        if self.panel == Panel::Typesetter {
+            let frame = self.line_preview(self.active_sub);
+            self.typesetter.set_frame(frame);
+        }
+    }
+
+    // The kanji timer copies the first selected line to the second, in
+    // display order
+    fn kanji_timer_rows(&self) -> Option<(usize, usize)> {
+        let mut selected = self
+            .visible_rows()
+            .into_iter()
+            .filter(|row| self.focused_subs.contains(row));
+        Some((selected.next()?, selected.next()?))
+    }
+
+    // Renders `row` alone, as placed by its style and tags
+    fn line_preview(&self, row: usize) -> Option<PreviewFrame> {
+        let style = self.line_style(row);
+        let (width, height) = self.resolution;
+        let script = style.sample_script(&self.controls_values[row].text, width, height);
+        self.line_previewer.render(&script)
+    }
+
+    // Lines keep using a style after it is renamed
+    fn rename_style(&mut self, from: &str, to: &str) {
+        let name = format!("Rename style {} to {}", from, to);
+        self.history.seal();
+        self.record(name, None, 0..self.controls_values.len(), |viewer| {
+            viewer
+                .styles
+                .iter_mut()
+                .filter(|style| style.name == from)
+                .for_each(|style| style.name = to.to_owned());
+            viewer
+                .controls_values
+                .iter_mut()
+                .filter(|line| line.style == from)
+                .for_each(|line| line.style = to.to_owned());
+        });
+    }
+
+    // Lines of a deleted style move to the first remaining one
+    fn delete_style(&mut self, name: &str) {
+        let entry = format!("Delete style {}", name);
+        self.history.seal();
+        self.record(entry, None, 0..self.controls_values.len(), |viewer| {
+            viewer.styles.retain(|style| style.name != name);
+            let fallback = &viewer.styles[0].name;
+            viewer
+                .controls_values
+                .iter_mut()
+                .filter(|line| line.style == name)
+                .for_each(|line| line.style = fallback.clone());
+        });
+    }
+
+    // Style of the line `row`, the default one when it is missing
+    fn line_style(&self, row: usize) -> AssStyle {
+        let name = &self.controls_values[row].style;
+        self.styles
+            .iter()
+            .find(|style| &style.name == name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Applies a toolbar format to the text selection of the active line
+    fn apply_format(&mut self, format: Format) {
+        let row = self.active_sub;
+        let style = self.line_style(row);
+        let text = &self.controls_values[row].text;
+        let selection = self.subs_controls.text_selection(text);
+        let (text, selection) = formatting::apply(text, selection, &format, &style, &self.styles);
+
+        self.history.seal();
+        self.record(format!("Apply {}", format), None, row..row + 1, |viewer| {
+            viewer.controls_values[row].text = text;
+        });
+        self.subs_controls.select_text(selection);
+    }
+
+    fn rename_actor(&mut self, from: &str, to: &str) {
+        let name = format!("Rename actor {} to {}", from, to);
+        self.history.seal();
+        self.record(name, None, 0..self.controls_values.len(), |viewer| {
+            viewer
+                .controls_values
+                .iter_mut()
+                .filter(|line| line.actor == from)
+                .for_each(|line| line.actor = to.to_owned());
+        });
+    }
+
+    // Indices of the lines passing the filter, in display order. Sorting
+    // never touches the order of `controls_values`.
+    fn visible_rows(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = self
+            .controls_values
+            .iter()
+            .enumerate()
+            .filter(|(_, sub)| self.filter_values.matches(sub))
+            .map(|(row, _)| row)
+            .collect();
+
+        if let Some(sort) = self.sort {
+            // Stable sort, so equal lines keep their document order
+            rows.sort_by(|&a, &b| {
+                sort.compare(
+                    &self.controls_values[a],
+                    &self.controls_values[b],
+                    &self.cps_settings,
+                )
+            });
+        }
+
+        rows
     }
 
     pub fn view(&mut self) -> Element<Message> {
-        let focus_sub = if self.focused_subs.start == self.focused_subs.end {
-            self.focused_subs.start
-        } else {
-            self.focused_subs.end
-        };
+        let rows = self.visible_rows();
+        let timing_issues = self.timing_settings.check(&self.controls_values);
+        let style_names = self.styles.iter().map(|style| style.name.clone()).collect();
+        let actors = actor_manager::actor_names(&self.controls_values);
+        let active_style = self.line_style(self.active_sub);
+        // Before the panels borrow their own state
+        let kanji_timer_rows = self.kanji_timer_rows();
         let content = Column::new()
             .padding(PADDING)
             .spacing(COLUMN_SPACING)
             .align_items(Align::Center)
             .push(
                 self.subs_controls
-                    .view(&self.controls_values[focus_sub])
+                    .view(&self.controls_values[self.active_sub], style_names, &actors)
                     .map(move |message| Message::SubsControls(message)),
             )
             .push(
-                TableViewer::new(
-                    &mut self.table_viewer,
-                    &self.controls_values,
-                    &self.focused_subs,
-                )
-                .on_click(Message::TableViewerClicked),
+                self.filter_bar
+                    .view(&self.filter_values)
+                    .map(Message::FilterBar),
+            )
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .align_items(Align::Center)
+                    .push(
+                        self.cps_controls
+                            .view(&self.cps_settings)
+                            .map(Message::CpsControls),
+                    )
+                    .push(
+                        self.timing_controls
+                            .view(&self.timing_settings)
+                            .map(Message::TimingControls),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(match self.panel {
+                        Panel::StyleManager => self
+                            .style_manager
+                            .view(&self.styles)
+                            .map(Message::StyleManager),
+                        Panel::ActorManager => self
+                            .actor_manager
+                            .view(&self.controls_values)
+                            .map(Message::ActorManager),
+                        Panel::Automation => self.automation.view().map(Message::Automation),
+                        Panel::TimeShifter => self.time_shifter.view().map(Message::TimeShifter),
+                        Panel::Retimer => self.retimer.view().map(Message::Retimer),
+                        Panel::TimingProcessor => {
+                            self.timing_processor.view().map(Message::TimingProcessor)
+                        }
+                        Panel::ReferenceSync => {
+                            self.reference_sync.view().map(Message::ReferenceSync)
+                        }
+                        Panel::KanjiTimer => {
+                            let values = &self.controls_values;
+                            let lines = kanji_timer_rows.map(|(source, destination)| {
+                                (&values[source], &values[destination])
+                            });
+                            self.kanji_timer.view(lines).map(Message::KanjiTimer)
+                        }
+                        Panel::KaraokeEditor => self
+                            .karaoke_editor
+                            .view(&self.controls_values[self.active_sub])
+                            .map(Message::KaraokeEditor),
+                        Panel::MotionImporter => {
+                            self.motion_importer.view().map(Message::MotionImporter)
+                        }
+                        Panel::Typesetter => self
+                            .typesetter
+                            .view(
+                                &self.controls_values[self.active_sub].text,
+                                &active_style,
+                                self.resolution,
+                            )
+                            .map(Message::Typesetter),
+                        Panel::Table => self
+                            .table_viewer
+                            .view(
+                                &self.controls_values,
+                                &rows,
+                                &self.focused_subs,
+                                DisplaySettings {
+                                    sort: self.sort,
+                                    tag_display: self.tag_display,
+                                    cps_settings: &self.cps_settings,
+                                    timing_issues: &timing_issues,
+                                },
+                            )
+                            .map(Message::TableViewer),
+                    })
+                    .push(
+                        Container::new(
+                            self.history_panel.view(&self.history).map(Message::History),
+                        )
+                        .width(Length::Units(HISTORY_WIDTH))
+                        .height(Length::Fill),
+                    ),
             );
 
         Container::new(content)
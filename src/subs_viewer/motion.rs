@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use iced::{
+    button, text_input, Align, Button, Checkbox, Column, Element, Length, Point, Row, Text,
+    TextInput,
+};
+use thiserror::Error;
+
+use super::drawing::{Drawing, Segment};
+use super::frame_rate::FrameRate;
+use super::styles::AssStyle;
+use super::time::Time;
+use super::timing_tools;
+use super::typesetting::{self, Clip, Placement, Tool};
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const FRAME_RATE_WIDTH: u16 = 100;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Not After Effects keyframe data")]
+    NotKeyframeData,
+    #[error("The keyframe data has no position")]
+    NoPosition,
+    #[error("Line {0}: invalid keyframe")]
+    InvalidKeyframe(usize),
+    #[error("Cannot read the keyframe file: {0}")]
+    Io(#[from] io::Error),
+}
+
+// Keyframe sections that are applied, the others are skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Position,
+    Scale,
+    Rotation,
+    Other,
+}
+
+/// Tracked state of a frame, in After Effects units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Keyframe {
+    position: Point,
+    scale_x: f32,
+    scale_y: f32,
+    /// Clockwise, in degrees.
+    rotation: f32,
+}
+
+/// Keyframe data exported by After Effects, one keyframe per frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tracking {
+    /// Frame rate the data was exported with.
+    pub frame_rate: Option<FrameRate>,
+    width: Option<f32>,
+    height: Option<f32>,
+    keyframes: Vec<Keyframe>,
+}
+
+impl FromStr for Tracking {
+    type Err = Error;
+
+    // Pasted data may have lost its tabs, so fields are split on any space
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        let mut lines = data
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        match lines.next() {
+            Some((_, header))
+                if header.starts_with("Adobe After Effects")
+                    && header.ends_with("Keyframe Data") => {}
+            _ => return Err(Error::NotKeyframeData),
+        }
+
+        let mut frame_rate = None;
+        let mut width = None;
+        let mut height = None;
+        let mut section = Section::Other;
+        let mut positions = BTreeMap::new();
+        let mut scales = BTreeMap::new();
+        let mut rotations = BTreeMap::new();
+
+        for (number, line) in lines {
+            if line.starts_with("End of Keyframe Data") {
+                break;
+            } else if let Some(name) = line.strip_prefix("Transform") {
+                section = match name.trim() {
+                    "Position" => Section::Position,
+                    "Scale" => Section::Scale,
+                    "Rotation" => Section::Rotation,
+                    _ => Section::Other,
+                };
+            } else if line.starts_with("Effects") {
+                section = Section::Other;
+            } else if line.starts_with("Units Per Second") {
+                frame_rate = setting(line, "Units Per Second");
+            } else if line.starts_with("Source Width") {
+                width = setting(line, "Source Width");
+            } else if line.starts_with("Source Height") {
+                height = setting(line, "Source Height");
+            } else {
+                // Column names and other settings are not numbers
+                let numbers: Vec<f32> = match line.split_whitespace().map(str::parse).collect() {
+                    Ok(numbers) => numbers,
+                    Err(_) => continue,
+                };
+                let frame = numbers[0].max(0.0) as u64;
+                let values = match section {
+                    Section::Other => continue,
+                    Section::Rotation => 1,
+                    Section::Position | Section::Scale => 2,
+                };
+                if numbers.len() < 1 + values {
+                    return Err(Error::InvalidKeyframe(number));
+                }
+
+                match section {
+                    Section::Position => {
+                        positions.insert(frame, Point::new(numbers[1], numbers[2]));
+                    }
+                    Section::Scale => {
+                        scales.insert(frame, (numbers[1], numbers[2]));
+                    }
+                    Section::Rotation => {
+                        rotations.insert(frame, numbers[1]);
+                    }
+                    Section::Other => {}
+                }
+            }
+        }
+
+        let (&first, &last) = match (positions.keys().next(), positions.keys().next_back()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(Error::NoPosition),
+        };
+
+        // Values hold until their next keyframe
+        fn at<T: Copy>(values: &BTreeMap<u64, T>, frame: u64) -> Option<T> {
+            values
+                .range(..=frame)
+                .next_back()
+                .or_else(|| values.iter().next())
+                .map(|(_, &value)| value)
+        }
+
+        let keyframes = (first..=last)
+            .filter_map(|frame| {
+                let (scale_x, scale_y) = at(&scales, frame).unwrap_or((100.0, 100.0));
+                Some(Keyframe {
+                    position: at(&positions, frame)?,
+                    scale_x,
+                    scale_y,
+                    rotation: at(&rotations, frame).unwrap_or(0.0),
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            frame_rate,
+            width,
+            height,
+            keyframes,
+        })
+    }
+}
+
+// Value of the `name` setting on `line`
+fn setting<T: FromStr>(line: &str, name: &str) -> Option<T> {
+    line.strip_prefix(name)
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Tracked properties applied to the lines, the position always is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub scale: bool,
+    pub rotation: bool,
+    pub clip: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scale: true,
+            rotation: true,
+            clip: true,
+        }
+    }
+}
+
+impl Tracking {
+    // Keyframe of the `index`th frame in pixels of a script of `resolution`,
+    // the last one holds
+    fn keyframe(&self, index: usize, resolution: (u32, u32)) -> Keyframe {
+        let keyframe = self.keyframes[index.min(self.keyframes.len() - 1)];
+        let ratio_x = self.width.map_or(1.0, |width| resolution.0 as f32 / width);
+        let ratio_y = self
+            .height
+            .map_or(1.0, |height| resolution.1 as f32 / height);
+        Keyframe {
+            position: Point::new(keyframe.position.x * ratio_x, keyframe.position.y * ratio_y),
+            ..keyframe
+        }
+    }
+
+    /// Splits `line` into one line per frame following the tracked motion,
+    /// its first frame matching the first keyframe. Frames past the data keep
+    /// the last keyframe.
+    pub fn apply(
+        &self,
+        line: &SubsControlsValues,
+        style: &AssStyle,
+        frame_rate: FrameRate,
+        options: Options,
+        resolution: (u32, u32),
+    ) -> Vec<SubsControlsValues> {
+        let start = line.start().unwrap_or(Time::ZERO);
+        let end = line.end().unwrap_or(start);
+        let first = frame_rate.frame_at(start);
+        let last = frame_rate.frame_at(end);
+
+        let placement = Placement::read(&line.text, style, resolution);
+        let reference = self.keyframe(0, resolution);
+
+        (first..last)
+            .map(|frame| {
+                let keyframe = self.keyframe((frame - first) as usize, resolution);
+                let mut tracked = line.clone();
+                tracked.text = moved(&line.text, &placement, reference, keyframe, options);
+                tracked.set_times(
+                    frame_rate.time_of(frame).max(start),
+                    frame_rate.time_of(frame + 1).min(end),
+                );
+                tracked
+            })
+            .collect()
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.keyframes.len()
+    }
+}
+
+// `text` placed at `keyframe` instead of `reference`
+fn moved(
+    text: &str,
+    placement: &Placement,
+    reference: Keyframe,
+    keyframe: Keyframe,
+    options: Options,
+) -> String {
+    let ratio = |value: f32, reference: f32| {
+        if options.scale && reference != 0.0 {
+            value / reference
+        } else {
+            1.0
+        }
+    };
+    let scale_x = ratio(keyframe.scale_x, reference.scale_x);
+    let scale_y = ratio(keyframe.scale_y, reference.scale_y);
+    let rotation = if options.rotation {
+        keyframe.rotation - reference.rotation
+    } else {
+        0.0
+    };
+
+    // Points keep their place relative to the tracked one, which scales and
+    // turns them around it
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let follow = |point: Point| {
+        let x = (point.x - reference.position.x) * scale_x;
+        let y = (point.y - reference.position.y) * scale_y;
+        Point::new(
+            keyframe.position.x + x * cos - y * sin,
+            keyframe.position.y + x * sin + y * cos,
+        )
+    };
+
+    let mut tracked = placement.clone();
+    tracked.position = follow(placement.position);
+    tracked.movement = None;
+    tracked.scale_x *= scale_x;
+    tracked.scale_y *= scale_y;
+    // `\frz` turns counterclockwise
+    tracked.rotation_z -= rotation;
+    tracked.clip = placement.clip.as_ref().map(|clip| match clip {
+        Clip::Rectangle { inverse, from, to } if rotation == 0.0 => Clip::Rectangle {
+            inverse: *inverse,
+            from: follow(*from),
+            to: follow(*to),
+        },
+        // A turned rectangle needs a vector clip
+        Clip::Rectangle { inverse, from, to } => {
+            let mut drawing = Drawing::default();
+            let corners = [
+                *from,
+                Point::new(to.x, from.y),
+                *to,
+                Point::new(from.x, to.y),
+            ];
+            for &corner in &corners {
+                drawing.push(Segment::Line, follow(corner), false);
+            }
+            Clip::Vector {
+                inverse: *inverse,
+                drawing,
+            }
+        }
+        Clip::Vector { inverse, drawing } => {
+            let mut drawing = drawing.clone();
+            drawing.map_points(&follow);
+            Clip::Vector {
+                inverse: *inverse,
+                drawing,
+            }
+        }
+    });
+
+    let mut tools = vec![Tool::Position];
+    if options.scale {
+        tools.push(Tool::Scale);
+    }
+    if options.rotation {
+        tools.push(Tool::RotateZ);
+    }
+    if options.clip && tracked.clip.is_some() {
+        tools.push(Tool::Clip);
+    }
+
+    tools.into_iter().fold(text.to_owned(), |text, tool| {
+        typesetting::write(&text, tool, &tracked.tags(tool))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Lines replacing the active one.
+    Tracked(Vec<SubsControlsValues>),
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    DataChanged(String),
+    PathChanged(String),
+    Load,
+    FrameRateChanged(String),
+    ScaleToggled(bool),
+    RotationToggled(bool),
+    ClipToggled(bool),
+    Apply,
+    Close,
+}
+
+/// Applies After Effects tracking data to the active line, pasted or loaded
+/// from a file.
+#[derive(Default)]
+pub struct MotionImporter {
+    data: text_input::State,
+    path: text_input::State,
+    load: button::State,
+    frame_rate: text_input::State,
+    apply: button::State,
+    close: button::State,
+    data_input: String,
+    path_input: String,
+    frame_rate_input: String,
+    options: Options,
+    // Outcome of the last operation
+    status: String,
+}
+
+impl MotionImporter {
+    // Checks the data, taking its frame rate when none was typed
+    fn read_data(&mut self, data: String) {
+        self.status = match data.parse::<Tracking>() {
+            Ok(tracking) => {
+                timing_tools::prefill_frame_rate(&mut self.frame_rate_input, tracking.frame_rate);
+                format!("{} keyframes", tracking.keyframe_count())
+            }
+            Err(error) => error.to_string(),
+        };
+        self.data_input = data;
+    }
+
+    /// Tracks `line`, the active line of style `style`, in a script of
+    /// `resolution`.
+    pub fn update(
+        &mut self,
+        message: Message,
+        line: &SubsControlsValues,
+        style: &AssStyle,
+        resolution: (u32, u32),
+    ) -> Option<Event> {
+        match message {
+            Message::DataChanged(data) => self.read_data(data),
+            Message::PathChanged(path) => self.path_input = path,
+            Message::Load => match fs::read_to_string(self.path_input.trim()) {
+                Ok(data) => self.read_data(data),
+                Err(error) => self.status = Error::from(error).to_string(),
+            },
+            Message::FrameRateChanged(frame_rate) => self.frame_rate_input = frame_rate,
+            Message::ScaleToggled(scale) => self.options.scale = scale,
+            Message::RotationToggled(rotation) => self.options.rotation = rotation,
+            Message::ClipToggled(clip) => self.options.clip = clip,
+            Message::Apply => {
+                let tracking = self.data_input.parse::<Tracking>();
+                let frame_rate = self.frame_rate_input.parse::<FrameRate>();
+                let (tracking, frame_rate) = match (
+                    tracking.map_err(|error| error.to_string()),
+                    frame_rate.map_err(|error| error.to_string()),
+                ) {
+                    (Ok(tracking), Ok(frame_rate)) => (tracking, frame_rate),
+                    (Err(error), _) | (_, Err(error)) => {
+                        self.status = error;
+                        return None;
+                    }
+                };
+
+                let lines = tracking.apply(line, style, frame_rate, self.options, resolution);
+                if lines.is_empty() {
+                    self.status = "The line does not last a frame".to_owned();
+                    return None;
+                }
+                self.status = if lines.len() == tracking.keyframe_count() {
+                    format!("Tracked {} frames", lines.len())
+                } else {
+                    format!(
+                        "Tracked {} frames with {} keyframes",
+                        lines.len(),
+                        tracking.keyframe_count()
+                    )
+                };
+                return Some(Event::Tracked(lines));
+            }
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let file = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.path,
+                    "Keyframe file path",
+                    &self.path_input,
+                    Message::PathChanged,
+                )
+                .on_submit(Message::Load)
+                .size(TEXT_SIZE),
+            )
+            .push(
+                Button::new(&mut self.load, Text::new("Load").size(TEXT_SIZE))
+                    .on_press(Message::Load),
+            );
+
+        let options = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("Frame rate").size(TEXT_SIZE))
+            .push(
+                TextInput::new(
+                    &mut self.frame_rate,
+                    "23.976",
+                    &self.frame_rate_input,
+                    Message::FrameRateChanged,
+                )
+                .width(Length::Units(FRAME_RATE_WIDTH))
+                .size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(self.options.scale, "Scale", Message::ScaleToggled)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(self.options.rotation, "Rotation", Message::RotationToggled)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(self.options.clip, "Clip", Message::ClipToggled).text_size(TEXT_SIZE),
+            );
+
+        let actions = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Button::new(
+                    &mut self.apply,
+                    Text::new("Apply to the line").size(TEXT_SIZE),
+                )
+                .on_press(Message::Apply),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(&self.status).size(TEXT_SIZE));
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(
+                Text::new(
+                    "Split the line into one line per frame following After Effects keyframe data",
+                )
+                .size(TEXT_SIZE),
+            )
+            .push(
+                TextInput::new(
+                    &mut self.data,
+                    "Paste the keyframe data here",
+                    &self.data_input,
+                    Message::DataChanged,
+                )
+                .size(TEXT_SIZE),
+            )
+            .push(file)
+            .push(options)
+            .push(actions)
+            .into()
+    }
+}
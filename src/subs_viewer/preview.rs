@@ -0,0 +1,71 @@
+use iced::image;
+
+use crate::ass_renderer::AssRenderer;
+
+const PREVIEW_BACKGROUND: [u8; 4] = [0x55, 0x56, 0x59, 0xFF];
+
+/// A rendered frame, kept both as pixels and as an image handle.
+#[derive(Debug, Clone)]
+pub struct PreviewFrame {
+    pub width: u32,
+    pub height: u32,
+    // BGRA, as wanted by the image widget
+    pixels: Vec<u8>,
+    pub handle: image::Handle,
+}
+
+impl PreviewFrame {
+    /// BGRA value of the pixel at `(x, y)`.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let offset = ((y * self.width + x) * 4) as usize;
+        let pixel = self.pixels.get(offset..offset + 4)?;
+        Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+    }
+}
+
+/// Renders scripts to frames of a fixed size.
+pub struct Previewer {
+    renderer: Option<AssRenderer<'static>>,
+    width: u32,
+    height: u32,
+}
+
+impl Previewer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut renderer = AssRenderer::new().ok();
+        if let Some(renderer) = renderer.as_mut() {
+            let _ = renderer.set_source(width as usize, height as usize);
+        }
+
+        Self {
+            renderer,
+            width,
+            height,
+        }
+    }
+
+    /// First frame of `script`, `None` when libass is not available.
+    pub fn render(&self, script: &str) -> Option<PreviewFrame> {
+        let mut pixels = self
+            .renderer
+            .as_ref()?
+            .render_frame(script, 0, PREVIEW_BACKGROUND)
+            .ok()?;
+
+        // The renderer gives RGBA pixels
+        pixels
+            .chunks_exact_mut(4)
+            .for_each(|pixel| pixel.swap(0, 2));
+
+        Some(PreviewFrame {
+            width: self.width,
+            height: self.height,
+            handle: image::Handle::from_pixels(self.width, self.height, pixels.clone()),
+            pixels,
+        })
+    }
+}
@@ -0,0 +1,448 @@
+use std::path::Path;
+
+use iced::{
+    button, scrollable, text_input, Align, Button, Column, Element, Length, Row, Scrollable, Text,
+    TextInput,
+};
+
+use super::subtitle_file::SubtitleFile;
+use super::time::Time;
+use super::timing_tools::Change;
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const SEGMENTS_HEIGHT: u16 = 120;
+
+// Matching costs: a differing duration or gap costs up to 1, an unmatched
+// line `SKIP_COST`, and far apart lines up to `OFFSET_WEIGHT` more
+const DIFFERENCE_SCALE: f64 = 1000.0;
+const SKIP_COST: f64 = 0.8;
+const OFFSET_SCALE: f64 = 60_000.0;
+const OFFSET_WEIGHT: f64 = 0.3;
+// Gaps longer than this are all long pauses
+const MAX_GAP: f64 = 5000.0;
+// Matched lines also map their end when their durations are this close
+const END_TOLERANCE: i64 = 100;
+// A match moving lines this much more than both of its neighbors is wrong
+const OUTLIER_TOLERANCE: i64 = 500;
+// Points of a segment have offsets this close
+const SEGMENT_TOLERANCE: i64 = 100;
+// Reference text shown next to a change
+const MAX_REFERENCE_CHARS: usize = 50;
+
+/// A line with times, by row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Timed {
+    row: usize,
+    start: Time,
+    end: Time,
+}
+
+// Non comment lines with times, sorted by start
+fn timed_lines(lines: &[SubsControlsValues]) -> Vec<Timed> {
+    let mut timed: Vec<Timed> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.is_comment)
+        .filter_map(|(row, line)| match (line.start(), line.end()) {
+            (Some(start), Some(end)) if start <= end => Some(Timed { row, start, end }),
+            _ => None,
+        })
+        .collect();
+    timed.sort_by_key(|line| (line.start, line.row));
+    timed
+}
+
+// Duration, gap before and gap after of each line, in milliseconds
+fn features(lines: &[Timed]) -> Vec<[f64; 3]> {
+    let gap = |before: Option<&Timed>, after: Option<&Timed>| match (before, after) {
+        (Some(before), Some(after)) => (after.start.diff(before.end) as f64).max(0.0).min(MAX_GAP),
+        _ => MAX_GAP,
+    };
+    (0..lines.len())
+        .map(|index| {
+            let line = &lines[index];
+            let previous = index.checked_sub(1).map(|index| &lines[index]);
+            [
+                line.end.diff(line.start) as f64,
+                gap(previous, Some(line)),
+                gap(Some(line), lines.get(index + 1)),
+            ]
+        })
+        .collect()
+}
+
+/// Pairs of indices of `ours` and `reference` matched by their durations
+/// and the gaps around them, in order. Lines can be left unmatched, as
+/// lines split or joined by a translation.
+fn align(ours: &[Timed], reference: &[Timed]) -> Vec<(usize, usize)> {
+    let (our_features, reference_features) = (features(ours), features(reference));
+    let difference = |a: f64, b: f64| ((a - b).abs() / DIFFERENCE_SCALE).min(1.0);
+    let match_cost = |i: usize, j: usize| {
+        let ([duration, before, after], [reference_duration, reference_before, reference_after]) =
+            (our_features[i], reference_features[j]);
+        let offset = reference[j].start.diff(ours[i].start).abs() as f64;
+        difference(duration, reference_duration)
+            + 0.5 * (difference(before, reference_before) + difference(after, reference_after))
+            + OFFSET_WEIGHT * (offset / OFFSET_SCALE).min(1.0)
+    };
+
+    // Least cost of aligning the first i and j lines, and whether its last
+    // step is a match
+    let width = reference.len() + 1;
+    let mut costs = vec![0.0; (ours.len() + 1) * width];
+    let mut is_match = vec![false; costs.len()];
+    for i in 0..=ours.len() {
+        for j in 0..=reference.len() {
+            let cell = i * width + j;
+            costs[cell] = match (i, j) {
+                (0, _) | (_, 0) => (i + j) as f64 * SKIP_COST,
+                _ => {
+                    let skip = SKIP_COST + costs[cell - width].min(costs[cell - 1]);
+                    let matched = costs[cell - width - 1] + match_cost(i - 1, j - 1);
+                    is_match[cell] = matched <= skip;
+                    matched.min(skip)
+                }
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (ours.len(), reference.len());
+    while i > 0 && j > 0 {
+        let cell = i * width + j;
+        if is_match[cell] {
+            pairs.push((i - 1, j - 1));
+            i -= 1;
+            j -= 1;
+        } else if costs[cell - width] <= costs[cell - 1] {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    pairs.reverse();
+
+    // A lone match disagreeing with its agreeing neighbors is a coincidence
+    let offsets: Vec<i64> = pairs
+        .iter()
+        .map(|&(i, j)| reference[j].start.diff(ours[i].start))
+        .collect();
+    let is_outlier = |index: usize| {
+        let previous = index.checked_sub(1).map(|index| offsets[index]);
+        match (previous, offsets.get(index + 1)) {
+            (Some(previous), Some(&next)) => {
+                (offsets[index] - previous).abs() > OUTLIER_TOLERANCE
+                    && (offsets[index] - next).abs() > OUTLIER_TOLERANCE
+                    && (previous - next).abs() <= OUTLIER_TOLERANCE
+            }
+            _ => false,
+        }
+    };
+    (0..pairs.len())
+        .filter(|&index| !is_outlier(index))
+        .map(|index| pairs[index])
+        .collect()
+}
+
+/// A piecewise linear mapping of our times to the reference ones, through
+/// the times of matched lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Mapping {
+    // Increasing in both times
+    points: Vec<(Time, Time)>,
+}
+
+impl Mapping {
+    fn new(ours: &[Timed], reference: &[Timed], pairs: &[(usize, usize)]) -> Self {
+        let mut candidates = Vec::new();
+        for &(i, j) in pairs {
+            let (line, reference_line) = (&ours[i], &reference[j]);
+            candidates.push((line.start, reference_line.start));
+            let duration = line.end.diff(line.start);
+            let reference_duration = reference_line.end.diff(reference_line.start);
+            if (duration - reference_duration).abs() <= END_TOLERANCE {
+                candidates.push((line.end, reference_line.end));
+            }
+        }
+        candidates.sort();
+
+        let mut points: Vec<(Time, Time)> = Vec::new();
+        for (time, reference_time) in candidates {
+            let is_after = points.last().map_or(true, |&(last, reference_last)| {
+                time > last && reference_time > reference_last
+            });
+            if is_after {
+                points.push((time, reference_time));
+            }
+        }
+        Self { points }
+    }
+
+    fn map(&self, time: Time) -> Time {
+        let index = self.points.partition_point(|&(point, _)| point <= time);
+        let offset = |(point, reference): (Time, Time)| reference.diff(point);
+        match (
+            index.checked_sub(1).map(|index| self.points[index]),
+            self.points.get(index),
+        ) {
+            (Some((from, reference_from)), Some(&(to, reference_to))) => {
+                let progress = time.diff(from) as f64 / to.diff(from) as f64;
+                let shift = progress * reference_to.diff(reference_from) as f64;
+                reference_from.offset(shift.round() as i64)
+            }
+            (Some(before), None) => time.offset(offset(before)),
+            (None, Some(&after)) => time.offset(offset(after)),
+            (None, None) => time,
+        }
+    }
+
+    // Runs of points moving times by the same offset: first and last time,
+    // offset in milliseconds
+    fn segments(&self) -> Vec<(Time, Time, i64)> {
+        let mut segments: Vec<(Time, Time, i64)> = Vec::new();
+        for &(time, reference) in &self.points {
+            let offset = reference.diff(time);
+            match segments.last_mut() {
+                Some((_, last, segment_offset))
+                    if (offset - *segment_offset).abs() <= SEGMENT_TOLERANCE =>
+                {
+                    *last = time
+                }
+                _ => segments.push((time, time, offset)),
+            }
+        }
+        segments
+    }
+}
+
+/// Matching of our lines to the reference ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Matching {
+    mapping: Mapping,
+    changes: Vec<Change>,
+    matched: usize,
+    timed: usize,
+}
+
+impl Matching {
+    fn new(lines: &[SubsControlsValues], reference: &[SubsControlsValues]) -> Self {
+        let (ours, theirs) = (timed_lines(lines), timed_lines(reference));
+        let pairs = align(&ours, &theirs);
+        let mapping = Mapping::new(&ours, &theirs, &pairs);
+
+        let mut changes = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            let (start, end) = match (line.start(), line.end()) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            let (new_start, new_end) = (mapping.map(start), mapping.map(end));
+            if (new_start, new_end) == (start, end) {
+                continue;
+            }
+
+            let reference = pairs
+                .iter()
+                .find(|&&(i, _)| ours[i].row == row)
+                .map(|&(_, j)| {
+                    let row = theirs[j].row;
+                    let text: String = reference[row]
+                        .text
+                        .chars()
+                        .take(MAX_REFERENCE_CHARS)
+                        .collect();
+                    (row, text)
+                });
+            changes.push(Change {
+                row,
+                start,
+                end,
+                new_start,
+                new_end,
+                reference,
+            });
+        }
+
+        Self {
+            mapping,
+            changes,
+            matched: pairs.len(),
+            timed: ours.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `lines` replace all the lines.
+    Synced {
+        name: String,
+        lines: Vec<SubsControlsValues>,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PathChanged(String),
+    Load,
+    Preview,
+    Apply,
+    Close,
+}
+
+/// Moves all the lines to match the timing of a reference subtitle file, as
+/// an official release with different cuts.
+#[derive(Default)]
+pub struct ReferenceSync {
+    path: text_input::State,
+    load: button::State,
+    preview_button: button::State,
+    apply: button::State,
+    close: button::State,
+    segments_scroll: scrollable::State,
+    changes_scroll: scrollable::State,
+    path_input: String,
+    reference: Vec<SubsControlsValues>,
+    // Matching shown for review, until the reference changes
+    preview: Matching,
+    // Outcome of the last operation
+    status: String,
+}
+
+impl ReferenceSync {
+    fn match_lines(&mut self, lines: &[SubsControlsValues]) {
+        self.preview = Matching::new(lines, &self.reference);
+        self.status = format!(
+            "Matched {} of {} lines, {} would change",
+            self.preview.matched,
+            self.preview.timed,
+            self.preview.changes.len()
+        );
+    }
+
+    /// Syncs `lines`, all of the script.
+    pub fn update(&mut self, message: Message, lines: &[SubsControlsValues]) -> Option<Event> {
+        match message {
+            Message::PathChanged(path) => self.path_input = path,
+            Message::Load => match SubtitleFile::read(Path::new(self.path_input.trim())) {
+                Ok(file) => {
+                    self.reference = file.lines;
+                    self.match_lines(lines);
+                }
+                Err(error) => {
+                    self.reference.clear();
+                    self.preview = Matching::default();
+                    self.status = error.to_string();
+                }
+            },
+            Message::Preview => self.match_lines(lines),
+            Message::Apply => {
+                // The lines may have changed since the preview
+                self.match_lines(lines);
+                if self.preview.changes.is_empty() {
+                    self.status = "No line to sync".to_owned();
+                    return None;
+                }
+
+                let mut synced = lines.to_vec();
+                for change in &self.preview.changes {
+                    synced[change.row].set_times(change.new_start, change.new_end);
+                }
+                let name = format!("Sync {} lines", self.preview.changes.len());
+                self.status = format!("Synced {} lines", self.preview.changes.len());
+                self.preview = Matching::default();
+                return Some(Event::Synced {
+                    name,
+                    lines: synced,
+                });
+            }
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let file = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.path,
+                    "Reference .ass or .srt file path",
+                    &self.path_input,
+                    Message::PathChanged,
+                )
+                .on_submit(Message::Load)
+                .size(TEXT_SIZE),
+            )
+            .push(
+                Button::new(&mut self.load, Text::new("Load").size(TEXT_SIZE))
+                    .on_press(Message::Load),
+            );
+
+        let mut preview_button = Button::new(
+            &mut self.preview_button,
+            Text::new("Preview").size(TEXT_SIZE),
+        );
+        let mut apply = Button::new(&mut self.apply, Text::new("Sync").size(TEXT_SIZE));
+        if !self.reference.is_empty() {
+            preview_button = preview_button.on_press(Message::Preview);
+            apply = apply.on_press(Message::Apply);
+        }
+        let actions = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(preview_button)
+            .push(apply)
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(&self.status).size(TEXT_SIZE));
+
+        let segments = self.preview.mapping.segments().into_iter().fold(
+            Scrollable::new(&mut self.segments_scroll)
+                .spacing(COLUMN_SPACING)
+                .height(Length::Units(SEGMENTS_HEIGHT))
+                .push(
+                    Text::new("Offsets, lines between two segments are moved gradually")
+                        .size(TEXT_SIZE),
+                ),
+            |segments, (first, last, offset)| {
+                let sign = if offset < 0 { '-' } else { '+' };
+                segments.push(
+                    Text::new(format!(
+                        "{} to {}: {}{}.{:03} s",
+                        first,
+                        last,
+                        sign,
+                        offset.abs() / 1000,
+                        offset.abs() % 1000
+                    ))
+                    .size(TEXT_SIZE),
+                )
+            },
+        );
+
+        let changes = self.preview.changes.iter().fold(
+            Scrollable::new(&mut self.changes_scroll)
+                .spacing(COLUMN_SPACING)
+                .height(Length::Fill),
+            |changes, change| changes.push(Text::new(change.to_string()).size(TEXT_SIZE)),
+        );
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(Text::new("Match the timing of a reference subtitle file").size(TEXT_SIZE))
+            .push(file)
+            .push(actions)
+            .push(segments)
+            .push(changes)
+            .into()
+    }
+}
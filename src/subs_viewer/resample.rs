@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use super::subtitle_file::SubtitleFile;
+use super::tags::{ClipShape, Item, Line, NodeKind, Tag, TagKind};
+
+/// Resolution of scripts without `PlayResX` and `PlayResY`.
+pub const DEFAULT_RESOLUTION: (u32, u32) = (384, 288);
+
+/// Scales the styles, margins and tags of `file` to a `width` by `height`
+/// resolution. Sizes follow the height, so text keeps its shape when the
+/// aspect ratio changes.
+pub fn resample(file: &mut SubtitleFile, width: u32, height: u32) {
+    let (from_width, from_height) = file.resolution().unwrap_or(DEFAULT_RESOLUTION);
+    let scale = (
+        width as f32 / from_width.max(1) as f32,
+        height as f32 / from_height.max(1) as f32,
+    );
+    let (x, y) = scale;
+    let margin = |margin: usize, ratio: f32| (margin as f32 * ratio).round() as usize;
+
+    for style in &mut file.styles {
+        style.font_size = round(style.font_size * y);
+        style.outline = round(style.outline * y);
+        style.shadow = round(style.shadow * y);
+        style.spacing = round(style.spacing * x);
+        style.margin_left = margin(style.margin_left, x);
+        style.margin_right = margin(style.margin_right, x);
+        style.margin_vertical = margin(style.margin_vertical, y);
+    }
+    for line in &mut file.lines {
+        line.margin_left = margin(line.margin_left, x);
+        line.margin_right = margin(line.margin_right, x);
+        line.margin_vertical = margin(line.margin_vertical, y);
+        line.text = scaled_text(&line.text, scale);
+    }
+
+    file.set_info("PlayResX", &width.to_string());
+    file.set_info("PlayResY", &height.to_string());
+}
+
+// Two decimals are more than a pixel needs
+fn round(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+fn scaled_text(text: &str, scale: (f32, f32)) -> String {
+    let line = Line::parse(text);
+    let mut edits = Vec::new();
+    for node in &line.nodes {
+        match &node.kind {
+            NodeKind::Block(items) => {
+                for item in items {
+                    if let Item::Tag(tag) = item {
+                        scale_tag(tag, scale, &mut edits);
+                    }
+                }
+            }
+            NodeKind::Drawing => edits.push((
+                node.span.clone(),
+                scaled_drawing(&text[node.span.clone()], scale),
+            )),
+            _ => {}
+        }
+    }
+
+    // Edits are in text order and never overlap
+    let mut result = String::new();
+    let mut written = 0;
+    for (span, replacement) in edits {
+        result.push_str(&text[written..span.start]);
+        result.push_str(&replacement);
+        written = span.end;
+    }
+    result.push_str(&text[written..]);
+    result
+}
+
+// Adds the scaled version of `tag` to `edits`, if it has a position or size
+fn scale_tag(tag: &Tag<'_>, (x, y): (f32, f32), edits: &mut Vec<(Range<usize>, String)>) {
+    let point = |(from_x, from_y): (f32, f32)| (round(from_x * x), round(from_y * y));
+    let kind = match &tag.kind {
+        // Only the animated tags change
+        TagKind::Transform { tags, .. } => {
+            for tag in tags {
+                scale_tag(tag, (x, y), edits);
+            }
+            return;
+        }
+        TagKind::Clip {
+            inverse,
+            shape: ClipShape::Drawing { scale, commands },
+        } => {
+            let commands = scaled_drawing(commands, (x, y));
+            let kind = TagKind::Clip {
+                inverse: *inverse,
+                shape: ClipShape::Drawing {
+                    scale: *scale,
+                    commands: &commands,
+                },
+            };
+            edits.push((tag.span.clone(), kind.to_string()));
+            return;
+        }
+        TagKind::Clip {
+            inverse,
+            shape: ClipShape::Rectangle(x1, y1, x2, y2),
+        } => {
+            let ((x1, y1), (x2, y2)) = (point((*x1, *y1)), point((*x2, *y2)));
+            TagKind::Clip {
+                inverse: *inverse,
+                shape: ClipShape::Rectangle(x1, y1, x2, y2),
+            }
+        }
+        TagKind::Position(from_x, from_y) => {
+            let (x, y) = point((*from_x, *from_y));
+            TagKind::Position(x, y)
+        }
+        TagKind::Origin(from_x, from_y) => {
+            let (x, y) = point((*from_x, *from_y));
+            TagKind::Origin(x, y)
+        }
+        TagKind::Move { from, to, times } => TagKind::Move {
+            from: point(*from),
+            to: point(*to),
+            times: *times,
+        },
+        TagKind::FontSize(size) => TagKind::FontSize(round(size * y)),
+        TagKind::Spacing(spacing) => TagKind::Spacing(round(spacing * x)),
+        TagKind::Border(size) => TagKind::Border(round(size * y)),
+        TagKind::BorderX(size) => TagKind::BorderX(round(size * x)),
+        TagKind::BorderY(size) => TagKind::BorderY(round(size * y)),
+        TagKind::Shadow(size) => TagKind::Shadow(round(size * y)),
+        TagKind::ShadowX(size) => TagKind::ShadowX(round(size * x)),
+        TagKind::ShadowY(size) => TagKind::ShadowY(round(size * y)),
+        TagKind::Blur(size) => TagKind::Blur(round(size * y)),
+        TagKind::Baseline(offset) => TagKind::Baseline(round(offset * y)),
+        _ => return,
+    };
+    edits.push((tag.span.clone(), kind.to_string()));
+}
+
+// Drawing commands with their coordinates scaled, which alternate between
+// x and y after each command letter
+fn scaled_drawing(commands: &str, (x, y): (f32, f32)) -> String {
+    let mut is_x = true;
+    commands
+        .split_whitespace()
+        .map(|word| match word.parse::<f32>() {
+            Ok(value) => {
+                let ratio = if is_x { x } else { y };
+                is_x = !is_x;
+                round(value * ratio).to_string()
+            }
+            Err(_) => {
+                is_x = true;
+                word.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+
+use iced::{
+    button, pick_list, text_input, Align, Button, Column, Element, Length, PickList, Row, Text,
+    TextInput,
+};
+
+use super::time::Time;
+use super::timecodes::Timebase;
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const INPUT_WIDTH: u16 = 240;
+
+/// How lines follow a video whose frame times changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// Lines stay on their frames, for video sped up or slowed down, as PAL
+    /// releases, or for variable frame rate video encoded at a constant one.
+    KeepFrames,
+    /// Lines keep their times, snapped to the new frames, for video as long
+    /// as before, as after an inverse telecine.
+    KeepTimes,
+}
+
+impl Default for Conversion {
+    fn default() -> Self {
+        Self::KeepFrames
+    }
+}
+
+impl From<Conversion> for &'static str {
+    fn from(val: Conversion) -> Self {
+        match val {
+            Conversion::KeepFrames => "Keep frames (speed change)",
+            Conversion::KeepTimes => "Keep times (same speed)",
+        }
+    }
+}
+
+static ALL_CONVERSIONS: &[Conversion] = &[Conversion::KeepFrames, Conversion::KeepTimes];
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+impl Conversion {
+    /// `time` of a video with the `from` frame times, for one with the `to`
+    /// ones.
+    pub fn convert(self, time: Time, from: &Timebase, to: &Timebase) -> Time {
+        match self {
+            Conversion::KeepFrames => from.convert(time, to),
+            Conversion::KeepTimes => to.time_of(to.frame_at(time)),
+        }
+    }
+}
+
+// Common conversions: label, from, to
+static PRESETS: &[(&str, &str, &str, Conversion)] = &[
+    ("23.976 → 25", "24000/1001", "25", Conversion::KeepFrames),
+    ("25 → 23.976", "25", "24000/1001", Conversion::KeepFrames),
+    (
+        "29.97 → 23.976",
+        "30000/1001",
+        "24000/1001",
+        Conversion::KeepTimes,
+    ),
+    (
+        "23.976 → 29.97",
+        "24000/1001",
+        "30000/1001",
+        Conversion::KeepTimes,
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `lines` replace all the lines.
+    Retimed {
+        name: String,
+        lines: Vec<SubsControlsValues>,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    FromChanged(String),
+    ToChanged(String),
+    ConversionPicked(Conversion),
+    Preset(usize),
+    Apply,
+    Close,
+}
+
+/// Moves all the lines to the frames of a video at another frame rate.
+#[derive(Default)]
+pub struct Retimer {
+    from: text_input::State,
+    to: text_input::State,
+    conversion: pick_list::State<Conversion>,
+    presets: [button::State; 4],
+    apply: button::State,
+    close: button::State,
+    from_input: String,
+    to_input: String,
+    conversion_value: Conversion,
+    // Outcome of the last conversion
+    status: String,
+}
+
+impl Retimer {
+    // `lines` with their times converted, and how many changed
+    fn retimed(
+        &self,
+        lines: &[SubsControlsValues],
+    ) -> Result<(Vec<SubsControlsValues>, usize), String> {
+        let to = Timebase::read(&self.to_input).map_err(|error| error.to_string())?;
+        let from = match self.conversion_value {
+            Conversion::KeepFrames => {
+                Timebase::read(&self.from_input).map_err(|error| error.to_string())?
+            }
+            // The old frames do not matter
+            Conversion::KeepTimes => to.clone(),
+        };
+
+        let mut retimed = lines.to_vec();
+        let mut count = 0;
+        for line in &mut retimed {
+            let (start, end) = match (line.start(), line.end()) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            let new_start = self.conversion_value.convert(start, &from, &to);
+            let new_end = self.conversion_value.convert(end, &from, &to);
+            if (new_start, new_end) != (start, end) {
+                line.set_times(new_start, new_end);
+                count += 1;
+            }
+        }
+        Ok((retimed, count))
+    }
+
+    /// Converts the times of `lines`, all of the script.
+    pub fn update(&mut self, message: Message, lines: &[SubsControlsValues]) -> Option<Event> {
+        match message {
+            Message::FromChanged(from) => self.from_input = from,
+            Message::ToChanged(to) => self.to_input = to,
+            Message::ConversionPicked(conversion) => self.conversion_value = conversion,
+            Message::Preset(index) => {
+                if let Some(&(_, from, to, conversion)) = PRESETS.get(index) {
+                    self.from_input = from.to_owned();
+                    self.to_input = to.to_owned();
+                    self.conversion_value = conversion;
+                }
+            }
+            Message::Apply => match self.retimed(lines) {
+                Ok((_, 0)) => self.status = "No line to retime".to_owned(),
+                Ok((lines, count)) => {
+                    self.status = format!("Retimed {} lines", count);
+                    return Some(Event::Retimed {
+                        name: format!("Retime {} lines", count),
+                        lines,
+                    });
+                }
+                Err(error) => self.status = error,
+            },
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let presets = self.presets.iter_mut().zip(PRESETS).enumerate().fold(
+            Row::new()
+                .spacing(ROW_SPACING)
+                .align_items(Align::Center)
+                .push(Text::new("Presets").size(TEXT_SIZE)),
+            |row, (index, (state, (label, ..)))| {
+                row.push(
+                    Button::new(state, Text::new(*label).size(TEXT_SIZE))
+                        .on_press(Message::Preset(index)),
+                )
+            },
+        );
+
+        let mut rates = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.conversion,
+                    ALL_CONVERSIONS,
+                    Some(self.conversion_value),
+                    Message::ConversionPicked,
+                )
+                .text_size(TEXT_SIZE),
+            );
+        rates = match self.conversion_value {
+            Conversion::KeepFrames => rates
+                .push(Text::new("from").size(TEXT_SIZE))
+                .push(
+                    TextInput::new(
+                        &mut self.from,
+                        "23.976 or timecodes file",
+                        &self.from_input,
+                        Message::FromChanged,
+                    )
+                    .on_submit(Message::Apply)
+                    .width(Length::Units(INPUT_WIDTH))
+                    .size(TEXT_SIZE),
+                )
+                .push(Text::new("to").size(TEXT_SIZE)),
+            Conversion::KeepTimes => rates.push(Text::new("on the frames of").size(TEXT_SIZE)),
+        };
+        rates = rates.push(
+            TextInput::new(
+                &mut self.to,
+                "25 or timecodes file",
+                &self.to_input,
+                Message::ToChanged,
+            )
+            .on_submit(Message::Apply)
+            .width(Length::Units(INPUT_WIDTH))
+            .size(TEXT_SIZE),
+        );
+
+        let actions = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Button::new(&mut self.apply, Text::new("Retime").size(TEXT_SIZE))
+                    .on_press(Message::Apply),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(&self.status).size(TEXT_SIZE));
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(Text::new("Convert all the lines to another frame rate").size(TEXT_SIZE))
+            .push(
+                Text::new(
+                    "Frame rates are numbers or fractions like 24000/1001, timecodes are v1 \
+                     or v2 files for variable frame rate video",
+                )
+                .size(TEXT_SIZE),
+            )
+            .push(presets)
+            .push(rates)
+            .push(actions)
+            .into()
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use super::styles::{self, AssStyle, STYLE_FORMAT};
+
+const CATALOG_EXTENSION: &str = "sty";
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("No configuration directory available")]
+    NoDirectory,
+    #[error("Invalid catalog name")]
+    InvalidName,
+    #[error("Catalog I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {line}: {source}")]
+    Style { line: usize, source: styles::Error },
+}
+
+/// What to do when an imported or exported style has the name of an
+/// existing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    Overwrite,
+    KeepBoth,
+    Skip,
+}
+
+impl Default for Conflict {
+    fn default() -> Self {
+        Self::KeepBoth
+    }
+}
+
+impl From<Conflict> for &'static str {
+    fn from(val: Conflict) -> Self {
+        match val {
+            Conflict::Overwrite => "Overwrite existing",
+            Conflict::KeepBoth => "Keep both",
+            Conflict::Skip => "Skip existing",
+        }
+    }
+}
+
+pub static ALL_CONFLICTS: &[Conflict] = &[Conflict::Overwrite, Conflict::KeepBoth, Conflict::Skip];
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+/// Copies `style` into `styles`, returning the name it was stored with, or
+/// `None` when it was skipped.
+pub fn merge(styles: &mut Vec<AssStyle>, style: &AssStyle, conflict: Conflict) -> Option<String> {
+    let existing = styles.iter().position(|other| other.name == style.name);
+
+    match (existing, conflict) {
+        (None, _) => styles.push(style.clone()),
+        (Some(index), Conflict::Overwrite) => {
+            styles[index] = style.clone();
+            return Some(style.name.clone());
+        }
+        (Some(_), Conflict::KeepBoth) => {
+            let mut counter = 1;
+            let mut name = style.name.clone();
+            while styles.iter().any(|other| other.name == name) {
+                counter += 1;
+                name = format!("{} {}", style.name, counter);
+            }
+            styles.push(AssStyle {
+                name,
+                ..style.clone()
+            });
+        }
+        (Some(_), Conflict::Skip) => return None,
+    }
+
+    // The style was pushed
+    styles.last().map(|style| style.name.clone())
+}
+
+/// A named set of styles stored on disk, shared by every script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    pub name: String,
+    pub styles: Vec<AssStyle>,
+}
+
+impl Catalog {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            styles: Vec::new(),
+        }
+    }
+
+    /// Directory containing the catalogs, created when missing.
+    pub fn directory() -> Result<PathBuf> {
+        let directory = dirs::config_dir()
+            .ok_or(Error::NoDirectory)?
+            .join("spirit_sub")
+            .join("catalogs");
+        fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// Names of the stored catalogs, in alphabetical order.
+    pub fn list() -> Result<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(Self::directory()?)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |ext| ext == CATALOG_EXTENSION)
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load(name: &str) -> Result<Self> {
+        let data = fs::read_to_string(Self::path(name)?)?;
+        let styles = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.trim_start().starts_with("Style:"))
+            .map(|(index, line)| {
+                line.parse().map_err(|source| Error::Style {
+                    line: index + 1,
+                    source,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            styles,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut data = format!("[V4+ Styles]\n{}\n", STYLE_FORMAT);
+        for style in &self.styles {
+            data.push_str(&style.to_string());
+            data.push('\n');
+        }
+
+        fs::write(Self::path(&self.name)?, data)?;
+        Ok(())
+    }
+
+    fn path(name: &str) -> Result<PathBuf> {
+        // The name becomes a file name, so it must not contain a path
+        let is_valid =
+            !name.trim().is_empty() && !name.contains(|c| matches!(c, '/' | '\\' | ':' | '.'));
+        if !is_valid {
+            return Err(Error::InvalidName);
+        }
+
+        Ok(Self::directory()?.join(format!("{}.{}", name.trim(), CATALOG_EXTENSION)))
+    }
+}
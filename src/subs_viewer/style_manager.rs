@@ -0,0 +1,882 @@
+use iced::{
+    button, pick_list, scrollable, text_input, Align, Button, Checkbox, Column, Element, Image,
+    Length, PickList, Row, Scrollable, Text, TextInput,
+};
+
+use iced_aw::number_input::{self, NumberInput};
+
+use super::color_picker::{self, ColorPicker};
+use super::preview::{PreviewFrame, Previewer};
+use super::style_catalog::{self, Catalog, Conflict, ALL_CONFLICTS};
+use super::styles::{
+    Alignment, AssColor, AssStyle, BorderStyle, ALL_ALIGNMENTS, ALL_BORDER_STYLES,
+};
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const LABEL_WIDTH: u16 = 120;
+const LIST_WIDTH: u16 = 200;
+
+// Size of the frame the preview sample is rendered in
+const PREVIEW_WIDTH: u32 = 640;
+const PREVIEW_HEIGHT: u32 = 160;
+const PREVIEW_TEXT: &str = "SpiritSub 0123 AaBbCc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSlot {
+    Primary,
+    Secondary,
+    Outline,
+    Back,
+}
+
+static ALL_COLOR_SLOTS: &[ColorSlot] = &[
+    ColorSlot::Primary,
+    ColorSlot::Secondary,
+    ColorSlot::Outline,
+    ColorSlot::Back,
+];
+
+impl ColorSlot {
+    fn index(self) -> usize {
+        match self {
+            ColorSlot::Primary => 0,
+            ColorSlot::Secondary => 1,
+            ColorSlot::Outline => 2,
+            ColorSlot::Back => 3,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ColorSlot::Primary => "Primary",
+            ColorSlot::Secondary => "Secondary",
+            ColorSlot::Outline => "Outline",
+            ColorSlot::Back => "Shadow",
+        }
+    }
+
+    pub fn color(self, style: &AssStyle) -> AssColor {
+        match self {
+            ColorSlot::Primary => style.primary_color,
+            ColorSlot::Secondary => style.secondary_color,
+            ColorSlot::Outline => style.outline_color,
+            ColorSlot::Back => style.back_color,
+        }
+    }
+
+    pub fn color_mut(self, style: &mut AssStyle) -> &mut AssColor {
+        match self {
+            ColorSlot::Primary => &mut style.primary_color,
+            ColorSlot::Secondary => &mut style.secondary_color,
+            ColorSlot::Outline => &mut style.outline_color,
+            ColorSlot::Back => &mut style.back_color,
+        }
+    }
+}
+
+/// Changes of the style list, left to the viewer so they can be undone.
+/// Renames and deletions concern the subtitle lines too.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The styles after a change, with its name for the history and the field
+    /// being typed in, if any.
+    Edited {
+        name: String,
+        styles: Vec<AssStyle>,
+        typing: Option<&'static str>,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+    Deleted(String),
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    StyleSelected(usize),
+    NewStyle,
+    DuplicateStyle,
+    DeleteStyle,
+    Close,
+    NameChanged(String),
+    NameSubmitted,
+    FontNameChanged(String),
+    FontSizePicked(f32),
+    ColorChanged(ColorSlot, String),
+    PickColor(ColorSlot),
+    ColorPicker(color_picker::Message),
+    BoldToggled(bool),
+    ItalicToggled(bool),
+    UnderlineToggled(bool),
+    StrikeOutToggled(bool),
+    ScaleXPicked(f32),
+    ScaleYPicked(f32),
+    SpacingPicked(f32),
+    AnglePicked(f32),
+    BorderStylePicked(BorderStyle),
+    OutlinePicked(f32),
+    ShadowPicked(f32),
+    AlignmentPicked(Alignment),
+    MarginLeftPicked(usize),
+    MarginRightPicked(usize),
+    MarginVerticalPicked(usize),
+    EncodingPicked(usize),
+    CatalogPicked(String),
+    CatalogNameChanged(String),
+    CreateCatalog,
+    CatalogStyleSelected(usize),
+    ConflictPicked(Conflict),
+    ImportStyle,
+    ImportAll,
+    ExportStyle,
+}
+
+#[derive(Default)]
+pub struct StyleManager {
+    list_scroll: scrollable::State,
+    list_buttons: Vec<button::State>,
+    new_style: button::State,
+    duplicate_style: button::State,
+    delete_style: button::State,
+    close: button::State,
+    editor_scroll: scrollable::State,
+    name: text_input::State,
+    font_name: text_input::State,
+    font_size: number_input::State,
+    colors: [text_input::State; 4],
+    pick_colors: [button::State; 4],
+    color_picker: ColorPicker,
+    scale_x: number_input::State,
+    scale_y: number_input::State,
+    spacing: number_input::State,
+    angle: number_input::State,
+    border_style: pick_list::State<BorderStyle>,
+    outline: number_input::State,
+    shadow: number_input::State,
+    alignment: pick_list::State<Alignment>,
+    margin_left: number_input::State,
+    margin_right: number_input::State,
+    margin_vertical: number_input::State,
+    encoding: number_input::State,
+    catalog_pick: pick_list::State<String>,
+    catalog_name: text_input::State,
+    create_catalog: button::State,
+    catalog_scroll: scrollable::State,
+    catalog_buttons: Vec<button::State>,
+    conflict_pick: pick_list::State<Conflict>,
+    import_style: button::State,
+    import_all: button::State,
+    export_style: button::State,
+    // Text being typed, applied only once valid
+    name_input: String,
+    color_inputs: [String; 4],
+    selected: usize,
+    catalog_names: Vec<String>,
+    catalog_name_input: String,
+    catalog: Option<Catalog>,
+    catalog_selected: usize,
+    conflict: Conflict,
+    // Outcome of the last catalog operation
+    catalog_status: String,
+    // Color being edited with the color picker
+    picking: Option<ColorSlot>,
+    previewer: Option<Previewer>,
+    preview: Option<PreviewFrame>,
+}
+
+impl StyleManager {
+    pub fn new() -> Self {
+        Self {
+            previewer: Some(Previewer::new(PREVIEW_WIDTH, PREVIEW_HEIGHT)),
+            ..Self::default()
+        }
+    }
+
+    /// Shows the style called `name`, if any.
+    pub fn open(&mut self, styles: &[AssStyle], name: &str) {
+        self.selected = styles
+            .iter()
+            .position(|style| style.name == name)
+            .unwrap_or(0);
+        self.load(styles);
+        self.refresh_catalogs();
+    }
+
+    pub fn update(&mut self, message: Message, current: &[AssStyle]) -> Option<Event> {
+        // Edits are made on a copy the viewer records
+        let mut styles = current.to_vec();
+        let mut name = None;
+        let mut typing = None;
+        match message {
+            Message::StyleSelected(selected) => {
+                self.selected = selected;
+                self.picking = None;
+            }
+            Message::NewStyle => {
+                let new = Self::unique_name(&styles, "New style");
+                name = Some(format!("New style {}", new));
+                styles.push(AssStyle::new(&new));
+                self.selected = styles.len() - 1;
+            }
+            Message::DuplicateStyle => {
+                let mut style = styles[self.selected].clone();
+                name = Some(format!("Duplicate style {}", style.name));
+                style.name = Self::unique_name(&styles, &format!("{} copy", style.name));
+                styles.insert(self.selected + 1, style);
+                self.selected += 1;
+            }
+            Message::DeleteStyle => {
+                // Lines always need a style to fall back to
+                if styles.len() > 1 {
+                    return Some(Event::Deleted(styles[self.selected].name.clone()));
+                }
+            }
+            Message::Close => return Some(Event::Closed),
+            Message::NameChanged(name) => {
+                self.name_input = name;
+                return None;
+            }
+            Message::NameSubmitted => {
+                // Commas separate the fields of the style lines
+                let name = self.name_input.trim().to_owned();
+                let style = &styles[self.selected];
+                let is_valid = !name.is_empty() && !name.contains(',');
+                if is_valid && name != style.name && !Self::exists(&styles, &name) {
+                    return Some(Event::Renamed {
+                        from: style.name.clone(),
+                        to: name,
+                    });
+                }
+            }
+            Message::ColorChanged(slot, value) => {
+                if let Ok(color) = value.parse::<AssColor>() {
+                    *slot.color_mut(&mut styles[self.selected]) = color;
+                    self.refresh_preview(&styles[self.selected]);
+                }
+                self.color_inputs[slot.index()] = value;
+                // The typed text is kept until it is submitted or replaced
+                return self.edited(current, styles, None, Some(slot.label()));
+            }
+            Message::PickColor(slot) => {
+                let color = slot.color(&styles[self.selected]);
+                self.color_picker.open(color, self.preview.clone());
+                self.picking = Some(slot);
+                return None;
+            }
+            Message::ColorPicker(message) => match self.color_picker.update(message) {
+                Some(color_picker::Event::Picked(color)) => {
+                    if let Some(slot) = self.picking.take() {
+                        *slot.color_mut(&mut styles[self.selected]) = color;
+                    }
+                }
+                Some(color_picker::Event::Cancelled) => {
+                    self.picking = None;
+                    return None;
+                }
+                None => return None,
+            },
+            Message::CatalogPicked(name) => {
+                self.open_catalog(&name);
+                return None;
+            }
+            Message::CatalogNameChanged(name) => {
+                self.catalog_name_input = name;
+                return None;
+            }
+            Message::CreateCatalog => {
+                let catalog = Catalog::new(self.catalog_name_input.trim());
+                if self.catalog_names.contains(&catalog.name) {
+                    self.catalog_status = format!("Catalog {} already exists", catalog.name);
+                } else if self.save_catalog(catalog) {
+                    self.catalog_name_input.clear();
+                    self.refresh_catalogs();
+                }
+                return None;
+            }
+            Message::CatalogStyleSelected(selected) => {
+                self.catalog_selected = selected;
+                return None;
+            }
+            Message::ConflictPicked(conflict) => {
+                self.conflict = conflict;
+                return None;
+            }
+            Message::ImportStyle | Message::ImportAll => {
+                let catalog = match &self.catalog {
+                    Some(catalog) => catalog,
+                    None => return None,
+                };
+                let incoming: Vec<&AssStyle> = if matches!(message, Message::ImportStyle) {
+                    catalog
+                        .styles
+                        .get(self.catalog_selected)
+                        .into_iter()
+                        .collect()
+                } else {
+                    catalog.styles.iter().collect()
+                };
+
+                let mut imported = 0;
+                for style in incoming {
+                    if let Some(name) = style_catalog::merge(&mut styles, style, self.conflict) {
+                        imported += 1;
+                        self.selected = styles
+                            .iter()
+                            .position(|style| style.name == name)
+                            .unwrap_or(self.selected);
+                    }
+                }
+                self.catalog_status = format!("Imported {} styles from {}", imported, catalog.name);
+                name = Some(format!("Import styles from {}", catalog.name));
+            }
+            Message::ExportStyle => {
+                let mut catalog = match self.catalog.clone() {
+                    Some(catalog) => catalog,
+                    None => return None,
+                };
+                if let Some(name) =
+                    style_catalog::merge(&mut catalog.styles, &styles[self.selected], self.conflict)
+                {
+                    let status = format!("Exported {} to {}", name, catalog.name);
+                    if self.save_catalog(catalog) {
+                        self.catalog_status = status;
+                    }
+                } else {
+                    self.catalog_status = format!("Skipped {}", styles[self.selected].name);
+                }
+                return None;
+            }
+            message => {
+                typing = Self::edit(message, &mut styles[self.selected]);
+            }
+        }
+
+        self.load(&styles);
+        self.edited(current, styles, name, typing)
+    }
+
+    // The change from `current` to `styles`, if any. Unnamed changes are
+    // edits of the selected style.
+    fn edited(
+        &self,
+        current: &[AssStyle],
+        styles: Vec<AssStyle>,
+        name: Option<String>,
+        typing: Option<&'static str>,
+    ) -> Option<Event> {
+        if styles == current {
+            return None;
+        }
+        let name = name.unwrap_or_else(|| format!("Edit style {}", styles[self.selected].name));
+        Some(Event::Edited {
+            name,
+            styles,
+            typing,
+        })
+    }
+
+    /// Shows `styles` again after they were changed outside of the manager.
+    pub fn refresh(&mut self, styles: &[AssStyle]) {
+        self.selected = self.selected.min(styles.len() - 1);
+        self.picking = None;
+        self.load(styles);
+    }
+
+    // Field changes of the selected style, returning the field typed in
+    fn edit(message: Message, style: &mut AssStyle) -> Option<&'static str> {
+        match message {
+            Message::FontNameChanged(font_name) => {
+                style.font_name = font_name;
+                return Some("font name");
+            }
+            Message::FontSizePicked(font_size) => style.font_size = font_size,
+            Message::BoldToggled(bold) => style.bold = bold,
+            Message::ItalicToggled(italic) => style.italic = italic,
+            Message::UnderlineToggled(underline) => style.underline = underline,
+            Message::StrikeOutToggled(strike_out) => style.strike_out = strike_out,
+            Message::ScaleXPicked(scale_x) => style.scale_x = scale_x,
+            Message::ScaleYPicked(scale_y) => style.scale_y = scale_y,
+            Message::SpacingPicked(spacing) => style.spacing = spacing,
+            Message::AnglePicked(angle) => style.angle = angle,
+            Message::BorderStylePicked(border_style) => style.border_style = border_style,
+            Message::OutlinePicked(outline) => style.outline = outline,
+            Message::ShadowPicked(shadow) => style.shadow = shadow,
+            Message::AlignmentPicked(alignment) => style.alignment = alignment,
+            Message::MarginLeftPicked(margin_left) => style.margin_left = margin_left,
+            Message::MarginRightPicked(margin_right) => style.margin_right = margin_right,
+            Message::MarginVerticalPicked(margin_vertical) => {
+                style.margin_vertical = margin_vertical
+            }
+            Message::EncodingPicked(encoding) => style.encoding = encoding,
+            _ => {}
+        }
+        None
+    }
+
+    // Resets the typed text to the values of the selected style
+    fn load(&mut self, styles: &[AssStyle]) {
+        let style = &styles[self.selected];
+        self.name_input = style.name.clone();
+        for slot in ALL_COLOR_SLOTS {
+            self.color_inputs[slot.index()] = slot.color(style).to_string();
+        }
+        self.refresh_preview(style);
+    }
+
+    fn refresh_preview(&mut self, style: &AssStyle) {
+        let script = style.sample_script(PREVIEW_TEXT, PREVIEW_WIDTH, PREVIEW_HEIGHT);
+        self.preview = self
+            .previewer
+            .as_ref()
+            .and_then(|previewer| previewer.render(&script));
+    }
+
+    fn refresh_catalogs(&mut self) {
+        match Catalog::list() {
+            Ok(names) => self.catalog_names = names,
+            Err(e) => self.catalog_status = e.to_string(),
+        }
+    }
+
+    fn open_catalog(&mut self, name: &str) {
+        match Catalog::load(name) {
+            Ok(catalog) => {
+                self.catalog = Some(catalog);
+                self.catalog_selected = 0;
+                self.catalog_status.clear();
+            }
+            Err(e) => self.catalog_status = format!("Cannot open {}: {}", name, e),
+        }
+    }
+
+    // Writes `catalog` to disk and shows it, returns whether it succeeded
+    fn save_catalog(&mut self, catalog: Catalog) -> bool {
+        match catalog.save() {
+            Ok(()) => {
+                self.catalog_status.clear();
+                self.catalog = Some(catalog);
+                true
+            }
+            Err(e) => {
+                self.catalog_status = format!("Cannot save {}: {}", catalog.name, e);
+                false
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn exists(styles: &[AssStyle], name: &str) -> bool {
+        styles.iter().any(|style| style.name == name)
+    }
+
+    fn unique_name(styles: &[AssStyle], name: &str) -> String {
+        let mut unique = name.to_owned();
+        let mut counter = 1;
+        while Self::exists(styles, &unique) {
+            counter += 1;
+            unique = format!("{} {}", name, counter);
+        }
+        unique
+    }
+
+    pub fn view(&mut self, styles: &[AssStyle]) -> Element<Message> {
+        let selected = self.selected;
+        self.list_buttons
+            .resize_with(styles.len(), button::State::default);
+
+        let list = self.list_buttons.iter_mut().zip(styles).enumerate().fold(
+            Column::new().width(Length::Fill),
+            |column, (index, (state, style))| {
+                let label = if index == selected {
+                    format!("> {}", style.name)
+                } else {
+                    style.name.clone()
+                };
+                column.push(
+                    Button::new(state, Text::new(label).size(TEXT_SIZE))
+                        .width(Length::Fill)
+                        .on_press(Message::StyleSelected(index)),
+                )
+            },
+        );
+
+        let list_column = Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Units(LIST_WIDTH))
+            .push(
+                Scrollable::new(&mut self.list_scroll)
+                    .height(Length::Fill)
+                    .push(list),
+            )
+            .push(Self::button(&mut self.new_style, "New", Message::NewStyle))
+            .push(Self::button(
+                &mut self.duplicate_style,
+                "Duplicate",
+                Message::DuplicateStyle,
+            ))
+            .push(Self::button(
+                &mut self.delete_style,
+                "Delete",
+                Message::DeleteStyle,
+            ))
+            .push(Self::button(&mut self.close, "Close", Message::Close));
+
+        let style = &styles[selected];
+
+        let preview: Element<Message> = match &self.preview {
+            Some(frame) => Image::new(frame.handle.clone())
+                .width(Length::Units(PREVIEW_WIDTH as u16))
+                .height(Length::Units(PREVIEW_HEIGHT as u16))
+                .into(),
+            None => Text::new("Preview unavailable").size(TEXT_SIZE).into(),
+        };
+
+        let mut colors = Row::new().spacing(ROW_SPACING).align_items(Align::Center);
+        for (((state, pick_state), input), slot) in self
+            .colors
+            .iter_mut()
+            .zip(self.pick_colors.iter_mut())
+            .zip(self.color_inputs.iter())
+            .zip(ALL_COLOR_SLOTS)
+        {
+            let slot = *slot;
+            colors = colors
+                .push(Text::new(slot.label()).size(TEXT_SIZE))
+                .push(
+                    TextInput::new(state, "&HAABBGGRR", input, move |value| {
+                        Message::ColorChanged(slot, value)
+                    })
+                    .size(TEXT_SIZE)
+                    .width(Length::Units(110)),
+                )
+                .push(
+                    Button::new(pick_state, Text::new("...").size(TEXT_SIZE))
+                        .on_press(Message::PickColor(slot)),
+                );
+        }
+
+        let flags = Row::new()
+            .spacing(ROW_SPACING)
+            .push(Checkbox::new(style.bold, "Bold", Message::BoldToggled).text_size(TEXT_SIZE))
+            .push(
+                Checkbox::new(style.italic, "Italic", Message::ItalicToggled).text_size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(style.underline, "Underline", Message::UnderlineToggled)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(
+                Checkbox::new(style.strike_out, "Strikeout", Message::StrikeOutToggled)
+                    .text_size(TEXT_SIZE),
+            );
+
+        let editor = Column::new()
+            .spacing(COLUMN_SPACING)
+            .push(preview)
+            .push(Self::labeled(
+                "Name",
+                TextInput::new(
+                    &mut self.name,
+                    "Style name",
+                    &self.name_input,
+                    Message::NameChanged,
+                )
+                .on_submit(Message::NameSubmitted)
+                .size(TEXT_SIZE)
+                .into(),
+            ))
+            .push(Self::labeled(
+                "Font",
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(
+                        TextInput::new(
+                            &mut self.font_name,
+                            "Font name",
+                            &style.font_name,
+                            Message::FontNameChanged,
+                        )
+                        .size(TEXT_SIZE),
+                    )
+                    .push(Self::float(
+                        &mut self.font_size,
+                        style.font_size,
+                        (1.0, 999.0, 1.0),
+                        Message::FontSizePicked,
+                    ))
+                    .into(),
+            ))
+            .push(Self::labeled("Colors", colors.into()));
+
+        let editor = match self.picking {
+            Some(slot) => editor.push(Self::labeled(
+                slot.label(),
+                self.color_picker.view().map(Message::ColorPicker),
+            )),
+            None => editor,
+        };
+
+        let editor = editor
+            .push(Self::labeled("", flags.into()))
+            .push(Self::labeled(
+                "Scale X / Y",
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(Self::float(
+                        &mut self.scale_x,
+                        style.scale_x,
+                        (0.0, 1000.0, 1.0),
+                        Message::ScaleXPicked,
+                    ))
+                    .push(Self::float(
+                        &mut self.scale_y,
+                        style.scale_y,
+                        (0.0, 1000.0, 1.0),
+                        Message::ScaleYPicked,
+                    ))
+                    .into(),
+            ))
+            .push(Self::labeled(
+                "Spacing / Angle",
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(Self::float(
+                        &mut self.spacing,
+                        style.spacing,
+                        (-100.0, 100.0, 0.5),
+                        Message::SpacingPicked,
+                    ))
+                    .push(Self::float(
+                        &mut self.angle,
+                        style.angle,
+                        (-360.0, 360.0, 1.0),
+                        Message::AnglePicked,
+                    ))
+                    .into(),
+            ))
+            .push(Self::labeled(
+                "Border",
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .align_items(Align::Center)
+                    .push(
+                        PickList::new(
+                            &mut self.border_style,
+                            ALL_BORDER_STYLES,
+                            Some(style.border_style),
+                            Message::BorderStylePicked,
+                        )
+                        .text_size(TEXT_SIZE),
+                    )
+                    .push(Text::new("Outline").size(TEXT_SIZE))
+                    .push(Self::float(
+                        &mut self.outline,
+                        style.outline,
+                        (0.0, 100.0, 0.5),
+                        Message::OutlinePicked,
+                    ))
+                    .push(Text::new("Shadow").size(TEXT_SIZE))
+                    .push(Self::float(
+                        &mut self.shadow,
+                        style.shadow,
+                        (0.0, 100.0, 0.5),
+                        Message::ShadowPicked,
+                    ))
+                    .into(),
+            ))
+            .push(Self::labeled(
+                "Alignment",
+                PickList::new(
+                    &mut self.alignment,
+                    ALL_ALIGNMENTS,
+                    Some(style.alignment),
+                    Message::AlignmentPicked,
+                )
+                .text_size(TEXT_SIZE)
+                .into(),
+            ))
+            .push(Self::labeled(
+                "Margins L / R / V",
+                Row::new()
+                    .spacing(ROW_SPACING)
+                    .push(Self::integer(
+                        &mut self.margin_left,
+                        style.margin_left,
+                        Message::MarginLeftPicked,
+                    ))
+                    .push(Self::integer(
+                        &mut self.margin_right,
+                        style.margin_right,
+                        Message::MarginRightPicked,
+                    ))
+                    .push(Self::integer(
+                        &mut self.margin_vertical,
+                        style.margin_vertical,
+                        Message::MarginVerticalPicked,
+                    ))
+                    .into(),
+            ))
+            .push(Self::labeled(
+                "Encoding",
+                Self::integer(&mut self.encoding, style.encoding, Message::EncodingPicked),
+            ));
+
+        let catalog_selected = self.catalog_selected;
+        let catalog_styles = self
+            .catalog
+            .as_ref()
+            .map_or(&[][..], |catalog| &catalog.styles[..]);
+        self.catalog_buttons
+            .resize_with(catalog_styles.len(), button::State::default);
+
+        let catalog_list = self
+            .catalog_buttons
+            .iter_mut()
+            .zip(catalog_styles)
+            .enumerate()
+            .fold(
+                Column::new().width(Length::Fill),
+                |column, (index, (state, style))| {
+                    let label = if index == catalog_selected {
+                        format!("> {}", style.name)
+                    } else {
+                        style.name.clone()
+                    };
+                    column.push(
+                        Button::new(state, Text::new(label).size(TEXT_SIZE))
+                            .width(Length::Fill)
+                            .on_press(Message::CatalogStyleSelected(index)),
+                    )
+                },
+            );
+
+        let catalog_column = Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Units(LIST_WIDTH))
+            .push(Text::new("Catalog").size(TEXT_SIZE))
+            .push(
+                PickList::new(
+                    &mut self.catalog_pick,
+                    &self.catalog_names[..],
+                    self.catalog.as_ref().map(|catalog| catalog.name.clone()),
+                    Message::CatalogPicked,
+                )
+                .text_size(TEXT_SIZE)
+                .width(Length::Fill),
+            )
+            .push(
+                TextInput::new(
+                    &mut self.catalog_name,
+                    "New catalog",
+                    &self.catalog_name_input,
+                    Message::CatalogNameChanged,
+                )
+                .on_submit(Message::CreateCatalog)
+                .size(TEXT_SIZE),
+            )
+            .push(Self::button(
+                &mut self.create_catalog,
+                "Create catalog",
+                Message::CreateCatalog,
+            ))
+            .push(
+                Scrollable::new(&mut self.catalog_scroll)
+                    .height(Length::Fill)
+                    .push(catalog_list),
+            )
+            .push(Text::new("On name collision").size(TEXT_SIZE))
+            .push(
+                PickList::new(
+                    &mut self.conflict_pick,
+                    ALL_CONFLICTS,
+                    Some(self.conflict),
+                    Message::ConflictPicked,
+                )
+                .text_size(TEXT_SIZE)
+                .width(Length::Fill),
+            )
+            .push(Self::button(
+                &mut self.import_style,
+                "Import selected",
+                Message::ImportStyle,
+            ))
+            .push(Self::button(
+                &mut self.import_all,
+                "Import all",
+                Message::ImportAll,
+            ))
+            .push(Self::button(
+                &mut self.export_style,
+                "Export current style",
+                Message::ExportStyle,
+            ))
+            .push(Text::new(&self.catalog_status).size(TEXT_SIZE));
+
+        Row::new()
+            .spacing(ROW_SPACING)
+            .push(list_column)
+            .push(
+                Scrollable::new(&mut self.editor_scroll)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .push(editor),
+            )
+            .push(catalog_column)
+            .into()
+    }
+
+    #[inline(always)]
+    fn button<'a>(
+        state: &'a mut button::State,
+        label: &str,
+        message: Message,
+    ) -> Element<'a, Message> {
+        Button::new(state, Text::new(label).size(TEXT_SIZE))
+            .width(Length::Fill)
+            .on_press(message)
+            .into()
+    }
+
+    #[inline(always)]
+    fn labeled<'a>(label: &str, element: Element<'a, Message>) -> Element<'a, Message> {
+        Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Text::new(label)
+                    .size(TEXT_SIZE)
+                    .width(Length::Units(LABEL_WIDTH)),
+            )
+            .push(element)
+            .into()
+    }
+
+    #[inline(always)]
+    fn float<'a>(
+        state: &'a mut number_input::State,
+        value: f32,
+        (min, max, step): (f32, f32, f32),
+        message: fn(f32) -> Message,
+    ) -> Element<'a, Message> {
+        NumberInput::new(state, value, max, message)
+            .step(step)
+            .min(min)
+            .into()
+    }
+
+    #[inline(always)]
+    fn integer<'a>(
+        state: &'a mut number_input::State,
+        value: usize,
+        message: fn(usize) -> Message,
+    ) -> Element<'a, Message> {
+        NumberInput::new(state, value, 9999, message)
+            .step(1)
+            .min(0)
+            .into()
+    }
+}
@@ -0,0 +1,323 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+pub const DEFAULT_STYLE: &str = "Default";
+
+pub const STYLE_FORMAT: &str = "Format: Name, Fontname, Fontsize, PrimaryColour, \
+     SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, \
+     ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, \
+     MarginL, MarginR, MarginV, Encoding";
+
+const STYLE_FIELDS: usize = 23;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("A style line must start with \"Style:\"")]
+    NotAStyle,
+    #[error("A style line must have 23 fields")]
+    WrongFieldCount,
+    #[error("Invalid value for the {0} field")]
+    InvalidField(&'static str),
+    #[error("Invalid color")]
+    InvalidColor,
+}
+
+/// A color in ASS order. An `alpha` of 0 is opaque, 255 is transparent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl AssColor {
+    pub const fn new(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    /// Value of a `\c` tag, which has no alpha: `&HBBGGRR&`.
+    pub fn to_override(self) -> String {
+        format!("&H{:02X}{:02X}{:02X}&", self.blue, self.green, self.red)
+    }
+
+    /// Value of a `\1a` tag: `&HAA&`.
+    pub fn alpha_override(self) -> String {
+        format!("&H{:02X}&", self.alpha)
+    }
+}
+
+impl FromStr for AssColor {
+    type Err = Error;
+
+    // Accepts `&HAABBGGRR`, `&HBBGGRR`, with or without the trailing `&`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim().trim_end_matches('&');
+        let hex = value
+            .strip_prefix("&H")
+            .or_else(|| value.strip_prefix("&h"))
+            .ok_or(Error::InvalidColor)?;
+
+        if hex.is_empty() || hex.len() > 8 {
+            return Err(Error::InvalidColor);
+        }
+        let value = u32::from_str_radix(hex, 16).map_err(|_| Error::InvalidColor)?;
+        let [alpha, blue, green, red] = value.to_be_bytes();
+
+        Ok(Self::new(red, green, blue, alpha))
+    }
+}
+
+impl fmt::Display for AssColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "&H{:02X}{:02X}{:02X}{:02X}",
+            self.alpha, self.blue, self.green, self.red
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    Outline,
+    OpaqueBox,
+}
+
+impl From<BorderStyle> for &'static str {
+    fn from(val: BorderStyle) -> Self {
+        match val {
+            BorderStyle::Outline => "Outline and shadow",
+            BorderStyle::OpaqueBox => "Opaque box",
+        }
+    }
+}
+
+pub static ALL_BORDER_STYLES: &[BorderStyle] = &[BorderStyle::Outline, BorderStyle::OpaqueBox];
+
+impl fmt::Display for BorderStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+/// Numpad position of the text, as used by `\an`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment(pub u8);
+
+pub static ALL_ALIGNMENTS: &[Alignment] = &[
+    Alignment(1),
+    Alignment(2),
+    Alignment(3),
+    Alignment(4),
+    Alignment(5),
+    Alignment(6),
+    Alignment(7),
+    Alignment(8),
+    Alignment(9),
+];
+
+impl fmt::Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = match self.0 {
+            1 => "bottom left",
+            2 => "bottom center",
+            3 => "bottom right",
+            4 => "middle left",
+            5 => "middle center",
+            6 => "middle right",
+            7 => "top left",
+            8 => "top center",
+            9 => "top right",
+            _ => "unknown",
+        };
+        write!(f, "{} ({})", self.0, position)
+    }
+}
+
+/// A `[V4+ Styles]` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssStyle {
+    pub name: String,
+    pub font_name: String,
+    pub font_size: f32,
+    pub primary_color: AssColor,
+    pub secondary_color: AssColor,
+    pub outline_color: AssColor,
+    pub back_color: AssColor,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike_out: bool,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub spacing: f32,
+    pub angle: f32,
+    pub border_style: BorderStyle,
+    pub outline: f32,
+    pub shadow: f32,
+    pub alignment: Alignment,
+    pub margin_left: usize,
+    pub margin_right: usize,
+    pub margin_vertical: usize,
+    pub encoding: usize,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_STYLE.to_owned(),
+            font_name: "Arial".to_owned(),
+            font_size: 48.0,
+            primary_color: AssColor::new(0xFF, 0xFF, 0xFF, 0),
+            secondary_color: AssColor::new(0xFF, 0, 0, 0),
+            outline_color: AssColor::new(0, 0, 0, 0),
+            back_color: AssColor::new(0, 0, 0, 0),
+            bold: false,
+            italic: false,
+            underline: false,
+            strike_out: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: BorderStyle::Outline,
+            outline: 2.0,
+            shadow: 2.0,
+            alignment: Alignment(2),
+            margin_left: 10,
+            margin_right: 10,
+            margin_vertical: 10,
+            encoding: 1,
+        }
+    }
+}
+
+impl AssStyle {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// A script showing `text` with this style, used for previews.
+    pub fn sample_script(&self, text: &str, width: u32, height: u32) -> String {
+        format!(
+            "[Script Info]\nScriptType: v4.00+\nPlayResX: {}\nPlayResY: {}\n\n\
+             [V4+ Styles]\n{}\n{}\n\n\
+             [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, \
+             Effect, Text\nDialogue: 0,0:00:00.00,0:01:00.00,{},,0,0,0,,{}\n",
+            width, height, STYLE_FORMAT, self, self.name, text
+        )
+    }
+}
+
+impl FromStr for AssStyle {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let values = line.trim().strip_prefix("Style:").ok_or(Error::NotAStyle)?;
+        let fields: Vec<&str> = values.splitn(STYLE_FIELDS, ',').map(str::trim).collect();
+        if fields.len() != STYLE_FIELDS {
+            return Err(Error::WrongFieldCount);
+        }
+
+        let color = |index: usize, name| {
+            fields[index]
+                .parse::<AssColor>()
+                .map_err(|_| Error::InvalidField(name))
+        };
+        let number = |index: usize, name| {
+            fields[index]
+                .parse::<f32>()
+                .map_err(|_| Error::InvalidField(name))
+        };
+        let integer = |index: usize, name| {
+            fields[index]
+                .parse::<i64>()
+                .map_err(|_| Error::InvalidField(name))
+        };
+        let flag = |index: usize, name| integer(index, name).map(|value| value != 0);
+        let margin = |index: usize, name| integer(index, name).map(|value| value.max(0) as usize);
+
+        Ok(Self {
+            name: fields[0].to_owned(),
+            font_name: fields[1].to_owned(),
+            font_size: number(2, "Fontsize")?,
+            primary_color: color(3, "PrimaryColour")?,
+            secondary_color: color(4, "SecondaryColour")?,
+            outline_color: color(5, "OutlineColour")?,
+            back_color: color(6, "BackColour")?,
+            bold: flag(7, "Bold")?,
+            italic: flag(8, "Italic")?,
+            underline: flag(9, "Underline")?,
+            strike_out: flag(10, "StrikeOut")?,
+            scale_x: number(11, "ScaleX")?,
+            scale_y: number(12, "ScaleY")?,
+            spacing: number(13, "Spacing")?,
+            angle: number(14, "Angle")?,
+            border_style: match integer(15, "BorderStyle")? {
+                3 => BorderStyle::OpaqueBox,
+                _ => BorderStyle::Outline,
+            },
+            outline: number(16, "Outline")?,
+            shadow: number(17, "Shadow")?,
+            alignment: match integer(18, "Alignment")? {
+                alignment @ 1..=9 => Alignment(alignment as u8),
+                _ => return Err(Error::InvalidField("Alignment")),
+            },
+            margin_left: margin(19, "MarginL")?,
+            margin_right: margin(20, "MarginR")?,
+            margin_vertical: margin(21, "MarginV")?,
+            encoding: margin(22, "Encoding")?,
+        })
+    }
+}
+
+impl fmt::Display for AssStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ASS booleans are -1 and 0
+        let flag = |value: bool| if value { -1 } else { 0 };
+        let border_style = match self.border_style {
+            BorderStyle::Outline => 1,
+            BorderStyle::OpaqueBox => 3,
+        };
+
+        write!(
+            f,
+            "Style: {},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.name,
+            self.font_name,
+            self.font_size,
+            self.primary_color,
+            self.secondary_color,
+            self.outline_color,
+            self.back_color,
+            flag(self.bold),
+            flag(self.italic),
+            flag(self.underline),
+            flag(self.strike_out),
+            self.scale_x,
+            self.scale_y,
+            self.spacing,
+            self.angle,
+            border_style,
+            self.outline,
+            self.shadow,
+            self.alignment.0,
+            self.margin_left,
+            self.margin_right,
+            self.margin_vertical,
+            self.encoding
+        )
+    }
+}
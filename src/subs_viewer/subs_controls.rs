@@ -1,93 +1,155 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
 use iced::pick_list;
 use iced::{
-    text_input, tooltip, Align, Checkbox, Column, Element, Length, PickList, Row, TextInput,
-    Tooltip,
+    button, text_input, tooltip, Align, Button, Checkbox, Column, Element, Length, PickList, Row,
+    Text, TextInput, Tooltip,
 };
 
 use iced_aw::number_input::{self, NumberInput};
 
+use super::color_picker::{self, ColorPicker};
+use super::formatting::{self, Format, Toggle, ALL_TOGGLES};
+use super::preview::PreviewFrame;
+use super::styles::{Alignment, AssColor, AssStyle, ALL_ALIGNMENTS};
+use super::text_editor::{self, TextEditor};
 use super::SubsControlsValues;
 
+/// Color override tags that can be inserted in the text.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum StyleList {
-    r#Default,
-}
-
-impl Default for StyleList {
-    fn default() -> Self {
-        Self::r#Default
-    }
+pub enum ColorTag {
+    Primary,
+    Outline,
+    Shadow,
 }
 
-impl From<StyleList> for &'static str {
-    fn from(val: StyleList) -> Self {
+impl From<ColorTag> for &'static str {
+    fn from(val: ColorTag) -> Self {
         match val {
-            StyleList::r#Default => "Default",
+            ColorTag::Primary => "\\c",
+            ColorTag::Outline => "\\3c",
+            ColorTag::Shadow => "\\4c",
         }
     }
 }
 
-static ALL_STYLES: &[StyleList] = &[StyleList::r#Default];
+static ALL_COLOR_TAGS: &[ColorTag] = &[ColorTag::Primary, ColorTag::Outline, ColorTag::Shadow];
 
-impl std::fmt::Display for StyleList {
+impl std::fmt::Display for ColorTag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let val: &str = (*self).into();
         write!(f, "{}", val)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ActorList {
-    Undefined,
-}
+impl ColorTag {
+    /// Index of the color in the tags, as in `\3c`.
+    pub fn index(self) -> u8 {
+        match self {
+            ColorTag::Primary => 1,
+            ColorTag::Outline => 3,
+            ColorTag::Shadow => 4,
+        }
+    }
 
-impl Default for ActorList {
-    fn default() -> Self {
-        Self::Undefined
+    /// Color of `style` the tag overrides.
+    pub fn style_color(self, style: &AssStyle) -> AssColor {
+        match self {
+            ColorTag::Primary => style.primary_color,
+            ColorTag::Outline => style.outline_color,
+            ColorTag::Shadow => style.back_color,
+        }
     }
-}
 
-impl From<ActorList> for &'static str {
-    fn from(val: ActorList) -> Self {
-        match val {
-            ActorList::Undefined => "",
+    /// Tags setting `color`, with an alpha tag when it is not opaque.
+    pub fn tags(self, color: AssColor) -> String {
+        let alpha = match self {
+            ColorTag::Primary => "\\1a",
+            ColorTag::Outline => "\\3a",
+            ColorTag::Shadow => "\\4a",
+        };
+
+        let mut tags = format!("{}{}", self, color.to_override());
+        if color.alpha != 0 {
+            tags.push_str(alpha);
+            tags.push_str(&color.alpha_override());
         }
+        tags
     }
 }
 
-static ALL_ACTORS: &[ActorList] = &[ActorList::Undefined];
-
-impl std::fmt::Display for ActorList {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let val: &str = (*self).into();
-        write!(f, "{}", val)
+/// Actors of `actors` starting with `typed`, ignoring the case. An actor
+/// typed in full is not suggested.
+pub fn complete_actor<'a>(actors: &[&'a str], typed: &str) -> Vec<&'a str> {
+    let typed = typed.trim().to_lowercase();
+    if typed.is_empty() {
+        return Vec::new();
     }
+
+    actors
+        .iter()
+        .copied()
+        .filter(|actor| actor.to_lowercase().starts_with(&typed) && actor.to_lowercase() != typed)
+        .take(MAX_SUGGESTIONS)
+        .collect()
 }
 
 const ROW_SPACING: u16 = 6;
 const COLUMN_SPACING: u16 = 6;
 const ROW_MARGIN_SPACING: u16 = 4;
+const MAX_SUGGESTIONS: usize = 5;
+const FONT_NAME_WIDTH: u16 = 140;
+const FONT_SIZE_WIDTH: u16 = 50;
 
 #[derive(Default)]
 pub struct SubsControls {
-    style_list: pick_list::State<StyleList>,
-    actor_list: pick_list::State<ActorList>,
+    style: pick_list::State<String>,
+    manage_styles: button::State,
+    actor: text_input::State,
+    actor_suggestions: Vec<button::State>,
+    manage_actors: button::State,
+    automate: button::State,
     layer: number_input::State,
     margin_left: number_input::State,
     margin_vertical: number_input::State,
     margin_right: number_input::State,
+    effect: text_input::State,
+    shift_times: button::State,
+    retime: button::State,
+    process_timing: button::State,
+    sync_to_reference: button::State,
     start_time: text_input::State,
     end_time: text_input::State,
     duration: text_input::State,
-    text: text_input::State,
+    text: text_editor::State,
+    toggles: [button::State; 4],
+    font_name: text_input::State,
+    font_size: text_input::State,
+    color_tags: [button::State; 3],
+    alignment: pick_list::State<Alignment>,
+    typeset: button::State,
+    track_motion: button::State,
+    edit_karaoke: button::State,
+    time_kanji: button::State,
     notes: text_input::State,
+    color_picker: ColorPicker,
+    // Tag whose color is being picked
+    picking: Option<ColorTag>,
+    font_name_input: String,
+    font_size_input: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     CommentToggled(bool),
-    StyleListPicked(StyleList),
-    ActorListPicked(ActorList),
+    StylePicked(String),
+    ManageStyles,
+    ActorChanged(String),
+    ActorSubmitted,
+    ActorPicked(String),
+    ManageActors,
+    Automate,
     LayerPicked(usize),
     StartTimeChanged(String),
     EndTimeChanged(String),
@@ -95,16 +157,121 @@ pub enum Message {
     MarginLeftPicked(usize),
     MarginVerticalPicked(usize),
     MarginRightPicked(usize),
+    EffectChanged(String),
+    ShiftTimes,
+    Retime,
+    ProcessTiming,
+    SyncToReference,
     TextChanged(String),
+    TextSubmitted,
+    ToggleFormat(Toggle),
+    FontNameChanged(String),
+    FontNameSubmitted,
+    FontSizeChanged(String),
+    FontSizeSubmitted,
+    PickTagColor(ColorTag),
+    ColorPicker(color_picker::Message),
+    AlignmentPicked(Alignment),
+    Typeset,
+    TrackMotion,
+    EditKaraoke,
+    TimeKanji,
     NotesChanged(String),
 }
 
+impl Message {
+    /// Name of the edited field, shown in the history.
+    pub fn field(&self) -> &'static str {
+        match self {
+            Message::CommentToggled(_) => "comment",
+            Message::StylePicked(_) | Message::ManageStyles => "style",
+            Message::ActorChanged(_)
+            | Message::ActorSubmitted
+            | Message::ActorPicked(_)
+            | Message::ManageActors => "actor",
+            Message::Automate | Message::ShiftTimes | Message::Retime | Message::ProcessTiming => {
+                "lines"
+            }
+            Message::LayerPicked(_) => "layer",
+            Message::StartTimeChanged(_) => "start time",
+            Message::EndTimeChanged(_) => "end time",
+            Message::DurationChanged(_) => "duration",
+            Message::MarginLeftPicked(_) => "left margin",
+            Message::MarginVerticalPicked(_) => "vertical margin",
+            Message::MarginRightPicked(_) => "right margin",
+            Message::EffectChanged(_) => "effect",
+            Message::TextChanged(_)
+            | Message::TextSubmitted
+            | Message::ToggleFormat(_)
+            | Message::FontNameChanged(_)
+            | Message::FontNameSubmitted
+            | Message::FontSizeChanged(_)
+            | Message::FontSizeSubmitted
+            | Message::PickTagColor(_)
+            | Message::ColorPicker(_)
+            | Message::AlignmentPicked(_)
+            | Message::Typeset
+            | Message::TrackMotion
+            | Message::EditKaraoke
+            | Message::TimeKanji => "text",
+            Message::NotesChanged(_) => "notes",
+        }
+    }
+
+    /// Whether the change is applied to every selected line instead of the
+    /// active one only.
+    pub fn applies_to_selection(&self) -> bool {
+        matches!(
+            self,
+            Message::CommentToggled(_)
+                | Message::StylePicked(_)
+                | Message::ActorChanged(_)
+                | Message::ActorPicked(_)
+                | Message::LayerPicked(_)
+                | Message::MarginLeftPicked(_)
+                | Message::MarginVerticalPicked(_)
+                | Message::MarginRightPicked(_)
+                | Message::EffectChanged(_)
+        )
+    }
+
+    /// Whether the message comes from the formatting toolbar.
+    pub fn is_formatting(&self) -> bool {
+        matches!(
+            self,
+            Message::ToggleFormat(_)
+                | Message::FontNameChanged(_)
+                | Message::FontNameSubmitted
+                | Message::FontSizeChanged(_)
+                | Message::FontSizeSubmitted
+                | Message::AlignmentPicked(_)
+        )
+    }
+
+    /// Whether the change comes from typing, so that consecutive keystrokes
+    /// make a single history entry.
+    pub fn is_typing(&self) -> bool {
+        matches!(
+            self,
+            Message::ActorChanged(_)
+                | Message::StartTimeChanged(_)
+                | Message::EndTimeChanged(_)
+                | Message::DurationChanged(_)
+                | Message::EffectChanged(_)
+                | Message::TextChanged(_)
+                | Message::NotesChanged(_)
+        )
+    }
+}
+
 impl SubsControls {
     pub fn update(&mut self, message: Message, subs_controls: &mut SubsControlsValues) {
         match message {
             Message::CommentToggled(is_comment) => subs_controls.is_comment = is_comment,
-            Message::StyleListPicked(style_list) => subs_controls.style_list = style_list,
-            Message::ActorListPicked(actor_list) => subs_controls.actor_list = actor_list,
+            Message::StylePicked(style) => subs_controls.style = style,
+            Message::ActorChanged(actor) | Message::ActorPicked(actor) if !actor.contains(',') => {
+                subs_controls.actor = actor
+            }
             Message::LayerPicked(layer) => subs_controls.layer = layer,
             Message::StartTimeChanged(start_time) => subs_controls.start_time = start_time,
             Message::EndTimeChanged(end_time) => subs_controls.end_time = end_time,
@@ -114,12 +281,52 @@ impl SubsControls {
                 subs_controls.margin_vertical = margin_vertical
             }
             Message::MarginRightPicked(margin_right) => subs_controls.margin_right = margin_right,
+            Message::EffectChanged(effect) if !effect.contains(',') => {
+                subs_controls.effect = effect
+            }
+            // Commas separate the fields of the event lines
+            Message::ActorChanged(_) | Message::ActorPicked(_) | Message::EffectChanged(_) => {}
             Message::TextChanged(text) => subs_controls.text = text,
+            // Handled by the subs viewer
+            Message::TextSubmitted
+            | Message::ToggleFormat(_)
+            | Message::FontNameChanged(_)
+            | Message::FontNameSubmitted
+            | Message::FontSizeChanged(_)
+            | Message::FontSizeSubmitted
+            | Message::PickTagColor(_)
+            | Message::ColorPicker(_)
+            | Message::AlignmentPicked(_)
+            | Message::Typeset
+            | Message::TrackMotion
+            | Message::EditKaraoke
+            | Message::TimeKanji
+            | Message::ManageStyles
+            | Message::ActorSubmitted
+            | Message::ManageActors
+            | Message::Automate
+            | Message::ShiftTimes
+            | Message::Retime
+            | Message::ProcessTiming
+            | Message::SyncToReference => {}
             Message::NotesChanged(notes) => subs_controls.notes = notes,
         }
     }
 
-    pub fn view(&mut self, values: &SubsControlsValues) -> Element<Message> {
+    pub fn view(
+        &mut self,
+        values: &SubsControlsValues,
+        style_names: Vec<String>,
+        actors: &[&str],
+    ) -> Element<Message> {
+        let suggestions = if self.actor.is_focused() {
+            complete_actor(actors, &values.actor)
+        } else {
+            Vec::new()
+        };
+        self.actor_suggestions
+            .resize_with(suggestions.len(), button::State::default);
+
         let first_row = Row::new()
             .spacing(ROW_SPACING)
             .align_items(Align::Center)
@@ -131,26 +338,59 @@ impl SubsControls {
             .push(Self::tooltip(
                 "Style for the selected line",
                 Self::picklist(
-                    &mut self.style_list,
-                    ALL_STYLES,
-                    values.style_list,
-                    Message::StyleListPicked,
+                    &mut self.style,
+                    style_names,
+                    values.style.clone(),
+                    Message::StylePicked,
                 )
                 .into(),
                 tooltip::Position::Bottom,
             ))
             .push(Self::tooltip(
-                "Actor name for this speech",
-                Self::picklist(
-                    &mut self.actor_list,
-                    ALL_ACTORS,
-                    values.actor_list,
-                    Message::ActorListPicked,
+                "Create and edit the styles",
+                Button::new(&mut self.manage_styles, Text::new("Styles").size(16))
+                    .on_press(Message::ManageStyles)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Actor name for this speech, Enter completes it",
+                TextInput::new(
+                    &mut self.actor,
+                    "Actor",
+                    &values.actor,
+                    Message::ActorChanged,
                 )
+                .on_submit(Message::ActorSubmitted)
+                .width(Length::Fill)
                 .into(),
                 tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Rename the actors of the document",
+                Button::new(&mut self.manage_actors, Text::new("Actors").size(16))
+                    .on_press(Message::ManageActors)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Run the macros of the automation scripts",
+                Button::new(&mut self.automate, Text::new("Automation").size(16))
+                    .on_press(Message::Automate)
+                    .into(),
+                tooltip::Position::Bottom,
             ));
 
+        let first_row = self.actor_suggestions.iter_mut().zip(suggestions).fold(
+            first_row,
+            |row, (state, actor)| {
+                row.push(
+                    Button::new(state, Text::new(actor).size(14))
+                        .on_press(Message::ActorPicked(actor.to_owned())),
+                )
+            },
+        );
+
         let second_row = Row::new()
             .spacing(ROW_SPACING)
             .align_items(Align::Center)
@@ -184,37 +424,247 @@ impl SubsControls {
                 values.margin_left,
                 values.margin_vertical,
                 values.margin_right,
+            ))
+            .push(TextInput::new(
+                &mut self.effect,
+                "Effect",
+                &values.effect,
+                Message::EffectChanged,
+            ))
+            .push(Self::tooltip(
+                "Move lines earlier or later",
+                Button::new(&mut self.shift_times, Text::new("Shift").size(16))
+                    .on_press(Message::ShiftTimes)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Convert the times to another frame rate",
+                Button::new(&mut self.retime, Text::new("FPS").size(16))
+                    .on_press(Message::Retime)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Add lead-in and lead-out, chain lines and snap them to keyframes",
+                Button::new(&mut self.process_timing, Text::new("Timing").size(16))
+                    .on_press(Message::ProcessTiming)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Match the timing of a reference subtitle file",
+                Button::new(&mut self.sync_to_reference, Text::new("Sync").size(16))
+                    .on_press(Message::SyncToReference)
+                    .into(),
+                tooltip::Position::Bottom,
+            ));
+
+        let toolbar = self.toggles.iter_mut().zip(ALL_TOGGLES).fold(
+            Row::new().spacing(ROW_SPACING).align_items(Align::Center),
+            |row, (state, &toggle)| {
+                row.push(Self::tooltip(
+                    &format!("Toggle {} on the selection", toggle.name()),
+                    Button::new(state, Text::new(toggle.to_string()).size(16))
+                        .on_press(Message::ToggleFormat(toggle))
+                        .into(),
+                    tooltip::Position::Bottom,
+                ))
+            },
+        );
+
+        let toolbar = toolbar
+            .push(Self::tooltip(
+                "Font of the selection, Enter applies it",
+                TextInput::new(
+                    &mut self.font_name,
+                    "Font",
+                    &self.font_name_input,
+                    Message::FontNameChanged,
+                )
+                .on_submit(Message::FontNameSubmitted)
+                .width(Length::Units(FONT_NAME_WIDTH))
+                .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Font size of the selection, Enter applies it",
+                TextInput::new(
+                    &mut self.font_size,
+                    "Size",
+                    &self.font_size_input,
+                    Message::FontSizeChanged,
+                )
+                .on_submit(Message::FontSizeSubmitted)
+                .width(Length::Units(FONT_SIZE_WIDTH))
+                .into(),
+                tooltip::Position::Bottom,
+            ));
+
+        let toolbar =
+            self.color_tags
+                .iter_mut()
+                .zip(ALL_COLOR_TAGS)
+                .fold(toolbar, |row, (state, &tag)| {
+                    row.push(Self::tooltip(
+                        "Color the selection",
+                        Button::new(state, Text::new(tag.to_string()).size(16))
+                            .on_press(Message::PickTagColor(tag))
+                            .into(),
+                        tooltip::Position::Bottom,
+                    ))
+                });
+
+        let toolbar = toolbar
+            .push(Self::tooltip(
+                "Alignment of the line",
+                PickList::new(
+                    &mut self.alignment,
+                    ALL_ALIGNMENTS,
+                    formatting::line_alignment(&values.text),
+                    Message::AlignmentPicked,
+                )
+                .text_size(16)
+                .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Place the line by dragging on its preview",
+                Button::new(&mut self.typeset, Text::new("Typeset").size(16))
+                    .on_press(Message::Typeset)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Follow After Effects tracking data, one line per frame",
+                Button::new(&mut self.track_motion, Text::new("Motion").size(16))
+                    .on_press(Message::TrackMotion)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Split the line into syllables and time them",
+                Button::new(&mut self.edit_karaoke, Text::new("Karaoke").size(16))
+                    .on_press(Message::EditKaraoke)
+                    .into(),
+                tooltip::Position::Bottom,
+            ))
+            .push(Self::tooltip(
+                "Copy the syllable timing of the first selected line to the second",
+                Button::new(&mut self.time_kanji, Text::new("Kanji").size(16))
+                    .on_press(Message::TimeKanji)
+                    .into(),
+                tooltip::Position::Bottom,
             ));
 
         let third_row = Row::new()
             .spacing(ROW_SPACING)
             .align_items(Align::Center)
             .push(
-                TextInput::new(
+                TextEditor::new(
                     &mut self.text,
                     "Sub text",
                     &values.text,
                     Message::TextChanged,
                 )
-                .width(Length::Fill),
-            )
-            .push(
-                TextInput::new(
-                    &mut self.notes,
-                    "Notes",
-                    &values.notes,
-                    Message::NotesChanged,
-                )
+                .on_submit(Message::TextSubmitted)
                 .width(Length::Fill),
             );
 
-        Column::new()
+        let third_row = third_row.push(
+            TextInput::new(
+                &mut self.notes,
+                "Notes",
+                &values.notes,
+                Message::NotesChanged,
+            )
+            .width(Length::Fill),
+        );
+
+        let column = Column::new()
             .spacing(COLUMN_SPACING)
             .align_items(Align::Center)
             .push(first_row)
             .push(second_row)
-            .push(third_row)
-            .into()
+            .push(toolbar)
+            .push(third_row);
+
+        match self.picking {
+            Some(_) => column
+                .push(self.color_picker.view().map(Message::ColorPicker))
+                .into(),
+            None => column.into(),
+        }
+    }
+
+    pub fn open_color_picker(
+        &mut self,
+        tag: ColorTag,
+        color: AssColor,
+        frame: Option<PreviewFrame>,
+    ) {
+        self.color_picker.open(color, frame);
+        self.picking = Some(tag);
+    }
+
+    /// Returns the tag and the color to insert once picked.
+    pub fn update_color_picker(
+        &mut self,
+        message: color_picker::Message,
+    ) -> Option<(ColorTag, AssColor)> {
+        match self.color_picker.update(message)? {
+            color_picker::Event::Picked(color) => Some((self.picking.take()?, color)),
+            color_picker::Event::Cancelled => {
+                self.picking = None;
+                None
+            }
+        }
+    }
+
+    /// Byte offset in `text` of the cursor, or of the selection start, of
+    /// the text field.
+    pub fn text_cursor(&self, text: &str) -> usize {
+        self.text.selection(text).start
+    }
+
+    pub fn text_selection(&self, text: &str) -> Range<usize> {
+        self.text.selection(text)
+    }
+
+    pub fn select_text(&mut self, selection: Range<usize>) {
+        self.text.select(selection);
+    }
+
+    /// Returns the format to apply from a toolbar message.
+    pub fn update_toolbar(&mut self, message: Message) -> Option<Format> {
+        match message {
+            Message::ToggleFormat(toggle) => Some(Format::Toggle(toggle)),
+            Message::FontNameChanged(name) => {
+                self.font_name_input = name;
+                None
+            }
+            Message::FontNameSubmitted => {
+                let name = self.font_name_input.trim();
+                if name.is_empty() {
+                    None
+                } else {
+                    Some(Format::FontName(name.to_owned()))
+                }
+            }
+            Message::FontSizeChanged(size) => {
+                self.font_size_input = size;
+                None
+            }
+            Message::FontSizeSubmitted => self
+                .font_size_input
+                .trim()
+                .parse()
+                .ok()
+                .filter(|size: &f32| *size > 0.0)
+                .map(Format::FontSize),
+            Message::AlignmentPicked(alignment) => Some(Format::Alignment(alignment)),
+            _ => None,
+        }
     }
 
     #[inline(always)]
@@ -278,7 +728,7 @@ impl SubsControls {
     #[inline(always)]
     fn picklist<'a, T>(
         state: &'a mut pick_list::State<T>,
-        all: &'a [T],
+        all: impl Into<Cow<'a, [T]>>,
         value: T,
         message: impl Fn(T) -> Message + 'static,
     ) -> PickList<'a, T, Message>
@@ -292,7 +742,8 @@ impl SubsControls {
     }
 }
 
-mod style {
+// Shared with the tooltips of the table
+pub(super) mod style {
     use iced::container;
     use iced::Color;
 
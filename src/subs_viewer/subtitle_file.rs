@@ -0,0 +1,673 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use thiserror::Error;
+
+use super::styles::{self, AssStyle, STYLE_FORMAT};
+use super::tags::{Item, Line, NodeKind, TagKind};
+use super::time::Time;
+use super::SubsControlsValues;
+
+const EVENT_FORMAT: &str =
+    "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+const EVENT_FIELDS: &[&str] = &[
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+// Tags SRT has, as written by most players
+const SRT_TAGS: &[&str] = &["i", "b", "u", "s"];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unknown subtitle format, the file must end with .ass or .srt")]
+    UnknownFormat,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Line {line}: {source}")]
+    Style { line: usize, source: styles::Error },
+    #[error("Line {line}: wrong number of fields")]
+    WrongFieldCount { line: usize },
+    #[error("Line {line}: invalid value for the {field} field")]
+    InvalidField { line: usize, field: String },
+    #[error("Line {line}: a subtitle must start with its times, as 00:00:01,000 --> 00:00:02,000")]
+    InvalidCue { line: usize },
+    #[error("Line {line}: SSA styles are not supported, only the V4+ styles of ASS files")]
+    SsaStyles { line: usize },
+}
+
+/// Subtitle formats, told apart by the file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ass,
+    Srt,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("ass") => Ok(Format::Ass),
+            Some("srt") => Ok(Format::Srt),
+            _ => Err(Error::UnknownFormat),
+        }
+    }
+}
+
+// Sections of an ASS file, the others are kept as they are
+enum Section {
+    Info,
+    Styles,
+    Events,
+    Other,
+}
+
+/// The script info, styles and lines of a subtitle file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubtitleFile {
+    /// `[Script Info]` lines, comments included.
+    pub info: Vec<String>,
+    pub styles: Vec<AssStyle>,
+    pub lines: Vec<SubsControlsValues>,
+    // Sections such as embedded fonts, written back as they were read
+    sections: Vec<(String, Vec<String>)>,
+}
+
+impl SubtitleFile {
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let format = Format::of(path)?;
+        let text = fs::read_to_string(path)?;
+        // Editors often start files with a byte order mark
+        let text = text.trim_start_matches('\u{feff}');
+        match format {
+            Format::Ass => Self::from_ass(text),
+            Format::Srt => Self::from_srt(text),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let text = match Format::of(path)? {
+            Format::Ass => self.to_ass(),
+            Format::Srt => self.to_srt(),
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Value of the `key` script info.
+    pub fn info(&self, key: &str) -> Option<&str> {
+        self.info.iter().find_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if name.trim() == key => Some(value.trim()),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn set_info(&mut self, key: &str, value: &str) {
+        let line = format!("{}: {}", key, value);
+        let prefix = format!("{}:", key);
+        match self.info.iter_mut().find(|info| info.starts_with(&prefix)) {
+            Some(info) => *info = line,
+            None => self.info.push(line),
+        }
+    }
+
+    /// `PlayResX` and `PlayResY`, the size positions and sizes refer to.
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        let width = self.info("PlayResX")?.parse().ok()?;
+        let height = self.info("PlayResY")?.parse().ok()?;
+        Some((width, height))
+    }
+
+    pub fn from_ass(text: &str) -> Result<Self, Error> {
+        let mut file = Self::default();
+        let mut section = Section::Info;
+        let mut format: Vec<String> = EVENT_FIELDS.iter().map(|&field| field.to_owned()).collect();
+        let mut style_format: Option<Vec<String>> = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len() - 1] {
+                    "Script Info" => Section::Info,
+                    "V4+ Styles" => Section::Styles,
+                    // Their fields and alignments differ from the V4+ ones
+                    "V4 Styles" => return Err(Error::SsaStyles { line: number }),
+                    "Events" => Section::Events,
+                    name => {
+                        file.sections.push((name.to_owned(), Vec::new()));
+                        Section::Other
+                    }
+                };
+                continue;
+            }
+
+            match section {
+                Section::Info => file.info.push(line.to_owned()),
+                Section::Styles if line.starts_with("Format:") => {
+                    style_format = Some(fields(line));
+                }
+                Section::Styles if line.starts_with("Style:") => {
+                    // Styles are kept in the standard order, the one they are written in
+                    let line = match &style_format {
+                        Some(format) => standard_style(line, format, number)?,
+                        None => line.to_owned(),
+                    };
+                    let style = line.parse().map_err(|source| Error::Style {
+                        line: number,
+                        source,
+                    })?;
+                    file.styles.push(style);
+                }
+                Section::Styles => {}
+                Section::Events => {
+                    if line.starts_with("Format:") {
+                        format = fields(line);
+                    } else if let Some(event) = parse_event(line, &format, number)? {
+                        file.lines.push(event);
+                    }
+                }
+                Section::Other => {
+                    if let Some((_, lines)) = file.sections.last_mut() {
+                        lines.push(line.to_owned());
+                    }
+                }
+            }
+        }
+        Ok(file)
+    }
+
+    pub fn to_ass(&self) -> String {
+        let mut text = String::from("[Script Info]\n");
+        if self.info("ScriptType").is_none() {
+            text.push_str("ScriptType: v4.00+\n");
+        }
+        for line in &self.info {
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        text.push_str("\n[V4+ Styles]\n");
+        text.push_str(STYLE_FORMAT);
+        text.push('\n');
+        if self.styles.is_empty() {
+            text.push_str(&format!("{}\n", AssStyle::default()));
+        }
+        for style in &self.styles {
+            text.push_str(&format!("{}\n", style));
+        }
+
+        text.push_str("\n[Events]\n");
+        text.push_str(EVENT_FORMAT);
+        text.push('\n');
+        for line in &self.lines {
+            text.push_str(&format!(
+                "{}: {},{},{},{},{},{},{},{},{},{}\n",
+                if line.is_comment {
+                    "Comment"
+                } else {
+                    "Dialogue"
+                },
+                line.layer,
+                ass_time(line.start().unwrap_or_default()),
+                ass_time(line.end().unwrap_or_default()),
+                line.style,
+                line.actor,
+                line.margin_left,
+                line.margin_right,
+                line.margin_vertical,
+                line.effect,
+                line.text
+            ));
+        }
+
+        for (name, lines) in &self.sections {
+            text.push_str(&format!("\n[{}]\n", name));
+            for line in lines {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn from_srt(text: &str) -> Result<Self, Error> {
+        let mut file = Self::default();
+        let mut lines = text.lines().map(str::trim_end).enumerate().peekable();
+
+        loop {
+            // Subtitles are separated by blank lines
+            while let Some((_, line)) = lines.peek() {
+                if !line.trim().is_empty() {
+                    break;
+                }
+                lines.next();
+            }
+            let (mut index, mut line) = match lines.next() {
+                Some(line) => line,
+                None => break,
+            };
+            // The subtitle number is not needed
+            if !line.contains("-->") {
+                let next = lines.next().ok_or(Error::InvalidCue { line: index + 2 })?;
+                index = next.0;
+                line = next.1;
+            }
+
+            let mut times = line.splitn(2, "-->");
+            let (start, end) = match (times.next(), times.next()) {
+                // Some files have positions after the end time
+                (Some(start), Some(end)) => (
+                    srt_time(start),
+                    srt_time(end.split_whitespace().next().unwrap_or_default()),
+                ),
+                _ => (None, None),
+            };
+            let (start, end) = match (start, end) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return Err(Error::InvalidCue { line: index + 1 }),
+            };
+
+            let mut text = Vec::new();
+            while let Some((_, line)) = lines.peek() {
+                if line.trim().is_empty() {
+                    break;
+                }
+                text.push(from_html(line));
+                lines.next();
+            }
+
+            let mut event = SubsControlsValues::new();
+            event.text = text.join("\\N");
+            event.set_times(start, end);
+            file.lines.push(event);
+        }
+        Ok(file)
+    }
+
+    /// The dialogue lines in SRT, by start time. Tags other than italic,
+    /// bold, underline and strike out are lost, and times are to the
+    /// centisecond as lines keep them.
+    pub fn to_srt(&self) -> String {
+        let mut lines: Vec<(Time, Time, String)> = self
+            .lines
+            .iter()
+            .filter(|line| !line.is_comment)
+            .map(|line| {
+                let start = line.start().unwrap_or_default();
+                let end = line.end().unwrap_or_default().max(start);
+                (start, end, to_html(&line.text))
+            })
+            .filter(|(_, _, text)| !text.trim().is_empty())
+            .collect();
+        lines.sort_by_key(|&(start, _, _)| start);
+
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, (start, end, text))| {
+                format!(
+                    "{}\n{} --> {}\n{}\n\n",
+                    index + 1,
+                    srt_time_string(start),
+                    srt_time_string(end),
+                    text
+                )
+            })
+            .collect()
+    }
+}
+
+// Field names of a `Format:` line
+fn fields(line: &str) -> Vec<String> {
+    line["Format:".len()..]
+        .split(',')
+        .map(|field| field.trim().to_owned())
+        .collect()
+}
+
+// A `Style:` line in the order of `STYLE_FORMAT`, from one in the order of
+// `format`. Fields it lacks have their default value.
+fn standard_style(line: &str, format: &[String], number: usize) -> Result<String, Error> {
+    let standard = fields(STYLE_FORMAT);
+    if format == standard.as_slice() {
+        return Ok(line.to_owned());
+    }
+
+    let values: Vec<&str> = line["Style:".len()..].splitn(format.len(), ',').collect();
+    if values.len() != format.len() {
+        return Err(Error::WrongFieldCount { line: number });
+    }
+    let default = AssStyle::default().to_string();
+    let defaults = default["Style:".len()..].split(',');
+    let values: Vec<&str> = standard
+        .iter()
+        .zip(defaults)
+        .map(|(name, default)| {
+            format
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case(name))
+                .map_or(default, |index| values[index])
+        })
+        .collect();
+    Ok(format!("Style: {}", values.join(",")))
+}
+
+// A `Dialogue` or `Comment` line, fields in the order of `format`
+fn parse_event(
+    line: &str,
+    format: &[String],
+    number: usize,
+) -> Result<Option<SubsControlsValues>, Error> {
+    let (is_comment, values) = if let Some(values) = line.strip_prefix("Dialogue:") {
+        (false, values)
+    } else if let Some(values) = line.strip_prefix("Comment:") {
+        (true, values)
+    } else {
+        return Ok(None);
+    };
+
+    // The last field, the text, may hold commas
+    let values: Vec<&str> = values.trim_start().splitn(format.len(), ',').collect();
+    if values.len() != format.len() {
+        return Err(Error::WrongFieldCount { line: number });
+    }
+
+    let mut event = SubsControlsValues {
+        is_comment,
+        ..SubsControlsValues::new()
+    };
+    let (mut start, mut end) = (Time::ZERO, Time::ZERO);
+    for (field, value) in format.iter().zip(values) {
+        let invalid = || Error::InvalidField {
+            line: number,
+            field: field.clone(),
+        };
+        let margin = || {
+            value
+                .trim()
+                .parse::<i64>()
+                .map(|margin| margin.max(0) as usize)
+                .map_err(|_| invalid())
+        };
+        match field.as_str() {
+            "Layer" => event.layer = value.trim().parse().map_err(|_| invalid())?,
+            "Start" => start = value.parse().map_err(|_| invalid())?,
+            "End" => end = value.parse().map_err(|_| invalid())?,
+            "Style" => event.style = value.trim().to_owned(),
+            "Name" | "Actor" => event.actor = value.trim().to_owned(),
+            "MarginL" => event.margin_left = margin()?,
+            "MarginR" => event.margin_right = margin()?,
+            "MarginV" => event.margin_vertical = margin()?,
+            "Effect" => event.effect = value.trim().to_owned(),
+            "Text" => event.text = value.to_owned(),
+            _ => {}
+        }
+    }
+    event.set_times(start, end);
+    Ok(Some(event))
+}
+
+// `h:mm:ss.cc`
+fn ass_time(time: Time) -> String {
+    let centiseconds = (time.as_millis() + 5) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centiseconds / 360_000,
+        centiseconds / 6000 % 60,
+        centiseconds / 100 % 60,
+        centiseconds % 100
+    )
+}
+
+// `hh:mm:ss,mmm`, some files use a dot
+fn srt_time(value: &str) -> Option<Time> {
+    let mut fields = value.trim().splitn(3, ':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let mut seconds = fields.next()?.splitn(2, |c| c == ',' || c == '.');
+    let whole: u64 = seconds.next()?.parse().ok()?;
+    // A fraction of a second, `,5` being half of one
+    let fraction = seconds.next().unwrap_or("0");
+    if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let millis: u64 = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+        .parse()
+        .ok()?;
+    hours
+        .checked_mul(60)?
+        .checked_add(minutes)?
+        .checked_mul(60)?
+        .checked_add(whole)?
+        .checked_mul(1000)?
+        .checked_add(millis)
+        .map(Time::from_millis)
+}
+
+fn srt_time_string(time: Time) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+// SRT formatting tags as override tags, font tags are dropped
+fn from_html(line: &str) -> String {
+    let mut text = String::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('<') {
+        let close = match rest[open..].find('>') {
+            Some(close) => open + close,
+            None => break,
+        };
+        text.push_str(&rest[..open]);
+
+        let tag = rest[open + 1..close].trim().to_lowercase();
+        let (name, is_end) = match tag.strip_prefix('/') {
+            Some(name) => (name.trim(), true),
+            None => (tag.as_str(), false),
+        };
+        if SRT_TAGS.contains(&name) {
+            text.push_str(&format!("{{\\{}{}}}", name, u8::from(!is_end)));
+        } else if !(name == "font" || name.starts_with("font ")) {
+            text.push_str(&rest[open..=close]);
+        }
+        rest = &rest[close + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+// The visible text, with the SRT formatting tags
+fn to_html(source: &str) -> String {
+    let line = Line::parse(source);
+    let mut text = String::new();
+    let mut open: Vec<&str> = Vec::new();
+
+    for node in &line.nodes {
+        let items = match &node.kind {
+            NodeKind::Text => {
+                text.push_str(&source[node.span.clone()]);
+                continue;
+            }
+            NodeKind::LineBreak { hard: true } => {
+                text.push('\n');
+                continue;
+            }
+            NodeKind::LineBreak { hard: false } | NodeKind::HardSpace => {
+                text.push(' ');
+                continue;
+            }
+            NodeKind::Drawing => continue,
+            NodeKind::Block(items) => items,
+        };
+
+        for item in items {
+            let tag = match item {
+                Item::Tag(tag) => tag,
+                Item::Comment(_) => continue,
+            };
+            let (name, is_set) = match tag.kind {
+                TagKind::Italic(is_set) => ("i", is_set),
+                TagKind::Bold(weight) => ("b", weight == 1 || weight >= 700),
+                TagKind::Underline(is_set) => ("u", is_set),
+                TagKind::StrikeOut(is_set) => ("s", is_set),
+                TagKind::Reset(_) => {
+                    for name in open.drain(..).rev() {
+                        text.push_str(&format!("</{}>", name));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let position = open.iter().position(|&open| open == name);
+            match (is_set, position) {
+                (true, None) => {
+                    text.push_str(&format!("<{}>", name));
+                    open.push(name);
+                }
+                (false, Some(position)) => {
+                    text.push_str(&format!("</{}>", name));
+                    open.remove(position);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for name in open.into_iter().rev() {
+        text.push_str(&format!("</{}>", name));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASS: &str = "[Script Info]
+; A comment
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:01.00,0:00:02.50,Default,Alice,0,0,0,,Hello, {\\i1}world{\\i0}
+Comment: 1,0:00:03.00,0:00:04.00,Default,,0,0,0,karaoke,A note
+
+[Fonts]
+fontname: a.ttf
+";
+
+    #[test]
+    fn ass_files_are_written_back_as_they_were_read() {
+        let file = SubtitleFile::from_ass(ASS).unwrap();
+        assert_eq!(file.resolution(), Some((1920, 1080)));
+        assert_eq!(file.styles.len(), 1);
+        assert_eq!(file.lines.len(), 2);
+        assert_eq!(file.lines[0].actor, "Alice");
+        assert_eq!(file.lines[0].text, "Hello, {\\i1}world{\\i0}");
+        assert_eq!(file.lines[0].end(), Some(Time::from_millis(2500)));
+        assert!(file.lines[1].is_comment);
+        assert_eq!(file.lines[1].layer, 1);
+
+        let text = file.to_ass();
+        assert!(text.contains("\n[Fonts]\nfontname: a.ttf\n"));
+        assert_eq!(SubtitleFile::from_ass(&text).unwrap(), file);
+    }
+
+    #[test]
+    fn style_fields_are_read_in_their_declared_order() {
+        let text = "[V4+ Styles]
+Format: Fontname, Name, Fontsize, Alignment
+Style: Verdana,Sign,42,8
+";
+        let file = SubtitleFile::from_ass(text).unwrap();
+        let style = &file.styles[0];
+        assert_eq!(style.name, "Sign");
+        assert_eq!(style.font_name, "Verdana");
+        assert_eq!(style.font_size, 42.0);
+        assert_eq!(style.alignment.0, 8);
+        assert_eq!(style.outline, AssStyle::default().outline);
+
+        let text = "[V4+ Styles]\nFormat: Name, Fontname\nStyle: Sign\n";
+        assert!(matches!(
+            SubtitleFile::from_ass(text),
+            Err(Error::WrongFieldCount { line: 3 })
+        ));
+    }
+
+    #[test]
+    fn ssa_styles_are_rejected() {
+        let text = "[Script Info]\nScriptType: v4.00\n\n[V4 Styles]\n";
+        assert!(matches!(
+            SubtitleFile::from_ass(text),
+            Err(Error::SsaStyles { line: 4 })
+        ));
+    }
+
+    #[test]
+    fn srt_files_keep_their_times_and_formatting() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,500\n<i>Hello</i>\nworld\n\n\
+                   2\n00:00:03,000 --> 00:00:04,000 X1:0\n<font color=\"red\">Bye</font>\n";
+        let file = SubtitleFile::from_srt(srt).unwrap();
+        assert_eq!(file.lines.len(), 2);
+        assert_eq!(file.lines[0].text, "{\\i1}Hello{\\i0}\\Nworld");
+        assert_eq!(file.lines[1].text, "Bye");
+        assert_eq!(file.lines[1].start(), Some(Time::from_millis(3000)));
+
+        let text = file.to_srt();
+        assert_eq!(
+            text,
+            "1\n00:00:01,000 --> 00:00:02,500\n<i>Hello</i>\nworld\n\n\
+             2\n00:00:03,000 --> 00:00:04,000\nBye\n\n"
+        );
+        assert_eq!(SubtitleFile::from_srt(&text).unwrap(), file);
+    }
+
+    #[test]
+    fn srt_times_are_rounded_to_the_centisecond() {
+        let file = SubtitleFile::from_srt("00:00:01,004 --> 00:00:01,995\nText\n").unwrap();
+        assert_eq!(file.lines[0].start(), Some(Time::from_millis(1000)));
+        assert_eq!(file.lines[0].end(), Some(Time::from_millis(2000)));
+    }
+
+    #[test]
+    fn srt_fractions_are_of_a_second() {
+        assert_eq!(srt_time("00:00:01,5"), Some(Time::from_millis(1500)));
+        assert_eq!(srt_time("00:00:01.25"), Some(Time::from_millis(1250)));
+        assert_eq!(srt_time("00:00:01,0509"), Some(Time::from_millis(1050)));
+        assert_eq!(srt_time("00:00:01"), Some(Time::from_millis(1000)));
+        assert_eq!(srt_time("00:00:01,"), None);
+        assert_eq!(srt_time("00:00:01,-5"), None);
+        assert_eq!(srt_time("18446744073709551615:00:00,000"), None);
+    }
+
+    #[test]
+    fn malformed_srt_cues_are_rejected() {
+        assert!(matches!(
+            SubtitleFile::from_srt("1\nHello\n"),
+            Err(Error::InvalidCue { line: 2 })
+        ));
+    }
+}
@@ -1,21 +1,129 @@
+use std::cmp::Ordering;
+use std::ops::Range;
+
 use iced::{
-    scrollable, Align, Column, Container, Element, HorizontalAlignment, Length, Row, Scrollable,
+    pick_list, scrollable, tooltip, Align, Column, Container, Element, HorizontalAlignment, Length,
+    PickList, Row, Scrollable, Text, Tooltip,
 };
 
-use super::{cell::Cell, SubsControlsValues};
+use super::{
+    cell::{Cell, TagDisplay, ALL_TAG_DISPLAYS},
+    cps::{CpsLevel, CpsSettings},
+    subs_controls,
+    timing_check::TimingIssue,
+    SubsControlsValues,
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct TableViewer {
     scroll: scrollable::State,
+    tag_display: pick_list::State<TagDisplay>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     CellClicked(usize),
+    HeaderClicked(usize),
+    TagDisplayPicked(TagDisplay),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Start,
+    End,
+    Style,
+    Actor,
+    Effect,
+    Text,
+    Note,
+    Duration,
+    Cps,
+}
+
+impl SortColumn {
+    // Header cells are numbered from the first column after the row number
+    pub fn from_header(column: usize) -> Option<Self> {
+        match column {
+            0 => Some(Self::Start),
+            1 => Some(Self::End),
+            2 => Some(Self::Style),
+            3 => Some(Self::Actor),
+            4 => Some(Self::Effect),
+            5 => Some(Self::Text),
+            6 => Some(Self::Note),
+            7 => Some(Self::Duration),
+            8 => Some(Self::Cps),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub column: SortColumn,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// Returns the sort obtained by clicking on the `column` header: a new
+    /// column is sorted ascending, then descending, then the document order
+    /// is restored.
+    pub fn toggle(current: Option<Sort>, column: SortColumn) -> Option<Sort> {
+        match current {
+            Some(sort) if sort.column == column && sort.descending => None,
+            Some(sort) if sort.column == column => Some(Sort {
+                column,
+                descending: true,
+            }),
+            _ => Some(Sort {
+                column,
+                descending: false,
+            }),
+        }
+    }
+
+    pub fn compare(
+        &self,
+        a: &SubsControlsValues,
+        b: &SubsControlsValues,
+        cps_settings: &CpsSettings,
+    ) -> Ordering {
+        let ordering = match self.column {
+            SortColumn::Start => a.start().cmp(&b.start()),
+            SortColumn::End => a.end().cmp(&b.end()),
+            SortColumn::Style => a.style.cmp(&b.style),
+            SortColumn::Actor => a.actor.cmp(&b.actor),
+            SortColumn::Effect => a.effect.cmp(&b.effect),
+            SortColumn::Text => a.text.cmp(&b.text),
+            SortColumn::Note => a.notes.cmp(&b.notes),
+            SortColumn::Duration => a.duration().cmp(&b.duration()),
+            SortColumn::Cps => a
+                .cps(cps_settings)
+                .partial_cmp(&b.cps(cps_settings))
+                .unwrap_or(Ordering::Equal),
+        };
+
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// How the table shows its lines.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplaySettings<'a> {
+    pub sort: Option<Sort>,
+    pub tag_display: TagDisplay,
+    pub cps_settings: &'a CpsSettings,
+    /// Issues of every line, by row.
+    pub timing_issues: &'a [Vec<TimingIssue>],
 }
 
 // Container properties
 const CONTAINER_PADDING: u16 = 1;
+const TOOLBAR_SPACING: u16 = 6;
 
 // Scrollbar properties
 const SCROLLBAR_MARGIN: u16 = 10;
@@ -31,53 +139,46 @@ const CELL_TEXT_SIZE: u16 = 15;
 // for the number contained in the first cell of a row
 const FIRST_CELL_STEP: u16 = 10;
 
-// , Start, End, Style, Actor, Text, Note, Duration, CPS
+// , Start, End, Style, Actor, Effect, Text, Note, Duration, CPS
 impl TableViewer {
+    /// Shows the `subs_data` lines listed in `rows`, in that order.
     pub fn view<'a>(
         &'a mut self,
         subs_data: &'a [SubsControlsValues],
-        focused_row: usize,
+        rows: &[usize],
+        focused_rows: &Range<usize>,
+        settings: DisplaySettings,
     ) -> Element<Message> {
+        let DisplaySettings {
+            sort,
+            tag_display,
+            cps_settings,
+            timing_issues,
+        } = settings;
         let center_align = HorizontalAlignment::Center;
         let max_first_cell_units =
             subs_data.len().to_string().chars().count() as u16 * FIRST_CELL_STEP;
-        let rows = subs_data
-            .into_iter()
-            .enumerate()
-            .map(|(row, sub)| {
-                if focused_row == row {
-                    Row::with_children(vec![
-                        Self::first_cell(row, max_first_cell_units, true),
-                        Self::cell_row(row, &sub.start_time, center_align, true),
-                        Self::cell_row(row, &sub.end_time, center_align, true),
-                        Self::cell_row(row, sub.style_list.into(), center_align, true),
-                        Self::cell_row(row, sub.actor_list.into(), center_align, true),
-                        Self::cell_row(row, &sub.text, HorizontalAlignment::Left, true),
-                        Self::cell_row(row, &sub.notes, HorizontalAlignment::Left, true),
-                        Self::cell_row(row, &sub.duration, center_align, true),
-                        Self::cell_row(row, "CPS", center_align, true),
-                    ])
-                    .align_items(Align::Center)
-                    .width(Length::Fill)
-                    .height(Length::Shrink)
-                    .into()
-                } else {
-                    Row::with_children(vec![
-                        Self::first_cell(row, max_first_cell_units, false),
-                        Self::cell_row(row, &sub.start_time, center_align, false),
-                        Self::cell_row(row, &sub.end_time, center_align, false),
-                        Self::cell_row(row, sub.style_list.into(), center_align, false),
-                        Self::cell_row(row, sub.actor_list.into(), center_align, false),
-                        Self::cell_row(row, &sub.text, HorizontalAlignment::Left, false),
-                        Self::cell_row(row, &sub.notes, HorizontalAlignment::Left, false),
-                        Self::cell_row(row, &sub.duration, center_align, false),
-                        Self::cell_row(row, "CPS", center_align, false),
-                    ])
-                    .align_items(Align::Center)
-                    .width(Length::Fill)
-                    .height(Length::Shrink)
-                    .into()
-                }
+        let rows = rows
+            .iter()
+            .map(|&row| {
+                let sub = &subs_data[row];
+                let is_focused = focused_rows.contains(&row);
+                Row::with_children(vec![
+                    Self::first_cell(row, max_first_cell_units, is_focused, &timing_issues[row]),
+                    Self::time_cell(row, &sub.start_time, is_focused, &timing_issues[row]),
+                    Self::time_cell(row, &sub.end_time, is_focused, &timing_issues[row]),
+                    Self::cell_row(row, &sub.style, center_align, is_focused),
+                    Self::cell_row(row, &sub.actor, center_align, is_focused),
+                    Self::cell_row(row, &sub.effect, center_align, is_focused),
+                    Self::text_cell(row, &sub.text, tag_display, is_focused),
+                    Self::cell_row(row, &sub.notes, HorizontalAlignment::Left, is_focused),
+                    Self::cell_row(row, &sub.duration, center_align, is_focused),
+                    Self::cps_cell(row, sub.cps(cps_settings), cps_settings, is_focused),
+                ])
+                .align_items(Align::Center)
+                .width(Length::Fill)
+                .height(Length::Shrink)
+                .into()
             })
             .collect();
 
@@ -92,10 +193,25 @@ impl TableViewer {
             .scroller_width(SCROLLER_WIDTH)
             .push(columns);
 
+        let toolbar = Row::new()
+            .spacing(TOOLBAR_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("Override tags").size(CELL_TEXT_SIZE))
+            .push(
+                PickList::new(
+                    &mut self.tag_display,
+                    ALL_TAG_DISPLAYS,
+                    Some(tag_display),
+                    Message::TagDisplayPicked,
+                )
+                .text_size(CELL_TEXT_SIZE),
+            );
+
         let final_column = Column::new()
             .width(Length::Fill)
             .height(Length::Fill)
-            .push(Self::create_header(max_first_cell_units))
+            .push(toolbar)
+            .push(Self::create_header(max_first_cell_units, sort))
             .push(scrollable);
 
         Container::new(final_column)
@@ -105,17 +221,25 @@ impl TableViewer {
             .into()
     }
 
-    fn create_header<'a>(length_units: u16) -> Element<'a, Message> {
+    fn create_header<'a>(length_units: u16, sort: Option<Sort>) -> Element<'a, Message> {
+        let label = |column: usize, name: &str| match sort {
+            Some(sort) if SortColumn::from_header(column) == Some(sort.column) => {
+                format!("{} {}", name, if sort.descending { "v" } else { "^" })
+            }
+            _ => name.to_owned(),
+        };
+
         Row::with_children(vec![
             Self::first_header(length_units),
-            Self::header_center("Start"),
-            Self::header_center("End"),
-            Self::header_center("Style"),
-            Self::header_center("Actor"),
-            Self::header_left("Text"),
-            Self::header_left("Note"),
-            Self::header_center("Duration"),
-            Self::header_center("CPS"),
+            Self::header_center(0, &label(0, "Start")),
+            Self::header_center(1, &label(1, "End")),
+            Self::header_center(2, &label(2, "Style")),
+            Self::header_center(3, &label(3, "Actor")),
+            Self::header_center(4, &label(4, "Effect")),
+            Self::header_left(5, &label(5, "Text")),
+            Self::header_left(6, &label(6, "Note")),
+            Self::header_center(7, &label(7, "Duration")),
+            Self::header_center(8, &label(8, "CPS")),
         ])
         .align_items(Align::Center)
         .width(Length::Fill)
@@ -135,36 +259,84 @@ impl TableViewer {
     }
 
     #[inline(always)]
-    fn header_center(value: &str) -> Element<Message> {
-        Cell::no_interactive(value)
+    fn header_center<'a>(column: usize, value: &str) -> Element<'a, Message> {
+        Cell::sortable(column, value)
             .padding(CELL_PADDING)
             .size(HEADER_TEXT_SIZE)
             .style(style::Header)
             .horizontal_alignment(HorizontalAlignment::Center)
+            .on_click(Message::HeaderClicked)
             .into()
     }
 
     #[inline(always)]
-    fn header_left(value: &str) -> Element<Message> {
-        Cell::no_interactive(value)
+    fn header_left<'a>(column: usize, value: &str) -> Element<'a, Message> {
+        Cell::sortable(column, value)
             .padding(CELL_PADDING)
             .size(HEADER_TEXT_SIZE)
             .style(style::Header)
+            .on_click(Message::HeaderClicked)
             .into()
     }
 
     #[inline(always)]
-    fn first_cell<'a>(row: usize, length_units: u16, is_focused: bool) -> Element<'a, Message> {
+    fn first_cell<'a>(
+        row: usize,
+        length_units: u16,
+        is_focused: bool,
+        timing_issues: &[TimingIssue],
+    ) -> Element<'a, Message> {
         let value = (row + 1).to_string();
-        Cell::interactive(row, &value)
+        let cell = Cell::interactive(row, &value)
             .padding(CELL_PADDING)
             .size(CELL_TEXT_SIZE)
             .width(Length::Units(length_units))
             .horizontal_alignment(HorizontalAlignment::Center)
-            .style(style::FirstCell)
             .on_click(Message::CellClicked)
-            .focus(is_focused)
-            .into()
+            .focus(is_focused);
+
+        if timing_issues.is_empty() {
+            return cell.style(style::FirstCell).into();
+        }
+
+        // Explain the problems of the line when hovering its number
+        let label = timing_issues
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Tooltip::new(
+            cell.style(style::TimingIssue),
+            &label,
+            tooltip::Position::Right,
+        )
+        .gap(5)
+        .padding(10)
+        .size(16)
+        .style(subs_controls::style::Tooltip)
+        .into()
+    }
+
+    #[inline(always)]
+    fn time_cell<'a>(
+        row: usize,
+        value: &str,
+        is_focused: bool,
+        timing_issues: &[TimingIssue],
+    ) -> Element<'a, Message> {
+        let cell = Cell::interactive(row, value)
+            .padding(CELL_PADDING)
+            .size(CELL_TEXT_SIZE)
+            .horizontal_alignment(HorizontalAlignment::Center)
+            .on_click(Message::CellClicked)
+            .focus(is_focused);
+
+        if timing_issues.is_empty() {
+            cell.style(style::Cell)
+        } else {
+            cell.style(style::TimingIssue)
+        }
+        .into()
     }
 
     #[inline(always)]
@@ -183,6 +355,46 @@ impl TableViewer {
             .focus(is_focused)
             .into()
     }
+
+    #[inline(always)]
+    fn text_cell<'a>(
+        row: usize,
+        value: &str,
+        tag_display: TagDisplay,
+        is_focused: bool,
+    ) -> Element<'a, Message> {
+        Cell::interactive(row, value)
+            .padding(CELL_PADDING)
+            .size(CELL_TEXT_SIZE)
+            .tag_display(tag_display)
+            .style(style::Cell)
+            .on_click(Message::CellClicked)
+            .focus(is_focused)
+            .into()
+    }
+
+    #[inline(always)]
+    fn cps_cell<'a>(
+        row: usize,
+        cps: Option<f32>,
+        settings: &CpsSettings,
+        is_focused: bool,
+    ) -> Element<'a, Message> {
+        let value = cps.map_or_else(String::new, |cps| format!("{:.0}", cps));
+        let cell = Cell::interactive(row, &value)
+            .padding(CELL_PADDING)
+            .size(CELL_TEXT_SIZE)
+            .horizontal_alignment(HorizontalAlignment::Center)
+            .on_click(Message::CellClicked)
+            .focus(is_focused);
+
+        match cps.map(|cps| settings.level(cps)) {
+            Some(CpsLevel::Warning) => cell.style(style::CpsWarning),
+            Some(CpsLevel::Exceeded) => cell.style(style::CpsExceeded),
+            _ => cell.style(style::Cell),
+        }
+        .into()
+    }
 }
 
 mod style {
@@ -213,6 +425,24 @@ mod style {
         0.42,
     );
 
+    const CPS_WARNING: Color = Color::from_rgb(
+        0xF5 as f32 / 255.0,
+        0xD7 as f32 / 255.0,
+        0x8E as f32 / 255.0,
+    );
+
+    const CPS_EXCEEDED: Color = Color::from_rgb(
+        0xE8 as f32 / 255.0,
+        0x8A as f32 / 255.0,
+        0x8A as f32 / 255.0,
+    );
+
+    const TIMING_ISSUE: Color = Color::from_rgb(
+        0xD9 as f32 / 255.0,
+        0x6C as f32 / 255.0,
+        0xC9 as f32 / 255.0,
+    );
+
     pub struct Header;
 
     impl StyleSheet for Header {
@@ -344,4 +574,84 @@ mod style {
             }
         }
     }
+
+    pub struct CpsWarning;
+
+    impl StyleSheet for CpsWarning {
+        fn active(&self) -> Style {
+            Style {
+                background: CPS_WARNING.into(),
+                ..Cell.active()
+            }
+        }
+
+        fn hovered(&self) -> Style {
+            Cell.hovered()
+        }
+
+        fn highlight(&self) -> Style {
+            Style {
+                background: CPS_WARNING.into(),
+                ..Cell.highlight()
+            }
+        }
+
+        fn hover_highlight(&self) -> Style {
+            Cell.hover_highlight()
+        }
+    }
+
+    pub struct CpsExceeded;
+
+    impl StyleSheet for CpsExceeded {
+        fn active(&self) -> Style {
+            Style {
+                background: CPS_EXCEEDED.into(),
+                ..Cell.active()
+            }
+        }
+
+        fn hovered(&self) -> Style {
+            Cell.hovered()
+        }
+
+        fn highlight(&self) -> Style {
+            Style {
+                background: CPS_EXCEEDED.into(),
+                ..Cell.highlight()
+            }
+        }
+
+        fn hover_highlight(&self) -> Style {
+            Cell.hover_highlight()
+        }
+    }
+
+    pub struct TimingIssue;
+
+    impl StyleSheet for TimingIssue {
+        fn active(&self) -> Style {
+            Style {
+                background: TIMING_ISSUE.into(),
+                text_color: Color::WHITE,
+                ..Cell.active()
+            }
+        }
+
+        fn hovered(&self) -> Style {
+            Cell.hovered()
+        }
+
+        fn highlight(&self) -> Style {
+            Style {
+                background: TIMING_ISSUE.into(),
+                text_color: Color::WHITE,
+                ..Cell.highlight()
+            }
+        }
+
+        fn hover_highlight(&self) -> Style {
+            Cell.hover_highlight()
+        }
+    }
 }
@@ -0,0 +1,725 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::styles::AssColor;
+
+// Tag names, a name must come before the names it starts with
+static TAG_NAMES: &[&str] = &[
+    "xbord", "ybord", "xshad", "yshad", "alpha", "iclip", "blur", "bord", "shad", "fscx", "fscy",
+    "fsp", "fs", "frx", "fry", "frz", "fr", "fax", "fay", "fade", "fad", "fn", "fe", "be", "b",
+    "move", "clip", "pos", "pbo", "org", "an", "1c", "2c", "3c", "4c", "1a", "2a", "3a", "4a", "c",
+    "a", "kf", "ko", "k", "K", "q", "r", "i", "u", "s", "p", "t",
+];
+
+/// Karaoke effect of a syllable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Karaoke {
+    /// `\k`, the syllable is highlighted at once.
+    Fill,
+    /// `\kf` or `\K`, the highlight sweeps from left to right.
+    Sweep,
+    /// `\ko`, the outline is highlighted.
+    Outline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipShape<'a> {
+    Rectangle(f32, f32, f32, f32),
+    Drawing { scale: u32, commands: &'a str },
+}
+
+/// Meaning of an override tag. Tags with a malformed value are `Unknown`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagKind<'a> {
+    /// `\b`, 0 and 1 or a font weight.
+    Bold(u32),
+    Italic(bool),
+    Underline(bool),
+    StrikeOut(bool),
+    Border(f32),
+    BorderX(f32),
+    BorderY(f32),
+    Shadow(f32),
+    ShadowX(f32),
+    ShadowY(f32),
+    EdgeBlur(f32),
+    Blur(f32),
+    FontName(&'a str),
+    FontSize(f32),
+    ScaleX(f32),
+    ScaleY(f32),
+    Spacing(f32),
+    RotationX(f32),
+    RotationY(f32),
+    /// `\frz`, also written `\fr`.
+    RotationZ(f32),
+    ShearX(f32),
+    ShearY(f32),
+    Encoding(u32),
+    /// `\1c` to `\4c`, `\c` is `\1c`.
+    Color(u8, AssColor),
+    /// `\1a` to `\4a`, or `\alpha` for every color.
+    Alpha(Option<u8>, u8),
+    /// `\an`, numpad position.
+    Alignment(u8),
+    /// `\a`, the older SSA alignment.
+    LegacyAlignment(u8),
+    /// Duration in centiseconds.
+    Karaoke(Karaoke, u32),
+    WrapStyle(u8),
+    /// `\r`, back to the line style or to the named one.
+    Reset(Option<&'a str>),
+    Position(f32, f32),
+    Origin(f32, f32),
+    Move {
+        from: (f32, f32),
+        to: (f32, f32),
+        times: Option<(i32, i32)>,
+    },
+    /// `\fad`, fade in and out durations in milliseconds.
+    Fade(u32, u32),
+    /// `\fade`, three alphas and four times.
+    ComplexFade([u8; 3], [i32; 4]),
+    Clip {
+        inverse: bool,
+        shape: ClipShape<'a>,
+    },
+    /// `\p`, drawing mode scale, 0 turns it off.
+    Drawing(u32),
+    Baseline(f32),
+    /// `\t`, animates `tags` from `times`, with an acceleration.
+    Transform {
+        times: Option<(i32, i32)>,
+        accel: Option<f32>,
+        tags: Vec<Tag<'a>>,
+    },
+    Unknown {
+        name: &'a str,
+        args: &'a str,
+    },
+}
+
+/// An override tag, `span` is its position in the line text and `args`
+/// the position of what follows its name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag<'a> {
+    pub span: Range<usize>,
+    pub args: Range<usize>,
+    pub kind: TagKind<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<'a> {
+    Tag(Tag<'a>),
+    /// Text of a block before its first tag.
+    Comment(Range<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind<'a> {
+    Text,
+    /// Vector drawing commands, text is drawn as shapes while `\p` is set.
+    Drawing,
+    /// `\N`, or `\n` when not `hard`.
+    LineBreak {
+        hard: bool,
+    },
+    /// `\h`
+    HardSpace,
+    /// `{...}`, the items are inside the braces.
+    Block(Vec<Item<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'a> {
+    pub span: Range<usize>,
+    pub kind: NodeKind<'a>,
+}
+
+/// A parsed `Text` field. The nodes cover the whole text in order, so the
+/// line is written back byte by byte as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line<'a> {
+    source: &'a str,
+    pub nodes: Vec<Node<'a>>,
+}
+
+impl<'a> Line<'a> {
+    pub fn parse(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let mut nodes = Vec::new();
+        let mut is_drawing = false;
+        let mut text_start = 0;
+        let mut position = 0;
+
+        // Only ASCII bytes are matched, so positions stay on char boundaries
+        while position < bytes.len() {
+            let special = match bytes[position] {
+                b'{' => source[position..].find('}').map(|length| {
+                    let end = position + length + 1;
+                    let items = parse_items(source, position + 1..end - 1);
+                    (end, NodeKind::Block(items))
+                }),
+                b'\\' if !is_drawing => match bytes.get(position + 1) {
+                    Some(b'N') => Some((position + 2, NodeKind::LineBreak { hard: true })),
+                    Some(b'n') => Some((position + 2, NodeKind::LineBreak { hard: false })),
+                    Some(b'h') => Some((position + 2, NodeKind::HardSpace)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match special {
+                Some((end, kind)) => {
+                    if text_start < position {
+                        nodes.push(Node::text(text_start..position, is_drawing));
+                    }
+                    // The last `\p` of a block decides whether text is drawn
+                    if let NodeKind::Block(items) = &kind {
+                        for item in items {
+                            if let Item::Tag(Tag {
+                                kind: TagKind::Drawing(scale),
+                                ..
+                            }) = item
+                            {
+                                is_drawing = *scale > 0;
+                            }
+                        }
+                    }
+                    nodes.push(Node {
+                        span: position..end,
+                        kind,
+                    });
+                    position = end;
+                    text_start = end;
+                }
+                None => position += 1,
+            }
+        }
+
+        if text_start < bytes.len() {
+            nodes.push(Node::text(text_start..bytes.len(), is_drawing));
+        }
+
+        Self { source, nodes }
+    }
+
+    /// Node strictly containing `offset`, a node never contains its start.
+    pub fn node_at(&self, offset: usize) -> Option<&Node<'a>> {
+        self.nodes
+            .iter()
+            .find(|node| node.span.start < offset && offset < node.span.end)
+    }
+
+    /// Characters shown on screen: `\N` is a `'\n'`, `\n` a space and `\h`
+    /// a non-breaking space. Tags and drawings are left out.
+    pub fn visible_chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.nodes.iter().flat_map(move |node| {
+            let (text, break_char) = match node.kind {
+                NodeKind::Text => (&self.source[node.span.clone()], None),
+                NodeKind::LineBreak { hard: true } => ("", Some('\n')),
+                // Soft breaks only break with the `\q2` wrap style
+                NodeKind::LineBreak { hard: false } => ("", Some(' ')),
+                NodeKind::HardSpace => ("", Some('\u{a0}')),
+                NodeKind::Drawing | NodeKind::Block(_) => ("", None),
+            };
+            text.chars().chain(break_char)
+        })
+    }
+}
+
+impl<'a> Node<'a> {
+    #[inline(always)]
+    fn text(span: Range<usize>, is_drawing: bool) -> Self {
+        let kind = if is_drawing {
+            NodeKind::Drawing
+        } else {
+            NodeKind::Text
+        };
+        Self { span, kind }
+    }
+}
+
+impl<'a> fmt::Display for Line<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            match &node.kind {
+                // Written from the items, so that they cover the block
+                NodeKind::Block(items) => {
+                    write!(f, "{{")?;
+                    for item in items {
+                        let span = match item {
+                            Item::Tag(tag) => tag.span.clone(),
+                            Item::Comment(span) => span.clone(),
+                        };
+                        write!(f, "{}", &self.source[span])?;
+                    }
+                    write!(f, "}}")?;
+                }
+                _ => write!(f, "{}", &self.source[node.span.clone()])?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Items of the block text in `range`, which excludes the braces
+fn parse_items(source: &str, range: Range<usize>) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    let first_tag = source[range.clone()]
+        .find('\\')
+        .map_or(range.end, |index| range.start + index);
+    if first_tag > range.start {
+        items.push(Item::Comment(range.start..first_tag));
+    }
+
+    let mut position = first_tag;
+    while position < range.end {
+        let end = tag_end(source, position, range.end);
+        items.push(Item::Tag(parse_tag(source, position..end)));
+        position = end;
+    }
+
+    items
+}
+
+// A tag goes on until the next backslash outside of parentheses
+fn tag_end(source: &str, start: usize, end: usize) -> usize {
+    let mut depth = 0usize;
+    for (index, byte) in source.as_bytes()[start + 1..end].iter().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            b'\\' if depth == 0 => return start + 1 + index,
+            _ => {}
+        }
+    }
+    end
+}
+
+// `span` starts with the backslash of the tag
+fn parse_tag(source: &str, span: Range<usize>) -> Tag<'_> {
+    let body = &source[span.start + 1..span.end];
+    let name = TAG_NAMES
+        .iter()
+        .find(|name| body.starts_with(*name))
+        .copied()
+        .unwrap_or_else(|| {
+            let length = body
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or_else(|| body.len());
+            &body[..length]
+        });
+    let args_start = span.start + 1 + name.len();
+    let args = &source[args_start..span.end];
+
+    let kind = tag_kind(source, name, args, args_start).unwrap_or(TagKind::Unknown { name, args });
+    Tag {
+        args: args_start..span.end,
+        span,
+        kind,
+    }
+}
+
+fn tag_kind<'a>(
+    source: &'a str,
+    name: &'a str,
+    args: &'a str,
+    args_start: usize,
+) -> Option<TagKind<'a>> {
+    let value = args.trim();
+
+    let kind = match name {
+        "b" => TagKind::Bold(integer(value)?.max(0) as u32),
+        "i" => TagKind::Italic(integer(value)? != 0),
+        "u" => TagKind::Underline(integer(value)? != 0),
+        "s" => TagKind::StrikeOut(integer(value)? != 0),
+        "bord" => TagKind::Border(number(value)?),
+        "xbord" => TagKind::BorderX(number(value)?),
+        "ybord" => TagKind::BorderY(number(value)?),
+        "shad" => TagKind::Shadow(number(value)?),
+        "xshad" => TagKind::ShadowX(number(value)?),
+        "yshad" => TagKind::ShadowY(number(value)?),
+        "be" => TagKind::EdgeBlur(number(value)?),
+        "blur" => TagKind::Blur(number(value)?),
+        "fn" if !value.is_empty() => TagKind::FontName(value),
+        "fs" => TagKind::FontSize(number(value)?),
+        "fscx" => TagKind::ScaleX(number(value)?),
+        "fscy" => TagKind::ScaleY(number(value)?),
+        "fsp" => TagKind::Spacing(number(value)?),
+        "frx" => TagKind::RotationX(number(value)?),
+        "fry" => TagKind::RotationY(number(value)?),
+        "frz" | "fr" => TagKind::RotationZ(number(value)?),
+        "fax" => TagKind::ShearX(number(value)?),
+        "fay" => TagKind::ShearY(number(value)?),
+        "fe" => TagKind::Encoding(integer(value)?.max(0) as u32),
+        "c" | "1c" | "2c" | "3c" | "4c" => {
+            let index = if name == "c" {
+                1
+            } else {
+                name.as_bytes()[0] - b'0'
+            };
+            TagKind::Color(index, value.parse().ok()?)
+        }
+        "alpha" => TagKind::Alpha(None, alpha(value)?),
+        "1a" | "2a" | "3a" | "4a" => TagKind::Alpha(Some(name.as_bytes()[0] - b'0'), alpha(value)?),
+        "an" => match integer(value)? {
+            alignment @ 1..=9 => TagKind::Alignment(alignment as u8),
+            _ => return None,
+        },
+        "a" => TagKind::LegacyAlignment(integer(value)?.max(0).min(11) as u8),
+        "k" => TagKind::Karaoke(Karaoke::Fill, integer(value)?.max(0) as u32),
+        "kf" | "K" => TagKind::Karaoke(Karaoke::Sweep, integer(value)?.max(0) as u32),
+        "ko" => TagKind::Karaoke(Karaoke::Outline, integer(value)?.max(0) as u32),
+        "q" => TagKind::WrapStyle(integer(value)?.max(0).min(3) as u8),
+        "r" => TagKind::Reset(Some(value).filter(|style| !style.is_empty())),
+        "p" => TagKind::Drawing(integer(value)?.max(0) as u32),
+        "pbo" => TagKind::Baseline(number(value)?),
+        _ => {
+            let (inner, inner_start) = parenthesized(args, args_start)?;
+            let arguments = split_arguments(inner, inner_start);
+            let numbers: Option<Vec<f32>> = arguments
+                .iter()
+                .map(|(argument, _)| number(argument.trim()))
+                .collect();
+
+            match (name, arguments.len()) {
+                ("pos", 2) => {
+                    let numbers = numbers?;
+                    TagKind::Position(numbers[0], numbers[1])
+                }
+                ("org", 2) => {
+                    let numbers = numbers?;
+                    TagKind::Origin(numbers[0], numbers[1])
+                }
+                ("move", 4) | ("move", 6) => {
+                    let numbers = numbers?;
+                    TagKind::Move {
+                        from: (numbers[0], numbers[1]),
+                        to: (numbers[2], numbers[3]),
+                        times: numbers
+                            .get(4..6)
+                            .map(|times| (times[0] as i32, times[1] as i32)),
+                    }
+                }
+                ("fad", 2) => {
+                    let numbers = numbers?;
+                    TagKind::Fade(numbers[0].max(0.0) as u32, numbers[1].max(0.0) as u32)
+                }
+                ("fade", 7) => {
+                    let numbers = numbers?;
+                    let alpha = |value: f32| value.max(0.0).min(255.0) as u8;
+                    TagKind::ComplexFade(
+                        [alpha(numbers[0]), alpha(numbers[1]), alpha(numbers[2])],
+                        [
+                            numbers[3] as i32,
+                            numbers[4] as i32,
+                            numbers[5] as i32,
+                            numbers[6] as i32,
+                        ],
+                    )
+                }
+                ("clip", 4) | ("iclip", 4) => {
+                    let numbers = numbers?;
+                    TagKind::Clip {
+                        inverse: name == "iclip",
+                        shape: ClipShape::Rectangle(numbers[0], numbers[1], numbers[2], numbers[3]),
+                    }
+                }
+                ("clip", count @ 1..=2) | ("iclip", count @ 1..=2) => TagKind::Clip {
+                    inverse: name == "iclip",
+                    shape: ClipShape::Drawing {
+                        scale: if count == 2 {
+                            integer(arguments[0].0.trim())?.max(1) as u32
+                        } else {
+                            1
+                        },
+                        commands: arguments[count - 1].0.trim(),
+                    },
+                },
+                ("t", 1..=4) => {
+                    let (tags, tags_start) = arguments[arguments.len() - 1];
+                    let time = |index: usize| -> Option<i32> {
+                        Some(number(arguments[index].0.trim())? as i32)
+                    };
+                    let (times, accel) = match arguments.len() {
+                        1 => (None, None),
+                        2 => (None, Some(number(arguments[0].0.trim())?)),
+                        3 => (Some((time(0)?, time(1)?)), None),
+                        _ => (
+                            Some((time(0)?, time(1)?)),
+                            Some(number(arguments[2].0.trim())?),
+                        ),
+                    };
+                    let tags = parse_items(source, tags_start..tags_start + tags.len())
+                        .into_iter()
+                        .filter_map(|item| match item {
+                            Item::Tag(tag) => Some(tag),
+                            Item::Comment(_) => None,
+                        })
+                        .collect();
+                    TagKind::Transform { times, accel, tags }
+                }
+                _ => return None,
+            }
+        }
+    };
+
+    Some(kind)
+}
+
+// Text between the parentheses of `args`, the closing one may be missing
+fn parenthesized(args: &str, args_start: usize) -> Option<(&str, usize)> {
+    let open = args.find('(')?;
+    if !args[..open].trim().is_empty() {
+        return None;
+    }
+    let inner = &args[open + 1..];
+    let inner = inner.rfind(')').map_or(inner, |close| &inner[..close]);
+    Some((inner, args_start + open + 1))
+}
+
+// Comma separated arguments with their start, nested parentheses are kept
+fn split_arguments(inner: &str, inner_start: usize) -> Vec<(&str, usize)> {
+    let mut arguments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, byte) in inner.bytes().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            // Tags of `\t` may contain commas, they always come last
+            b'\\' if depth == 0 => break,
+            b',' if depth == 0 => {
+                arguments.push((&inner[start..index], inner_start + start));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push((&inner[start..], inner_start + start));
+    arguments
+}
+
+// Leading number of `value`, trailing text is ignored as renderers do
+fn number(value: &str) -> Option<f32> {
+    let end = value
+        .char_indices()
+        .find(|&(index, c)| !(c.is_ascii_digit() || c == '.' || (index == 0 && "+-".contains(c))))
+        .map_or(value.len(), |(index, _)| index);
+    value[..end].parse().ok()
+}
+
+fn integer(value: &str) -> Option<i64> {
+    number(value).map(|number| number as i64)
+}
+
+// `&HAA&`, or a bare hexadecimal value
+fn alpha(value: &str) -> Option<u8> {
+    let hex = value
+        .trim_start_matches('&')
+        .trim_start_matches(|c| c == 'H' || c == 'h')
+        .trim_end_matches('&');
+    let alpha = u32::from_str_radix(hex, 16).ok()?;
+    Some(alpha.min(255) as u8)
+}
+
+impl fmt::Display for Karaoke {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Karaoke::Fill => "k",
+            Karaoke::Sweep => "kf",
+            Karaoke::Outline => "ko",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Canonical form of a tag, used to write new ones.
+impl<'a> fmt::Display for TagKind<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagKind::Bold(weight) => write!(f, "\\b{}", weight),
+            TagKind::Italic(value) => write!(f, "\\i{}", *value as u8),
+            TagKind::Underline(value) => write!(f, "\\u{}", *value as u8),
+            TagKind::StrikeOut(value) => write!(f, "\\s{}", *value as u8),
+            TagKind::Border(value) => write!(f, "\\bord{}", value),
+            TagKind::BorderX(value) => write!(f, "\\xbord{}", value),
+            TagKind::BorderY(value) => write!(f, "\\ybord{}", value),
+            TagKind::Shadow(value) => write!(f, "\\shad{}", value),
+            TagKind::ShadowX(value) => write!(f, "\\xshad{}", value),
+            TagKind::ShadowY(value) => write!(f, "\\yshad{}", value),
+            TagKind::EdgeBlur(value) => write!(f, "\\be{}", value),
+            TagKind::Blur(value) => write!(f, "\\blur{}", value),
+            TagKind::FontName(name) => write!(f, "\\fn{}", name),
+            TagKind::FontSize(value) => write!(f, "\\fs{}", value),
+            TagKind::ScaleX(value) => write!(f, "\\fscx{}", value),
+            TagKind::ScaleY(value) => write!(f, "\\fscy{}", value),
+            TagKind::Spacing(value) => write!(f, "\\fsp{}", value),
+            TagKind::RotationX(value) => write!(f, "\\frx{}", value),
+            TagKind::RotationY(value) => write!(f, "\\fry{}", value),
+            TagKind::RotationZ(value) => write!(f, "\\frz{}", value),
+            TagKind::ShearX(value) => write!(f, "\\fax{}", value),
+            TagKind::ShearY(value) => write!(f, "\\fay{}", value),
+            TagKind::Encoding(value) => write!(f, "\\fe{}", value),
+            TagKind::Color(index, color) => write!(f, "\\{}c{}", index, color.to_override()),
+            TagKind::Alpha(None, alpha) => write!(f, "\\alpha&H{:02X}&", alpha),
+            TagKind::Alpha(Some(index), alpha) => write!(f, "\\{}a&H{:02X}&", index, alpha),
+            TagKind::Alignment(value) => write!(f, "\\an{}", value),
+            TagKind::LegacyAlignment(value) => write!(f, "\\a{}", value),
+            TagKind::Karaoke(karaoke, duration) => write!(f, "\\{}{}", karaoke, duration),
+            TagKind::WrapStyle(value) => write!(f, "\\q{}", value),
+            TagKind::Reset(style) => write!(f, "\\r{}", style.unwrap_or("")),
+            TagKind::Position(x, y) => write!(f, "\\pos({},{})", x, y),
+            TagKind::Origin(x, y) => write!(f, "\\org({},{})", x, y),
+            TagKind::Move { from, to, times } => {
+                write!(f, "\\move({},{},{},{}", from.0, from.1, to.0, to.1)?;
+                if let Some((start, end)) = times {
+                    write!(f, ",{},{}", start, end)?;
+                }
+                write!(f, ")")
+            }
+            TagKind::Fade(fade_in, fade_out) => write!(f, "\\fad({},{})", fade_in, fade_out),
+            TagKind::ComplexFade(alphas, times) => write!(
+                f,
+                "\\fade({},{},{},{},{},{},{})",
+                alphas[0], alphas[1], alphas[2], times[0], times[1], times[2], times[3]
+            ),
+            TagKind::Clip { inverse, shape } => {
+                let name = if *inverse { "iclip" } else { "clip" };
+                match shape {
+                    ClipShape::Rectangle(x1, y1, x2, y2) => {
+                        write!(f, "\\{}({},{},{},{})", name, x1, y1, x2, y2)
+                    }
+                    ClipShape::Drawing { scale: 1, commands } => {
+                        write!(f, "\\{}({})", name, commands)
+                    }
+                    ClipShape::Drawing { scale, commands } => {
+                        write!(f, "\\{}({},{})", name, scale, commands)
+                    }
+                }
+            }
+            TagKind::Drawing(scale) => write!(f, "\\p{}", scale),
+            TagKind::Baseline(value) => write!(f, "\\pbo{}", value),
+            TagKind::Transform { times, accel, tags } => {
+                write!(f, "\\t(")?;
+                if let Some((start, end)) = times {
+                    write!(f, "{},{},", start, end)?;
+                }
+                if let Some(accel) = accel {
+                    write!(f, "{},", accel)?;
+                }
+                for tag in tags {
+                    write!(f, "{}", tag.kind)?;
+                }
+                write!(f, ")")
+            }
+            TagKind::Unknown { name, args } => write!(f, "\\{}{}", name, args),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Kinds of the tags of the first block of `source`
+    fn kinds(source: &str) -> Vec<TagKind<'_>> {
+        match &Line::parse(source).nodes[0].kind {
+            NodeKind::Block(items) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Tag(tag) => Some(tag.kind.clone()),
+                    Item::Comment(_) => None,
+                })
+                .collect(),
+            kind => panic!("{:?} is not a block", kind),
+        }
+    }
+
+    #[test]
+    fn lines_are_written_back_as_they_were() {
+        let sources = [
+            "",
+            "Plain text",
+            "{\\an8\\pos(320,50)}Top\\Nline",
+            "{comment\\b1}bold{\\b0} and {\\i1}italic\\h{\\r}",
+            "{\\t(0,500,\\fscx120\\frz10)\\clip(m 0 0 l 10 0 10 10)}A",
+            "{\\p1}m 0 0 l 100 0 100 100{\\p0}",
+            "{unclosed \\b1",
+            "été \\n {\\fnArial Unicode}日本語",
+        ];
+        for source in sources.iter() {
+            assert_eq!(Line::parse(source).to_string(), *source);
+        }
+    }
+
+    #[test]
+    fn canonical_tags_are_parsed_back() {
+        let tags = [
+            "\\b700",
+            "\\i1",
+            "\\bord2.5",
+            "\\xshad-1",
+            "\\blur0.6",
+            "\\fnArial",
+            "\\fs48",
+            "\\fscx120",
+            "\\frz-12.5",
+            "\\fax0.1",
+            "\\3c&H0000FF&",
+            "\\alpha&H80&",
+            "\\2a&HFF&",
+            "\\an7",
+            "\\kf45",
+            "\\q2",
+            "\\rAlternate",
+            "\\pos(320,240)",
+            "\\move(0,0,100,50,0,1000)",
+            "\\fad(200,300)",
+            "\\fade(255,0,255,0,100,900,1000)",
+            "\\iclip(10,20,30,40)",
+            "\\clip(2,m 0 0 l 8 0 8 8)",
+            "\\p1",
+            "\\t(0,500,0.5,\\fscx150\\fscy150)",
+        ];
+        for tag in tags.iter() {
+            let source = format!("{{{}}}", tag);
+            let kinds = kinds(&source);
+            assert_eq!(kinds.len(), 1, "{}", tag);
+            assert!(!matches!(kinds[0], TagKind::Unknown { .. }), "{}", tag);
+            assert_eq!(kinds[0].to_string(), *tag);
+        }
+    }
+
+    #[test]
+    fn tag_aliases_have_their_canonical_kind() {
+        assert_eq!(kinds("{\\fr30}"), vec![TagKind::RotationZ(30.0)]);
+        assert_eq!(kinds("{\\K20}"), vec![TagKind::Karaoke(Karaoke::Sweep, 20)]);
+        assert_eq!(
+            kinds("{\\c&HFF0000&}")[0].to_string(),
+            "\\1c&HFF0000&".to_owned()
+        );
+        assert_eq!(
+            kinds("{\\pos(1,2,3)\\an10}"),
+            vec![
+                TagKind::Unknown {
+                    name: "pos",
+                    args: "(1,2,3)"
+                },
+                TagKind::Unknown {
+                    name: "an",
+                    args: "10"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn drawings_are_not_visible() {
+        let line = Line::parse("{\\p1}m 0 0 l 1 1{\\p0}A\\NB\\hC");
+        assert_eq!(line.nodes[1].kind, NodeKind::Drawing);
+        let visible: String = line.visible_chars().collect();
+        assert_eq!(visible, "A\nB\u{a0}C");
+    }
+}
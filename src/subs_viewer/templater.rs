@@ -0,0 +1,490 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+use thiserror::Error;
+
+use super::automation;
+use super::karaoke::{self, Syllables};
+use super::time::Time;
+use super::SubsControlsValues;
+
+const TEMPLATE: &str = "template";
+const CODE: &str = "code";
+
+/// Effect of the lines written by the templates.
+pub const FX: &str = "fx";
+/// Effect of the templated karaoke lines, commented out until cleaned.
+pub const KARAOKE: &str = "karaoke";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Line {line}: unknown modifier `{modifier}`")]
+    UnknownModifier { line: usize, modifier: String },
+    #[error("Line {line}: `!` expression without its closing `!`")]
+    UnclosedExpression { line: usize },
+    #[error("Line {line}: {source}")]
+    Script {
+        line: usize,
+        source: Box<EvalAltResult>,
+    },
+}
+
+// What a template is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    // Once, before the line text
+    PreLine,
+    // Before each syllable, on a single line
+    Line,
+    // A line for each syllable
+    Syllable,
+    // A line for each character
+    Char,
+}
+
+// When a code line runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    Once,
+    Line,
+    Syllable,
+}
+
+struct Template {
+    row: usize,
+    style: String,
+    kind: Kind,
+    // Whether syllables without text are skipped
+    skips_blank: bool,
+    // Whether the syllable text follows the template
+    keeps_text: bool,
+    text: String,
+}
+
+struct Code {
+    row: usize,
+    style: String,
+    run: Run,
+    code: String,
+}
+
+// Times `retime` reads and writes
+#[derive(Debug, Clone, Copy, Default)]
+struct Timing {
+    line: (Time, Time),
+    syllable: (Time, Time),
+    output: (Time, Time),
+}
+
+// A syllable or a character, times from the start of the line
+struct Unit {
+    start: u64,
+    end: u64,
+    index: usize,
+    text: String,
+}
+
+/// `lines` without the lines written by the templates, their karaoke lines
+/// shown again.
+pub fn clean(lines: &[SubsControlsValues]) -> Vec<SubsControlsValues> {
+    lines
+        .iter()
+        .filter(|line| line.effect != FX)
+        .map(|line| SubsControlsValues {
+            is_comment: line.is_comment && line.effect != KARAOKE,
+            ..line.clone()
+        })
+        .collect()
+}
+
+fn is_karaoke(line: &SubsControlsValues) -> bool {
+    !line.is_comment && (line.effect.is_empty() || line.effect == KARAOKE)
+}
+
+// Templates and code lines, which are comments whose effect starts with
+// `template` or `code` followed by modifiers
+fn read(lines: &[SubsControlsValues]) -> Result<(Vec<Template>, Vec<Code>), Error> {
+    let mut templates = Vec::new();
+    let mut codes = Vec::new();
+
+    for (row, line) in lines.iter().enumerate().filter(|(_, line)| line.is_comment) {
+        let mut words = line.effect.split_whitespace();
+        let unknown = |modifier: &str| Error::UnknownModifier {
+            line: row + 1,
+            modifier: modifier.to_owned(),
+        };
+
+        match words.next() {
+            Some(TEMPLATE) => {
+                let mut template = Template {
+                    row,
+                    style: line.style.clone(),
+                    kind: Kind::Syllable,
+                    skips_blank: false,
+                    keeps_text: true,
+                    text: line.text.clone(),
+                };
+                for word in words {
+                    match word {
+                        "pre-line" => template.kind = Kind::PreLine,
+                        "line" => template.kind = Kind::Line,
+                        "syl" => template.kind = Kind::Syllable,
+                        "char" => template.kind = Kind::Char,
+                        "noblank" => template.skips_blank = true,
+                        "notext" => template.keeps_text = false,
+                        _ => return Err(unknown(word)),
+                    }
+                }
+                templates.push(template);
+            }
+            Some(CODE) => {
+                let run = match words.next() {
+                    None | Some("once") => Run::Once,
+                    Some("line") => Run::Line,
+                    Some("syl") => Run::Syllable,
+                    Some(word) => return Err(unknown(word)),
+                };
+                codes.push(Code {
+                    row,
+                    style: line.style.clone(),
+                    run,
+                    code: line.text.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok((templates, codes))
+}
+
+// Engine whose `retime` moves the written line
+fn engine(timing: Rc<RefCell<Timing>>) -> Engine {
+    let mut engine = automation::bounded_engine();
+    let retime = move |mode: &str, start: INT, end: INT| -> Result<String, Box<EvalAltResult>> {
+        let mut timing = timing.borrow_mut();
+        let (line, syllable) = (timing.line, timing.syllable);
+        let (from, to) = match mode {
+            "line" => line,
+            "syl" => syllable,
+            "start2syl" => (line.0, syllable.0),
+            "syl2end" => (syllable.1, line.1),
+            "abs" => (Time::ZERO, Time::ZERO),
+            _ => return Err(format!("Unknown retime mode `{}`", mode).into()),
+        };
+        timing.output = (from.offset(start), to.offset(end));
+        // Called inline, it writes nothing
+        Ok(String::new())
+    };
+    let retime_exactly = retime.clone();
+
+    engine
+        .register_fn("retime", retime)
+        .register_fn("retime", move |mode: &str| retime_exactly(mode, 0, 0));
+    engine
+}
+
+fn line_map(line: &SubsControlsValues, row: usize) -> Map {
+    let start = line.start().unwrap_or_default().as_millis();
+    let end = line.end().unwrap_or_default().as_millis().max(start);
+
+    let mut map = Map::new();
+    map.insert("start".into(), (start as INT).into());
+    map.insert("end".into(), (end as INT).into());
+    map.insert("dur".into(), ((end - start) as INT).into());
+    map.insert("mid".into(), (((start + end) / 2) as INT).into());
+    map.insert("layer".into(), (line.layer as INT).into());
+    map.insert("style".into(), line.style.clone().into());
+    map.insert("actor".into(), line.actor.clone().into());
+    map.insert("i".into(), (row as INT + 1).into());
+    map.insert("text".into(), karaoke::visible(&line.text).into());
+    map
+}
+
+fn unit_map(unit: &Unit) -> Map {
+    let mut map = Map::new();
+    map.insert("start".into(), (unit.start as INT).into());
+    map.insert("end".into(), (unit.end as INT).into());
+    map.insert("dur".into(), ((unit.end - unit.start) as INT).into());
+    map.insert(
+        "kdur".into(),
+        (((unit.end - unit.start) / 10) as INT).into(),
+    );
+    map.insert("mid".into(), (((unit.start + unit.end) / 2) as INT).into());
+    map.insert("i".into(), (unit.index as INT + 1).into());
+    map.insert("text".into(), unit.text.clone().into());
+    map
+}
+
+// Replaces `$name` by the `name` entry of the line, or of the unit
+// prefixed with `s`, as `$sstart`
+fn substitute(text: &str, line: &Map, unit: &Map) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let len = after
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(after.len());
+        let name = &after[..len];
+
+        let value = match name.strip_prefix('l') {
+            Some(name) if line.contains_key(name) => line.get(name),
+            _ => name
+                .strip_prefix('s')
+                .and_then(|name| unit.get(name))
+                .or_else(|| unit.get(name))
+                .or_else(|| line.get(name)),
+        };
+        match value {
+            Some(value) => result.push_str(&value.to_string()),
+            None => {
+                result.push('$');
+                result.push_str(name);
+            }
+        }
+        rest = &after[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Runs the templates and the code lines of the document
+struct Templater<'a> {
+    engine: Engine,
+    scope: Scope<'a>,
+    timing: Rc<RefCell<Timing>>,
+    templates: Vec<Template>,
+    codes: Vec<Code>,
+}
+
+impl Templater<'_> {
+    fn run_code(&mut self, run: Run, style: &str) -> Result<(), Error> {
+        let engine = &self.engine;
+        let scope = &mut self.scope;
+        for code in self
+            .codes
+            .iter()
+            .filter(|code| code.run == run && (run == Run::Once || code.style == style))
+        {
+            engine
+                .run_with_scope(scope, &code.code)
+                .map_err(|source| Error::Script {
+                    line: code.row + 1,
+                    source,
+                })?;
+        }
+        Ok(())
+    }
+
+    // Text of `template` for `unit`, evaluating its `!expressions!`
+    fn fill(&mut self, template: usize, line: &Map, unit: &Unit) -> Result<String, Error> {
+        let style = self.templates[template].style.clone();
+        let unit_map = unit_map(unit);
+        self.scope.set_value("syl", unit_map.clone());
+        self.run_code(Run::Syllable, &style)?;
+
+        let template = &self.templates[template];
+        let error = |source| Error::Script {
+            line: template.row + 1,
+            source,
+        };
+        let text = substitute(&template.text, line, &unit_map);
+        let parts: Vec<&str> = text.split('!').collect();
+        if parts.len() % 2 == 0 {
+            return Err(Error::UnclosedExpression {
+                line: template.row + 1,
+            });
+        }
+
+        let mut result = String::new();
+        for (index, part) in parts.into_iter().enumerate() {
+            if index % 2 == 0 {
+                result.push_str(part);
+                continue;
+            }
+            let value: Dynamic = self
+                .engine
+                .eval_with_scope(&mut self.scope, part)
+                .map_err(error)?;
+            if !value.is::<()>() {
+                result.push_str(&value.to_string());
+            }
+        }
+        Ok(result)
+    }
+
+    // Lines written by the templates for `line`
+    fn apply_to(
+        &mut self,
+        line: &SubsControlsValues,
+        row: usize,
+    ) -> Result<Vec<SubsControlsValues>, Error> {
+        let templates: Vec<usize> = (0..self.templates.len())
+            .filter(|&index| self.templates[index].style == line.style)
+            .collect();
+        if templates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let start = line.start().unwrap_or_default();
+        let end = line.end().unwrap_or_default().max(start);
+        let syllables = Syllables::parse(&line.text, karaoke::line_duration(line));
+        let mut offset = 0;
+        let syllables: Vec<Unit> = syllables
+            .syllables()
+            .iter()
+            .enumerate()
+            .map(|(index, syllable)| {
+                let start = offset;
+                offset += u64::from(syllable.duration) * 10;
+                Unit {
+                    start,
+                    end: offset,
+                    index,
+                    text: karaoke::visible(&syllable.text),
+                }
+            })
+            .collect();
+
+        let line_map = line_map(line, row);
+        self.scope.set_value("line", line_map.clone());
+        self.run_code(Run::Line, &line.style)?;
+
+        let mut written = Vec::new();
+        for template in templates {
+            let Template {
+                kind,
+                skips_blank,
+                keeps_text,
+                ..
+            } = self.templates[template];
+            let units: Vec<&Unit> = syllables
+                .iter()
+                .filter(|unit| !skips_blank || !unit.text.trim().is_empty())
+                .collect();
+
+            // Each written line starts with the times of the karaoke line
+            let begin = |timing: &Rc<RefCell<Timing>>, unit: &Unit| {
+                let syllable = (
+                    start.offset(unit.start as i64),
+                    start.offset(unit.end as i64),
+                );
+                *timing.borrow_mut() = Timing {
+                    line: (start, end),
+                    syllable,
+                    output: (start, end),
+                };
+            };
+            let mut write = |text: String, timing: &Rc<RefCell<Timing>>| {
+                let (start, end) = timing.borrow().output;
+                let mut output = SubsControlsValues {
+                    effect: FX.to_owned(),
+                    is_comment: false,
+                    text,
+                    ..line.clone()
+                };
+                output.set_times(start, end.max(start));
+                written.push(output);
+            };
+
+            match kind {
+                Kind::PreLine => {
+                    let whole = Unit {
+                        start: 0,
+                        end: end.saturating_sub(start).as_millis(),
+                        index: 0,
+                        text: karaoke::visible(&line.text),
+                    };
+                    begin(&self.timing, &whole);
+                    let mut text = self.fill(template, &line_map, &whole)?;
+                    if keeps_text {
+                        text.push_str(&whole.text);
+                    }
+                    write(text, &self.timing);
+                }
+                Kind::Line => {
+                    let mut text = String::new();
+                    for unit in units {
+                        begin(&self.timing, unit);
+                        text.push_str(&self.fill(template, &line_map, unit)?);
+                        if keeps_text {
+                            text.push_str(&unit.text);
+                        }
+                    }
+                    write(text, &self.timing);
+                }
+                Kind::Syllable => {
+                    for unit in units {
+                        begin(&self.timing, unit);
+                        let mut text = self.fill(template, &line_map, unit)?;
+                        if keeps_text {
+                            text.push_str(&unit.text);
+                        }
+                        write(text, &self.timing);
+                    }
+                }
+                Kind::Char => {
+                    for syllable in units {
+                        let chars = karaoke::characters(&syllable.text);
+                        for (index, char) in chars.into_iter().enumerate() {
+                            // Characters have the times of their syllable
+                            let unit = Unit {
+                                start: syllable.start,
+                                end: syllable.end,
+                                index,
+                                text: char,
+                            };
+                            begin(&self.timing, &unit);
+                            let mut text = self.fill(template, &line_map, &unit)?;
+                            if keeps_text {
+                                text.push_str(&unit.text);
+                            }
+                            write(text, &self.timing);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// Runs the karaoke templates of `lines`, after removing what a previous run
+/// wrote. Each templated line is commented out and followed by what its
+/// templates wrote.
+pub fn apply(lines: &[SubsControlsValues]) -> Result<Vec<SubsControlsValues>, Error> {
+    let lines = clean(lines);
+    let (templates, codes) = read(&lines)?;
+    let timing = Rc::new(RefCell::new(Timing::default()));
+    let mut templater = Templater {
+        engine: engine(timing.clone()),
+        scope: Scope::new(),
+        timing,
+        templates,
+        codes,
+    };
+    templater.run_code(Run::Once, "")?;
+
+    let mut result = Vec::with_capacity(lines.len());
+    for (row, line) in lines.iter().enumerate() {
+        let written = if is_karaoke(line) {
+            templater.apply_to(line, row)?
+        } else {
+            Vec::new()
+        };
+
+        if written.is_empty() {
+            result.push(line.clone());
+        } else {
+            result.push(SubsControlsValues {
+                effect: KARAOKE.to_owned(),
+                is_comment: true,
+                ..line.clone()
+            });
+            result.extend(written);
+        }
+    }
+    Ok(result)
+}
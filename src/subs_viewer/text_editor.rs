@@ -0,0 +1,982 @@
+use std::ops::Range;
+
+use iced_graphics::{
+    backend::{self, Backend},
+    Primitive, Renderer,
+};
+use iced_native::{mouse, HorizontalAlignment, Point, Rectangle, Vector, VerticalAlignment};
+use iced_native::{Background, Color};
+
+use super::tags::{Item, Line, NodeKind, Tag, TagKind};
+
+pub use text_editor::State;
+
+pub type TextEditor<'a, Message, Backend> = text_editor::TextEditor<'a, Message, Renderer<Backend>>;
+
+/// Class of a byte of the line text, picks its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Text,
+    Brace,
+    TagName,
+    Parameter,
+    Comment,
+    Escape,
+    Drawing,
+}
+
+/// Highlight of every byte of `text`.
+pub fn highlights(text: &str) -> Vec<Highlight> {
+    let mut highlights = vec![Highlight::Text; text.len()];
+    let mut fill = |range: Range<usize>, highlight| {
+        highlights[range].iter_mut().for_each(|h| *h = highlight);
+    };
+
+    for node in Line::parse(text).nodes {
+        match &node.kind {
+            NodeKind::Text => {}
+            NodeKind::Drawing => fill(node.span, Highlight::Drawing),
+            NodeKind::LineBreak { .. } | NodeKind::HardSpace => fill(node.span, Highlight::Escape),
+            NodeKind::Block(items) => {
+                fill(node.span.start..node.span.start + 1, Highlight::Brace);
+                fill(node.span.end - 1..node.span.end, Highlight::Brace);
+                for item in items {
+                    match item {
+                        Item::Tag(tag) => highlight_tag(&mut fill, tag),
+                        Item::Comment(span) => fill(span.clone(), Highlight::Comment),
+                    }
+                }
+            }
+        }
+    }
+
+    highlights
+}
+
+// The tags animated by `\t` are highlighted like the others
+fn highlight_tag(fill: &mut impl FnMut(Range<usize>, Highlight), tag: &Tag<'_>) {
+    fill(tag.span.start..tag.args.start, Highlight::TagName);
+    fill(tag.args.clone(), Highlight::Parameter);
+    if let TagKind::Transform { tags, .. } = &tag.kind {
+        for tag in tags {
+            highlight_tag(fill, tag);
+        }
+    }
+}
+
+/// A row of the editor, the text goes to the next row after a `\N`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// Bytes of the row, with its `\N`.
+    pub span: Range<usize>,
+    /// End of the row text, before its `\N`.
+    pub end: usize,
+    /// Characters shown on screen.
+    pub visible: usize,
+}
+
+pub fn rows(text: &str) -> Vec<Row> {
+    let line = Line::parse(text);
+    let visible: String = line.visible_chars().collect();
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut counts = visible.split('\n').map(|row| row.chars().count());
+    for node in &line.nodes {
+        if let NodeKind::LineBreak { hard: true } = node.kind {
+            rows.push(Row {
+                span: start..node.span.end,
+                end: node.span.start,
+                visible: counts.next().unwrap_or(0),
+            });
+            start = node.span.end;
+        }
+    }
+    rows.push(Row {
+        span: start..text.len(),
+        end: text.len(),
+        visible: counts.next().unwrap_or(0),
+    });
+
+    rows
+}
+
+/// Bracket next to `cursor` and the one matching it, if any. Braces and
+/// parentheses are matched.
+pub fn matching_bracket(text: &str, cursor: usize) -> Option<(usize, Option<usize>)> {
+    let bytes = text.as_bytes();
+    let is_bracket = |index: usize| {
+        matches!(
+            bytes.get(index),
+            Some(b'{') | Some(b'}') | Some(b'(') | Some(b')')
+        )
+    };
+    let position = if is_bracket(cursor) {
+        cursor
+    } else if cursor > 0 && is_bracket(cursor - 1) {
+        cursor - 1
+    } else {
+        return None;
+    };
+
+    // Scanning toward the partner, the bracket itself opens a level
+    let (same, other, forward) = match bytes[position] {
+        b'{' => (b'{', b'}', true),
+        b'}' => (b'}', b'{', false),
+        b'(' => (b'(', b')', true),
+        _ => (b')', b'(', false),
+    };
+
+    let mut depth = 0usize;
+    let scan = |index: &usize| {
+        if bytes[*index] == same {
+            depth += 1;
+        } else if bytes[*index] == other {
+            depth -= 1;
+        }
+        depth == 0
+    };
+    let partner = if forward {
+        (position..bytes.len()).find(scan)
+    } else {
+        (0..=position).rev().find(scan)
+    };
+
+    Some((position, partner))
+}
+
+/// Text drawn in one color.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub position: Point,
+    pub content: String,
+    pub highlight: Highlight,
+}
+
+/// What the editor draws besides its background.
+#[derive(Debug, Clone, Default)]
+pub struct Contents {
+    pub segments: Vec<Segment>,
+    /// Vertical position and visible characters of every row.
+    pub counters: Vec<(f32, usize)>,
+    pub cursor: Option<Rectangle>,
+    pub selection: Vec<Rectangle>,
+    /// Brackets around the cursor, with whether they are matched.
+    pub brackets: Vec<(Rectangle, bool)>,
+}
+
+impl<B> text_editor::Renderer for Renderer<B>
+where
+    B: Backend + backend::Text,
+{
+    type Style = Box<dyn StyleSheet>;
+
+    fn draw(
+        &mut self,
+        bounds: Rectangle,
+        text_bounds: Rectangle,
+        cursor_position: Point,
+        font: Self::Font,
+        size: u16,
+        placeholder: Option<&str>,
+        contents: Contents,
+        is_focused: bool,
+        style: &Self::Style,
+    ) -> Self::Output {
+        let styling = if is_focused {
+            style.focused()
+        } else {
+            style.active()
+        };
+
+        let background = Primitive::Quad {
+            bounds,
+            background: styling.background,
+            border_radius: 0.,
+            border_width: styling.border_width,
+            border_color: styling.border_color,
+        };
+
+        let quad = |bounds: Rectangle, color: Color| Primitive::Quad {
+            bounds,
+            background: color.into(),
+            border_radius: 0.,
+            border_width: 0.,
+            border_color: color,
+        };
+
+        let text = |content: String, position: Point, color: Color, alignment| Primitive::Text {
+            content,
+            color,
+            font,
+            bounds: Rectangle {
+                x: position.x,
+                y: position.y,
+                width: f32::INFINITY,
+                height: f32::from(size),
+            },
+            size: f32::from(size),
+            horizontal_alignment: alignment,
+            vertical_alignment: VerticalAlignment::Top,
+        };
+
+        let mut primitives: Vec<Primitive> = contents
+            .selection
+            .into_iter()
+            .map(|bounds| quad(bounds, styling.selection_color))
+            .collect();
+
+        primitives.extend(contents.brackets.into_iter().map(|(bounds, is_matched)| {
+            let color = if is_matched {
+                styling.bracket_color
+            } else {
+                styling.unmatched_color
+            };
+            quad(bounds, color)
+        }));
+
+        match placeholder {
+            Some(placeholder) => primitives.push(text(
+                placeholder.to_owned(),
+                text_bounds.position(),
+                styling.placeholder_color,
+                HorizontalAlignment::Left,
+            )),
+            None => primitives.extend(contents.segments.into_iter().map(|segment| {
+                let color = styling.color(segment.highlight);
+                text(
+                    segment.content,
+                    segment.position,
+                    color,
+                    HorizontalAlignment::Left,
+                )
+            })),
+        }
+
+        primitives.extend(
+            contents
+                .cursor
+                .map(|bounds| quad(bounds, styling.cursor_color)),
+        );
+
+        let content = Primitive::Clip {
+            bounds: text_bounds,
+            offset: Vector::new(0, 0),
+            content: Box::new(Primitive::Group { primitives }),
+        };
+
+        let counter_x = bounds.x + bounds.width - f32::from(size) / 2.0;
+        let counters = contents.counters.into_iter().map(|(y, count)| {
+            text(
+                count.to_string(),
+                Point::new(counter_x, y),
+                styling.counter_color,
+                HorizontalAlignment::Right,
+            )
+        });
+
+        let mut primitives = vec![background, content];
+        primitives.extend(counters);
+
+        let interaction = if bounds.contains(cursor_position) {
+            mouse::Interaction::Text
+        } else {
+            mouse::Interaction::default()
+        };
+
+        (Primitive::Group { primitives }, interaction)
+    }
+}
+
+mod text_editor {
+    use std::ops::Range;
+
+    use iced_native::{
+        event, keyboard,
+        layout::{Limits, Node},
+        mouse, text, Clipboard, Element, Event, Hasher, Layout, Length, Point, Rectangle, Size,
+        Widget,
+    };
+
+    use super::{highlights, matching_bracket, rows, Contents, Row, Segment};
+
+    // Room on the right of the text for the character counters
+    const COUNTER_WIDTH: f32 = 36.0;
+    const ROW_GAP: f32 = 4.0;
+    const CURSOR_WIDTH: f32 = 1.0;
+
+    /// Cursor and selection of a text editor.
+    #[derive(Debug, Clone, Default)]
+    pub struct State {
+        is_focused: bool,
+        is_dragging: bool,
+        cursor: usize,
+        // Other end of the selection
+        anchor: Option<usize>,
+        modifiers: keyboard::Modifiers,
+    }
+
+    impl State {
+        /// Selected bytes of `text`, empty at the cursor when nothing is
+        /// selected.
+        pub fn selection(&self, text: &str) -> Range<usize> {
+            let cursor = snap(text, self.cursor);
+            let anchor = self.anchor.map_or(cursor, |anchor| snap(text, anchor));
+            cursor.min(anchor)..cursor.max(anchor)
+        }
+
+        pub fn select(&mut self, selection: Range<usize>) {
+            self.anchor = Some(selection.start);
+            self.cursor = selection.end;
+        }
+
+        fn move_to(&mut self, offset: usize, extend: bool) {
+            if extend {
+                self.anchor.get_or_insert(self.cursor);
+            } else {
+                self.anchor = None;
+            }
+            self.cursor = offset;
+        }
+    }
+
+    // The text may change under the state, offsets are kept on a char
+    fn snap(text: &str, offset: usize) -> usize {
+        let mut offset = offset.min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    fn previous_char(text: &str, offset: usize) -> usize {
+        text[..offset]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_char(text: &str, offset: usize) -> usize {
+        text[offset..]
+            .chars()
+            .next()
+            .map_or(offset, |c| offset + c.len_utf8())
+    }
+
+    /// A multi-line editor for the line text, highlighting the override
+    /// tags. `\N` breaks the rows and every row shows its character count.
+    pub struct TextEditor<'a, Message, Renderer: self::Renderer> {
+        state: &'a mut State,
+        value: String,
+        placeholder: String,
+        font: Renderer::Font,
+        width: Length,
+        padding: u16,
+        size: Option<u16>,
+        on_change: Box<dyn Fn(String) -> Message + 'a>,
+        on_submit: Option<Message>,
+        style: Renderer::Style,
+    }
+
+    impl<'a, Message, Renderer> TextEditor<'a, Message, Renderer>
+    where
+        Message: Clone,
+        Renderer: self::Renderer,
+    {
+        pub fn new<F>(state: &'a mut State, placeholder: &str, value: &str, on_change: F) -> Self
+        where
+            F: 'a + Fn(String) -> Message,
+        {
+            Self {
+                state,
+                value: String::from(value),
+                placeholder: String::from(placeholder),
+                font: Default::default(),
+                width: Length::Fill,
+                padding: 5,
+                size: None,
+                on_change: Box::new(on_change),
+                on_submit: None,
+                style: Renderer::Style::default(),
+            }
+        }
+
+        pub fn width(mut self, width: Length) -> Self {
+            self.width = width;
+            self
+        }
+
+        pub fn padding(mut self, units: u16) -> Self {
+            self.padding = units;
+            self
+        }
+
+        pub fn size(mut self, size: u16) -> Self {
+            self.size = Some(size);
+            self
+        }
+
+        /// Message sent on Enter, Shift+Enter inserts a `\N`.
+        pub fn on_submit(mut self, message: Message) -> Self {
+            self.on_submit = Some(message);
+            self
+        }
+
+        pub fn style(mut self, style: impl Into<Renderer::Style>) -> Self {
+            self.style = style.into();
+            self
+        }
+
+        fn text_size(&self, renderer: &Renderer) -> u16 {
+            self.size.unwrap_or(renderer.default_size())
+        }
+
+        fn line_height(&self, renderer: &Renderer) -> f32 {
+            f32::from(self.text_size(renderer)) + ROW_GAP
+        }
+
+        fn measure(&self, renderer: &Renderer, text: &str) -> f32 {
+            let (width, _) =
+                renderer.measure(text, self.text_size(renderer), self.font, Size::INFINITY);
+            width
+        }
+
+        // Row and horizontal position of `offset`
+        fn position(&self, renderer: &Renderer, rows: &[Row], offset: usize) -> (usize, f32) {
+            let offset = snap(&self.value, offset);
+            let row = rows
+                .iter()
+                .rposition(|row| row.span.start <= offset)
+                .unwrap_or(0);
+            let start = rows[row].span.start;
+            (row, self.measure(renderer, &self.value[start..offset]))
+        }
+
+        // Offset of the row `row` closest to the horizontal position `x`
+        fn offset_at(&self, renderer: &Renderer, rows: &[Row], row: usize, x: f32) -> usize {
+            let row = &rows[row.min(rows.len() - 1)];
+            let text = &self.value[row.span.start..row.end];
+
+            let mut closest = (row.span.start, x.abs());
+            for (index, c) in text.char_indices() {
+                let end = index + c.len_utf8();
+                let distance = (self.measure(renderer, &text[..end]) - x).abs();
+                if distance < closest.1 {
+                    closest = (row.span.start + end, distance);
+                }
+            }
+            closest.0
+        }
+
+        // Rows wider than the editor scroll to keep the cursor in sight
+        fn scroll(&self, renderer: &Renderer, rows: &[Row], width: f32) -> f32 {
+            let (_, x) = self.position(renderer, rows, self.state.cursor);
+            (x - width + CURSOR_WIDTH).max(0.0)
+        }
+
+        fn text_width(text_bounds: Rectangle) -> f32 {
+            (text_bounds.width - COUNTER_WIDTH).max(0.0)
+        }
+
+        fn hit(&self, renderer: &Renderer, text_bounds: Rectangle, point: Point) -> usize {
+            let rows = rows(&self.value);
+            let scroll = self.scroll(renderer, &rows, Self::text_width(text_bounds));
+            let row = ((point.y - text_bounds.y) / self.line_height(renderer)).max(0.0) as usize;
+            self.offset_at(renderer, &rows, row, point.x - text_bounds.x + scroll)
+        }
+
+        fn replace_selection(&mut self, with: &str, messages: &mut Vec<Message>) {
+            let selection = self.state.selection(&self.value);
+            self.value.replace_range(selection.clone(), with);
+            self.state.cursor = selection.start + with.len();
+            self.state.anchor = None;
+            messages.push((self.on_change)(self.value.clone()));
+        }
+
+        fn on_key(
+            &mut self,
+            key_code: keyboard::KeyCode,
+            modifiers: keyboard::Modifiers,
+            renderer: &Renderer,
+            clipboard: &mut dyn Clipboard,
+            messages: &mut Vec<Message>,
+        ) {
+            use keyboard::KeyCode;
+
+            let cursor = self.state.cursor;
+            let selection = self.state.selection(&self.value);
+            let extend = modifiers.shift;
+
+            if modifiers.is_command_pressed() {
+                match key_code {
+                    KeyCode::A => {
+                        self.state.anchor = Some(0);
+                        self.state.cursor = self.value.len();
+                    }
+                    KeyCode::C if !selection.is_empty() => {
+                        clipboard.write(self.value[selection].to_owned());
+                    }
+                    KeyCode::X if !selection.is_empty() => {
+                        clipboard.write(self.value[selection].to_owned());
+                        self.replace_selection("", messages);
+                    }
+                    KeyCode::V => {
+                        if let Some(content) = clipboard.read() {
+                            // Pasted line breaks become `\N`
+                            let content = content
+                                .lines()
+                                .filter(|line| !line.is_empty())
+                                .collect::<Vec<_>>()
+                                .join("\\N");
+                            self.replace_selection(&content, messages);
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+
+            match key_code {
+                KeyCode::Enter | KeyCode::NumpadEnter if modifiers.shift => {
+                    self.replace_selection("\\N", messages);
+                }
+                KeyCode::Enter | KeyCode::NumpadEnter => {
+                    if let Some(on_submit) = self.on_submit.clone() {
+                        messages.push(on_submit);
+                    }
+                }
+                KeyCode::Backspace => {
+                    if selection.is_empty() && cursor > 0 {
+                        self.state.anchor = Some(previous_char(&self.value, cursor));
+                    }
+                    if !self.state.selection(&self.value).is_empty() {
+                        self.replace_selection("", messages);
+                    }
+                }
+                KeyCode::Delete => {
+                    if selection.is_empty() {
+                        self.state.anchor = Some(next_char(&self.value, cursor));
+                    }
+                    if !self.state.selection(&self.value).is_empty() {
+                        self.replace_selection("", messages);
+                    }
+                }
+                KeyCode::Left => {
+                    let offset = if selection.is_empty() || extend {
+                        previous_char(&self.value, cursor)
+                    } else {
+                        selection.start
+                    };
+                    self.state.move_to(offset, extend);
+                }
+                KeyCode::Right => {
+                    let offset = if selection.is_empty() || extend {
+                        next_char(&self.value, cursor)
+                    } else {
+                        selection.end
+                    };
+                    self.state.move_to(offset, extend);
+                }
+                KeyCode::Up | KeyCode::Down => {
+                    let rows = rows(&self.value);
+                    let (row, x) = self.position(renderer, &rows, cursor);
+                    let offset = match key_code {
+                        KeyCode::Up if row == 0 => 0,
+                        KeyCode::Up => self.offset_at(renderer, &rows, row - 1, x),
+                        _ if row + 1 == rows.len() => self.value.len(),
+                        _ => self.offset_at(renderer, &rows, row + 1, x),
+                    };
+                    self.state.move_to(offset, extend);
+                }
+                KeyCode::Home | KeyCode::End => {
+                    let rows = rows(&self.value);
+                    let (row, _) = self.position(renderer, &rows, cursor);
+                    let offset = match key_code {
+                        KeyCode::Home => rows[row].span.start,
+                        _ => rows[row].end,
+                    };
+                    self.state.move_to(offset, extend);
+                }
+                KeyCode::Escape => {
+                    self.state.is_focused = false;
+                    self.state.anchor = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl<'a, Message, Renderer> Widget<Message, Renderer> for TextEditor<'a, Message, Renderer>
+    where
+        Message: Clone,
+        Renderer: self::Renderer,
+    {
+        fn width(&self) -> Length {
+            self.width
+        }
+
+        fn height(&self) -> Length {
+            Length::Shrink
+        }
+
+        fn layout(&self, renderer: &Renderer, limits: &Limits) -> Node {
+            let padding = f32::from(self.padding);
+            let height = rows(&self.value).len() as f32 * self.line_height(renderer);
+
+            let limits = limits
+                .pad(padding)
+                .width(self.width)
+                .height(Length::Units(height.ceil() as u16));
+
+            let mut text = Node::new(limits.resolve(Size::ZERO));
+            text.move_to(Point::new(padding, padding));
+
+            Node::with_children(text.size().pad(padding), vec![text])
+        }
+
+        fn on_event(
+            &mut self,
+            event: Event,
+            layout: Layout<'_>,
+            cursor_position: Point,
+            renderer: &Renderer,
+            clipboard: &mut dyn Clipboard,
+            messages: &mut Vec<Message>,
+        ) -> event::Status {
+            let bounds = layout.bounds();
+            let text_bounds = layout
+                .children()
+                .next()
+                .expect("fail to get text layout")
+                .bounds();
+
+            self.state.cursor = snap(&self.value, self.state.cursor);
+            self.state.anchor = self.state.anchor.map(|anchor| snap(&self.value, anchor));
+
+            match event {
+                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                    if !bounds.contains(cursor_position) {
+                        self.state.is_focused = false;
+                        return event::Status::Ignored;
+                    }
+                    let offset = self.hit(renderer, text_bounds, cursor_position);
+                    let extend = self.state.is_focused && self.state.modifiers.shift;
+                    self.state.move_to(offset, extend);
+                    self.state.is_focused = true;
+                    self.state.is_dragging = true;
+                    event::Status::Captured
+                }
+                Event::Mouse(mouse::Event::CursorMoved { position }) if self.state.is_dragging => {
+                    let offset = self.hit(renderer, text_bounds, position);
+                    self.state.move_to(offset, true);
+                    event::Status::Captured
+                }
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    self.state.is_dragging = false;
+                    event::Status::Ignored
+                }
+                Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                    self.state.modifiers = modifiers;
+                    event::Status::Ignored
+                }
+                Event::Keyboard(keyboard::Event::CharacterReceived(c))
+                    if self.state.is_focused
+                        && !c.is_control()
+                        && !self.state.modifiers.is_command_pressed()
+                        && !self.state.modifiers.alt =>
+                {
+                    let mut buffer = [0; 4];
+                    self.replace_selection(c.encode_utf8(&mut buffer), messages);
+                    event::Status::Captured
+                }
+                // Shortcuts with Alt are left to the application
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code,
+                    modifiers,
+                }) if self.state.is_focused && !modifiers.alt => {
+                    self.on_key(key_code, modifiers, renderer, clipboard, messages);
+                    event::Status::Captured
+                }
+                _ => event::Status::Ignored,
+            }
+        }
+
+        fn draw(
+            &self,
+            renderer: &mut Renderer,
+            _defaults: &Renderer::Defaults,
+            layout: Layout<'_>,
+            cursor_position: Point,
+            _viewport: &Rectangle,
+        ) -> Renderer::Output {
+            let bounds = layout.bounds();
+            let text_layout = layout.children().next().expect("fail to get text layout");
+            let text_bounds = text_layout.bounds();
+
+            let size = self.text_size(renderer);
+            let line_height = self.line_height(renderer);
+            let text_width = Self::text_width(text_bounds);
+
+            let rows = rows(&self.value);
+            let highlights = highlights(&self.value);
+            let scroll = self.scroll(renderer, &rows, text_width);
+            let cursor = snap(&self.value, self.state.cursor);
+            let selection = self.state.selection(&self.value);
+
+            let row_y = |row: usize| text_bounds.y + row as f32 * line_height;
+            let x_of = |offset: usize| {
+                let (row, x) = self.position(renderer, &rows, offset);
+                (row, text_bounds.x + x - scroll)
+            };
+
+            let mut contents = Contents::default();
+            for (index, row) in rows.iter().enumerate() {
+                let y = row_y(index);
+                contents.counters.push((y, row.visible));
+
+                // Consecutive bytes of the same highlight are drawn at once
+                let mut start = row.span.start;
+                while start < row.span.end {
+                    let highlight = highlights[start];
+                    let end = (start..row.span.end)
+                        .find(|&offset| highlights[offset] != highlight)
+                        .unwrap_or(row.span.end);
+                    let x = self.measure(renderer, &self.value[row.span.start..start]);
+                    contents.segments.push(Segment {
+                        position: Point::new(text_bounds.x + x - scroll, y),
+                        content: self.value[start..end].to_owned(),
+                        highlight,
+                    });
+                    start = end;
+                }
+
+                let start = selection.start.max(row.span.start);
+                let end = selection.end.min(row.span.end);
+                if start < end {
+                    let (_, from) = x_of(start);
+                    let to = text_bounds.x - scroll
+                        + self.measure(renderer, &self.value[row.span.start..end]);
+                    contents.selection.push(Rectangle {
+                        x: from,
+                        y,
+                        width: to - from,
+                        height: line_height,
+                    });
+                }
+            }
+
+            if self.state.is_focused {
+                let (row, x) = x_of(cursor);
+                contents.cursor = Some(Rectangle {
+                    x,
+                    y: row_y(row),
+                    width: CURSOR_WIDTH,
+                    height: line_height,
+                });
+
+                if let Some((bracket, partner)) = matching_bracket(&self.value, cursor) {
+                    let is_matched = partner.is_some();
+                    for offset in std::iter::once(bracket).chain(partner) {
+                        let (row, x) = x_of(offset);
+                        let width = self.measure(renderer, &self.value[offset..offset + 1]);
+                        contents.brackets.push((
+                            Rectangle {
+                                x,
+                                y: row_y(row),
+                                width,
+                                height: line_height,
+                            },
+                            is_matched,
+                        ));
+                    }
+                }
+            }
+
+            let placeholder = if self.value.is_empty() {
+                Some(self.placeholder.as_str())
+            } else {
+                None
+            };
+
+            self::Renderer::draw(
+                renderer,
+                bounds,
+                Rectangle {
+                    width: text_width,
+                    ..text_bounds
+                },
+                cursor_position,
+                self.font,
+                size,
+                placeholder,
+                contents,
+                self.state.is_focused,
+                &self.style,
+            )
+        }
+
+        fn hash_layout(&self, state: &mut Hasher) {
+            use std::hash::Hash;
+            struct Marker;
+            std::any::TypeId::of::<Marker>().hash(state);
+
+            self.width.hash(state);
+            self.padding.hash(state);
+            self.size.hash(state);
+            rows(&self.value).len().hash(state);
+        }
+    }
+
+    pub trait Renderer: text::Renderer + Sized {
+        type Style: Default;
+
+        fn draw(
+            &mut self,
+            bounds: Rectangle,
+            text_bounds: Rectangle,
+            cursor_position: Point,
+            font: Self::Font,
+            size: u16,
+            placeholder: Option<&str>,
+            contents: Contents,
+            is_focused: bool,
+            style: &Self::Style,
+        ) -> Self::Output;
+    }
+
+    impl<'a, Message, Renderer> From<TextEditor<'a, Message, Renderer>>
+        for Element<'a, Message, Renderer>
+    where
+        Message: 'a + Clone,
+        Renderer: 'a + self::Renderer,
+    {
+        fn from(editor: TextEditor<'a, Message, Renderer>) -> Self {
+            Element::new(editor)
+        }
+    }
+
+    impl Renderer for iced_native::renderer::Null {
+        type Style = ();
+
+        fn draw(
+            &mut self,
+            _: Rectangle,
+            _: Rectangle,
+            _: Point,
+            _: Self::Font,
+            _: u16,
+            _: Option<&str>,
+            _: Contents,
+            _: bool,
+            _: &Self::Style,
+        ) -> Self::Output {
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Style {
+    pub background: Background,
+
+    pub border_width: f32,
+
+    pub border_color: Color,
+
+    pub text_color: Color,
+
+    pub brace_color: Color,
+
+    pub tag_color: Color,
+
+    pub parameter_color: Color,
+
+    pub comment_color: Color,
+
+    pub escape_color: Color,
+
+    pub drawing_color: Color,
+
+    pub placeholder_color: Color,
+
+    pub counter_color: Color,
+
+    pub cursor_color: Color,
+
+    pub selection_color: Color,
+
+    pub bracket_color: Color,
+
+    pub unmatched_color: Color,
+}
+
+impl Style {
+    pub fn color(&self, highlight: Highlight) -> Color {
+        match highlight {
+            Highlight::Text => self.text_color,
+            Highlight::Brace => self.brace_color,
+            Highlight::TagName => self.tag_color,
+            Highlight::Parameter => self.parameter_color,
+            Highlight::Comment => self.comment_color,
+            Highlight::Escape => self.escape_color,
+            Highlight::Drawing => self.drawing_color,
+        }
+    }
+}
+
+pub trait StyleSheet {
+    fn active(&self) -> Style;
+
+    fn focused(&self) -> Style;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Default;
+
+fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::from_rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+impl StyleSheet for Default {
+    fn active(&self) -> Style {
+        Style {
+            background: Color::WHITE.into(),
+            border_width: 1.,
+            border_color: rgb(0xB4, 0xB8, 0xBF),
+            text_color: Color::BLACK,
+            brace_color: rgb(0x80, 0x80, 0x80),
+            tag_color: rgb(0x1F, 0x4E, 0xBF),
+            parameter_color: rgb(0x9A, 0x4F, 0x00),
+            comment_color: rgb(0x3A, 0x8A, 0x3A),
+            escape_color: rgb(0xA0, 0x20, 0xA0),
+            drawing_color: rgb(0x00, 0x80, 0x80),
+            placeholder_color: rgb(0xA0, 0xA0, 0xA0),
+            counter_color: rgb(0x80, 0x80, 0x80),
+            cursor_color: Color::BLACK,
+            selection_color: rgb(0xC8, 0xDC, 0xF8),
+            bracket_color: rgb(0xD8, 0xE8, 0xC0),
+            unmatched_color: rgb(0xF4, 0xC0, 0xC0),
+        }
+    }
+
+    fn focused(&self) -> Style {
+        Style {
+            border_color: rgb(0x5E, 0x7C, 0xE2),
+            ..self.active()
+        }
+    }
+}
+
+impl std::default::Default for Box<dyn StyleSheet> {
+    fn default() -> Self {
+        Box::new(Default)
+    }
+}
+
+impl<T> From<T> for Box<dyn StyleSheet>
+where
+    T: 'static + StyleSheet,
+{
+    fn from(style: T) -> Self {
+        Box::new(style)
+    }
+}
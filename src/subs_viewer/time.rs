@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+const MS_PER_CENTISECOND: u64 = 10;
+const MS_PER_SECOND: u64 = 1000;
+const MS_PER_MINUTE: u64 = 60 * MS_PER_SECOND;
+const MS_PER_HOUR: u64 = 60 * MS_PER_MINUTE;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("Time must have the hh:mm:ss:cc form")]
+    WrongFormat,
+    #[error("Time field out of range")]
+    OutOfRange,
+}
+
+/// A subtitle timestamp stored in milliseconds.
+///
+/// Its textual form is `hh:mm:ss:cc`, where `cc` are centiseconds. The ASS
+/// `h:mm:ss.cc` form is accepted too.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time(u64);
+
+impl Time {
+    pub const ZERO: Time = Time(0);
+
+    pub fn from_millis(millis: u64) -> Self {
+        Self(millis)
+    }
+
+    pub fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    pub fn saturating_sub(self, other: Time) -> Time {
+        Time(self.0.saturating_sub(other.0))
+    }
+
+    pub fn saturating_add(self, other: Time) -> Time {
+        Time(self.0.saturating_add(other.0))
+    }
+
+    /// Signed distance in milliseconds from `other` to `self`.
+    pub fn diff(self, other: Time) -> i64 {
+        self.0 as i64 - other.0 as i64
+    }
+
+    /// Moves the time by a signed amount of milliseconds, clamping at zero.
+    pub fn offset(self, millis: i64) -> Time {
+        if millis < 0 {
+            Time(self.0.saturating_sub(millis.unsigned_abs()))
+        } else {
+            Time(self.0.saturating_add(millis as u64))
+        }
+    }
+}
+
+impl FromStr for Time {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = value.trim().split(|c| c == ':' || c == '.').collect();
+        if fields.len() != 4 {
+            return Err(Error::WrongFormat);
+        }
+
+        let mut numbers = [0u64; 4];
+        for (number, field) in numbers.iter_mut().zip(&fields) {
+            if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+                return Err(Error::WrongFormat);
+            }
+            *number = field.parse().map_err(|_| Error::OutOfRange)?;
+        }
+
+        let [hours, minutes, seconds, centiseconds] = numbers;
+        if minutes > 59 || seconds > 59 || centiseconds > 99 {
+            return Err(Error::OutOfRange);
+        }
+
+        // Typed hours can be too many for milliseconds
+        hours
+            .checked_mul(MS_PER_HOUR)
+            .and_then(|millis| {
+                millis.checked_add(
+                    minutes * MS_PER_MINUTE
+                        + seconds * MS_PER_SECOND
+                        + centiseconds * MS_PER_CENTISECOND,
+                )
+            })
+            .map(Self)
+            .ok_or(Error::OutOfRange)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Round to the nearest centisecond, as ASS does
+        let centiseconds = self.0.saturating_add(MS_PER_CENTISECOND / 2) / MS_PER_CENTISECOND;
+        write!(
+            f,
+            "{:02}:{:02}:{:02}:{:02}",
+            centiseconds / 360_000,
+            centiseconds / 6000 % 60,
+            centiseconds / 100 % 60,
+            centiseconds % 100
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_forms_are_parsed() {
+        let time = Time::from_millis(3_723_450);
+        assert_eq!("01:02:03:45".parse(), Ok(time));
+        assert_eq!("1:02:03.45".parse(), Ok(time));
+        assert_eq!(" 01:02:03:45 ".parse(), Ok(time));
+    }
+
+    #[test]
+    fn malformed_times_are_rejected() {
+        assert_eq!("01:02:03".parse::<Time>(), Err(Error::WrongFormat));
+        assert_eq!("01:02:03:4x".parse::<Time>(), Err(Error::WrongFormat));
+        assert_eq!("01::03:45".parse::<Time>(), Err(Error::WrongFormat));
+        assert_eq!("-1:02:03:45".parse::<Time>(), Err(Error::WrongFormat));
+        assert_eq!("01:60:03:45".parse::<Time>(), Err(Error::OutOfRange));
+        assert_eq!("01:02:03:100".parse::<Time>(), Err(Error::OutOfRange));
+        assert_eq!(
+            "18446744073709551615:00:00:00".parse::<Time>(),
+            Err(Error::OutOfRange)
+        );
+        assert_eq!(
+            "5124095576030:59:59:99".parse::<Time>(),
+            Err(Error::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn display_rounds_to_the_centisecond() {
+        assert_eq!(Time::from_millis(3_723_450).to_string(), "01:02:03:45");
+        assert_eq!(Time::from_millis(1234).to_string(), "00:00:01:23");
+        assert_eq!(Time::from_millis(1235).to_string(), "00:00:01:24");
+        assert_eq!(Time::from_millis(59_995).to_string(), "00:01:00:00");
+    }
+}
@@ -0,0 +1,470 @@
+use std::fmt;
+use std::ops::Range;
+
+use iced::{
+    button, pick_list, text_input, Align, Button, Column, Element, Length, PickList, Row, Text,
+    TextInput,
+};
+
+use super::frame_rate::FrameRate;
+use super::time::Time;
+use super::timing_tools::{Scope, ALL_SCOPES};
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const INPUT_WIDTH: u16 = 110;
+// Shifts kept to be applied again, the latest first
+const RECENT_SHIFTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Time,
+    Frames,
+}
+
+impl Default for Unit {
+    fn default() -> Self {
+        Self::Time
+    }
+}
+
+impl From<Unit> for &'static str {
+    fn from(val: Unit) -> Self {
+        match val {
+            Unit::Time => "Time",
+            Unit::Frames => "Frames",
+        }
+    }
+}
+
+static ALL_UNITS: &[Unit] = &[Unit::Time, Unit::Frames];
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+impl Default for Direction {
+    fn default() -> Self {
+        Self::Forward
+    }
+}
+
+impl From<Direction> for &'static str {
+    fn from(val: Direction) -> Self {
+        match val {
+            Direction::Forward => "Forward",
+            Direction::Backward => "Backward",
+        }
+    }
+}
+
+static ALL_DIRECTIONS: &[Direction] = &[Direction::Forward, Direction::Backward];
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+/// Times of a line a shift moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Times {
+    Both,
+    Start,
+    End,
+}
+
+impl Default for Times {
+    fn default() -> Self {
+        Self::Both
+    }
+}
+
+impl From<Times> for &'static str {
+    fn from(val: Times) -> Self {
+        match val {
+            Times::Both => "Start and end",
+            Times::Start => "Start",
+            Times::End => "End",
+        }
+    }
+}
+
+static ALL_TIMES: &[Times] = &[Times::Both, Times::Start, Times::End];
+
+impl fmt::Display for Times {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+// How far lines move
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Amount {
+    Time(Time),
+    /// Lines stay on frame starts, as if they were timed to the video.
+    Frames(u64, FrameRate),
+}
+
+/// A shift as it was applied, to apply it again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shift {
+    amount: Amount,
+    direction: Direction,
+    times: Times,
+    scope: Scope,
+    // Only used by `Scope::After`
+    after: Time,
+}
+
+impl Shift {
+    fn moved(&self, time: Time) -> Time {
+        match (self.amount, self.direction) {
+            (Amount::Time(amount), Direction::Forward) => time.saturating_add(amount),
+            (Amount::Time(amount), Direction::Backward) => time.saturating_sub(amount),
+            (Amount::Frames(frames, rate), Direction::Forward) => {
+                rate.time_of(rate.frame_at(time) + frames)
+            }
+            (Amount::Frames(frames, rate), Direction::Backward) => {
+                rate.time_of(rate.frame_at(time).saturating_sub(frames))
+            }
+        }
+    }
+
+    /// Shifts the lines in scope of `lines`, returning how many moved.
+    fn apply(&self, lines: &mut [SubsControlsValues]) -> usize {
+        let mut count = 0;
+        for line in lines {
+            let (start, end) = match (line.start(), line.end()) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            if self.scope == Scope::After && start < self.after {
+                continue;
+            }
+
+            let new_start = match self.times {
+                Times::Both | Times::Start => self.moved(start),
+                Times::End => start,
+            };
+            let new_end = match self.times {
+                Times::Both | Times::End => self.moved(end),
+                Times::Start => end,
+            };
+            // Moving one end past the other leaves an empty line
+            line.set_times(new_start, new_end.max(new_start));
+            count += 1;
+        }
+        count
+    }
+}
+
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.amount {
+            Amount::Time(time) => write!(f, "{} {}", self.direction, time)?,
+            Amount::Frames(frames, rate) => {
+                write!(f, "{} {} frames at {} fps", self.direction, frames, rate)?
+            }
+        }
+        let times = match self.times {
+            Times::Both => "start and end",
+            Times::Start => "start",
+            Times::End => "end",
+        };
+        match self.scope {
+            Scope::All => write!(f, ", {} of all lines", times),
+            Scope::Selection => write!(f, ", {} of the selected lines", times),
+            Scope::After => write!(f, ", {} of the lines from {}", times, self.after),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `lines` replace the lines of `rows`.
+    Shifted {
+        name: String,
+        rows: Range<usize>,
+        lines: Vec<SubsControlsValues>,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    AmountChanged(String),
+    UnitPicked(Unit),
+    FrameRateChanged(String),
+    DirectionPicked(Direction),
+    TimesPicked(Times),
+    ScopePicked(Scope),
+    AfterChanged(String),
+    Apply,
+    /// Applies a recent shift again.
+    Reapply(usize),
+    Close,
+}
+
+/// Moves lines earlier or later by a time or a number of frames.
+#[derive(Default)]
+pub struct TimeShifter {
+    amount: text_input::State,
+    unit: pick_list::State<Unit>,
+    frame_rate: text_input::State,
+    direction: pick_list::State<Direction>,
+    times: pick_list::State<Times>,
+    scope: pick_list::State<Scope>,
+    after: text_input::State,
+    apply: button::State,
+    close: button::State,
+    recent_buttons: Vec<button::State>,
+    amount_input: String,
+    unit_value: Unit,
+    frame_rate_input: String,
+    direction_value: Direction,
+    times_value: Times,
+    scope_value: Scope,
+    after_input: String,
+    recent: Vec<Shift>,
+    // Outcome of the last shift
+    status: String,
+}
+
+impl TimeShifter {
+    // The shift described by the inputs
+    fn shift(&self) -> Result<Shift, String> {
+        let amount = match self.unit_value {
+            Unit::Time => Amount::Time(
+                self.amount_input
+                    .parse::<Time>()
+                    .map_err(|error| error.to_string())?,
+            ),
+            Unit::Frames => Amount::Frames(
+                self.amount_input
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| "Frames must be a whole number".to_owned())?,
+                self.frame_rate_input
+                    .parse::<FrameRate>()
+                    .map_err(|error| error.to_string())?,
+            ),
+        };
+        let after = match self.scope_value {
+            Scope::After => self
+                .after_input
+                .parse::<Time>()
+                .map_err(|error| error.to_string())?,
+            Scope::All | Scope::Selection => Time::ZERO,
+        };
+
+        Ok(Shift {
+            amount,
+            direction: self.direction_value,
+            times: self.times_value,
+            scope: self.scope_value,
+            after,
+        })
+    }
+
+    fn apply(
+        &mut self,
+        shift: Shift,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+    ) -> Option<Event> {
+        let rows = shift.scope.rows(lines.len(), selection);
+        let mut shifted = lines[rows.clone()].to_vec();
+        let count = shift.apply(&mut shifted);
+
+        self.recent.retain(|recent| *recent != shift);
+        self.recent.insert(0, shift);
+        self.recent.truncate(RECENT_SHIFTS);
+
+        if count == 0 {
+            self.status = "No line to shift".to_owned();
+            return None;
+        }
+        self.status = format!("Shifted {} lines", count);
+        Some(Event::Shifted {
+            name: format!(
+                "Shift {} lines {}",
+                count,
+                shift.direction.to_string().to_lowercase()
+            ),
+            rows,
+            lines: shifted,
+        })
+    }
+
+    /// Shifts `lines`, of which `selection` is selected.
+    pub fn update(
+        &mut self,
+        message: Message,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+    ) -> Option<Event> {
+        match message {
+            Message::AmountChanged(amount) => self.amount_input = amount,
+            Message::UnitPicked(unit) => self.unit_value = unit,
+            Message::FrameRateChanged(frame_rate) => self.frame_rate_input = frame_rate,
+            Message::DirectionPicked(direction) => self.direction_value = direction,
+            Message::TimesPicked(times) => self.times_value = times,
+            Message::ScopePicked(scope) => self.scope_value = scope,
+            Message::AfterChanged(after) => self.after_input = after,
+            Message::Apply => match self.shift() {
+                Ok(shift) => return self.apply(shift, lines, selection),
+                Err(error) => self.status = error,
+            },
+            Message::Reapply(index) => {
+                if let Some(&shift) = self.recent.get(index) {
+                    return self.apply(shift, lines, selection);
+                }
+            }
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let mut amount = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.direction,
+                    ALL_DIRECTIONS,
+                    Some(self.direction_value),
+                    Message::DirectionPicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                TextInput::new(
+                    &mut self.amount,
+                    match self.unit_value {
+                        Unit::Time => "00:00:01:00",
+                        Unit::Frames => "24",
+                    },
+                    &self.amount_input,
+                    Message::AmountChanged,
+                )
+                .on_submit(Message::Apply)
+                .width(Length::Units(INPUT_WIDTH))
+                .size(TEXT_SIZE),
+            )
+            .push(
+                PickList::new(
+                    &mut self.unit,
+                    ALL_UNITS,
+                    Some(self.unit_value),
+                    Message::UnitPicked,
+                )
+                .text_size(TEXT_SIZE),
+            );
+        if self.unit_value == Unit::Frames {
+            amount = amount.push(Text::new("at").size(TEXT_SIZE)).push(
+                TextInput::new(
+                    &mut self.frame_rate,
+                    "23.976",
+                    &self.frame_rate_input,
+                    Message::FrameRateChanged,
+                )
+                .on_submit(Message::Apply)
+                .width(Length::Units(INPUT_WIDTH))
+                .size(TEXT_SIZE),
+            );
+        }
+
+        let mut scope = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.times,
+                    ALL_TIMES,
+                    Some(self.times_value),
+                    Message::TimesPicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(Text::new("of").size(TEXT_SIZE))
+            .push(
+                PickList::new(
+                    &mut self.scope,
+                    ALL_SCOPES,
+                    Some(self.scope_value),
+                    Message::ScopePicked,
+                )
+                .text_size(TEXT_SIZE),
+            );
+        if self.scope_value == Scope::After {
+            scope = scope.push(
+                TextInput::new(
+                    &mut self.after,
+                    "00:00:00:00",
+                    &self.after_input,
+                    Message::AfterChanged,
+                )
+                .on_submit(Message::Apply)
+                .width(Length::Units(INPUT_WIDTH))
+                .size(TEXT_SIZE),
+            );
+        }
+
+        let actions = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Button::new(&mut self.apply, Text::new("Shift").size(TEXT_SIZE))
+                    .on_press(Message::Apply),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(&self.status).size(TEXT_SIZE));
+
+        self.recent_buttons
+            .resize_with(self.recent.len(), button::State::default);
+        let recent = self
+            .recent_buttons
+            .iter_mut()
+            .zip(&self.recent)
+            .enumerate()
+            .fold(
+                Column::new()
+                    .spacing(COLUMN_SPACING)
+                    .push(Text::new("Recent shifts, click to apply again").size(TEXT_SIZE)),
+                |column, (index, (state, shift))| {
+                    column.push(
+                        Button::new(state, Text::new(shift.to_string()).size(TEXT_SIZE))
+                            .on_press(Message::Reapply(index)),
+                    )
+                },
+            );
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(Text::new("Move lines earlier or later").size(TEXT_SIZE))
+            .push(amount)
+            .push(scope)
+            .push(actions)
+            .push(recent)
+            .into()
+    }
+}
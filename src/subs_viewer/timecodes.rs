@@ -0,0 +1,264 @@
+use std::fs;
+use std::io;
+
+use thiserror::Error;
+
+use super::frame_rate::FrameRate;
+use super::time::Time;
+
+const V1_HEADER: &str = "# timecode format v1";
+const V2_HEADER: &str = "# timecode format v2";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Neither a frame rate nor a timecodes file: {0}")]
+    Io(#[from] io::Error),
+    #[error("Timecodes must start with \"{}\" or \"{}\"", V1_HEADER, V2_HEADER)]
+    UnknownFormat,
+    #[error("Line {0}: invalid timecode")]
+    InvalidLine(usize),
+    #[error("The timecodes have no frame")]
+    Empty,
+}
+
+/// Frame times of a variable frame rate video, read from Matroska timecodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timecodes {
+    // Start of each frame in milliseconds, increasing
+    starts: Vec<f64>,
+    // Frame rate after the last listed frame
+    rate: f64,
+}
+
+impl Timecodes {
+    /// Reads v1 timecodes, frame ranges with their frame rate, or v2 ones,
+    /// the start of every frame.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let timecodes = match lines.next() {
+            Some((_, header)) if header.eq_ignore_ascii_case(V1_HEADER) => Self::parse_v1(lines)?,
+            Some((_, header)) if header.eq_ignore_ascii_case(V2_HEADER) => Self::parse_v2(lines)?,
+            _ => return Err(Error::UnknownFormat),
+        };
+
+        if timecodes.starts.is_empty() {
+            return Err(Error::Empty);
+        }
+        Ok(timecodes)
+    }
+
+    // `Assume <rate>`, then `<first frame>,<last frame>,<rate>` ranges
+    fn parse_v1<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> Result<Self, Error> {
+        let mut assumed = None;
+        let mut ranges = Vec::new();
+        for (number, line) in lines.filter(|(_, line)| !line.starts_with('#')) {
+            let invalid = || Error::InvalidLine(number);
+            if assumed.is_none() {
+                let rate = line
+                    .get(..6)
+                    .filter(|assume| assume.eq_ignore_ascii_case("assume"))
+                    .and_then(|_| line[6..].trim().parse::<f64>().ok())
+                    .filter(|rate| *rate > 0.0)
+                    .ok_or_else(invalid)?;
+                assumed = Some(rate);
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (first, last, rate) = match fields.as_slice() {
+                [first, last, rate] => (
+                    first.parse::<usize>().map_err(|_| invalid())?,
+                    last.parse::<usize>().map_err(|_| invalid())?,
+                    rate.parse::<f64>().map_err(|_| invalid())?,
+                ),
+                _ => return Err(invalid()),
+            };
+            if last < first || rate <= 0.0 {
+                return Err(invalid());
+            }
+            ranges.push((first, last, rate));
+        }
+
+        let assumed = assumed.ok_or(Error::Empty)?;
+        ranges.sort_by_key(|&(first, _, _)| first);
+        let frame_count = ranges
+            .iter()
+            .map(|&(_, last, _)| last + 1)
+            .max()
+            .unwrap_or(1);
+
+        // Frames outside of the ranges have the assumed rate
+        let mut durations = vec![1000.0 / assumed; frame_count];
+        for (first, last, rate) in ranges {
+            durations[first..=last]
+                .iter_mut()
+                .for_each(|duration| *duration = 1000.0 / rate);
+        }
+        // Listing the frame after the ranges too, as later frames follow it
+        let mut starts = vec![0.0];
+        for duration in durations {
+            let last = starts[starts.len() - 1];
+            starts.push(last + duration);
+        }
+
+        Ok(Self {
+            starts,
+            rate: assumed,
+        })
+    }
+
+    // The start of each frame, one per line
+    fn parse_v2<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> Result<Self, Error> {
+        let mut starts: Vec<f64> = Vec::new();
+        for (number, line) in lines.filter(|(_, line)| !line.starts_with('#')) {
+            let start = line
+                .parse::<f64>()
+                .map_err(|_| Error::InvalidLine(number))?;
+            if starts.last().map_or(false, |&last| start <= last) {
+                return Err(Error::InvalidLine(number));
+            }
+            starts.push(start);
+        }
+
+        // Frames after the last one last as long as it does
+        let rate = match starts.as_slice() {
+            [.., before, last] => 1000.0 / (last - before),
+            _ => 1000.0 / 24.0,
+        };
+        Ok(Self { starts, rate })
+    }
+
+    // Start of `frame` in milliseconds, extrapolated past the listed frames
+    fn start(&self, frame: u64) -> f64 {
+        let last = self.starts.len() - 1;
+        match self.starts.get(frame as usize) {
+            Some(&start) => start,
+            None => self.starts[last] + (frame - last as u64) as f64 * 1000.0 / self.rate,
+        }
+    }
+
+    /// First frame shown at or after `time`.
+    pub fn frame_at(&self, time: Time) -> u64 {
+        // Frame starts are rounded down to centiseconds in lines
+        let millis = time.as_millis() as f64 - 1e-6;
+        let frame = self.starts.partition_point(|&start| start < millis);
+        if frame < self.starts.len() {
+            return frame as u64;
+        }
+
+        let last = self.starts.len() - 1;
+        let after = (millis - self.starts[last]) * self.rate / 1000.0;
+        last as u64 + after.ceil().max(0.0) as u64
+    }
+
+    /// Time of the centisecond starting `frame`, the one lines use.
+    pub fn time_of(&self, frame: u64) -> Time {
+        let centiseconds = (self.start(frame) / 10.0).floor().max(0.0) as u64;
+        Time::from_millis(centiseconds * 10)
+    }
+}
+
+/// Frame times of a video, at a constant frame rate or from timecodes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timebase {
+    Constant(FrameRate),
+    Variable(Timecodes),
+}
+
+impl Timebase {
+    /// A frame rate, as 23.976 or 24000/1001, or the path of a timecodes
+    /// file.
+    pub fn read(input: &str) -> Result<Self, Error> {
+        match input.parse::<FrameRate>() {
+            Ok(rate) => Ok(Timebase::Constant(rate)),
+            Err(_) => Ok(Timebase::Variable(Timecodes::parse(&fs::read_to_string(
+                input.trim(),
+            )?)?)),
+        }
+    }
+
+    pub fn frame_at(&self, time: Time) -> u64 {
+        match self {
+            Timebase::Constant(rate) => rate.frame_at(time),
+            Timebase::Variable(timecodes) => timecodes.frame_at(time),
+        }
+    }
+
+    pub fn time_of(&self, frame: u64) -> Time {
+        match self {
+            Timebase::Constant(rate) => rate.time_of(frame),
+            Timebase::Variable(timecodes) => timecodes.time_of(frame),
+        }
+    }
+
+    /// `time` moved to where its frame is with the `to` frame times, for
+    /// video sped up or slowed down without dropping frames.
+    pub fn convert(&self, time: Time, to: &Timebase) -> Time {
+        to.time_of(self.frame_at(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_ranges_override_the_assumed_rate() {
+        let text = "# timecode format v1\nAssume 25\n# Faster\n2,3,50\n";
+        let timecodes = Timecodes::parse(text).unwrap();
+        assert_eq!(timecodes.starts, vec![0.0, 40.0, 80.0, 100.0, 120.0]);
+        assert_eq!(timecodes.rate, 25.0);
+        assert_eq!(timecodes.time_of(3), Time::from_millis(100));
+        // Later frames follow the frame after the ranges at the assumed rate
+        assert_eq!(timecodes.time_of(6), Time::from_millis(200));
+        assert_eq!(timecodes.frame_at(Time::from_millis(100)), 3);
+        assert_eq!(timecodes.frame_at(Time::from_millis(200)), 6);
+    }
+
+    #[test]
+    fn v2_lists_every_frame_start() {
+        let text = "# timecode format v2\n0\n40\n80\n";
+        let timecodes = Timecodes::parse(text).unwrap();
+        assert_eq!(timecodes.starts, vec![0.0, 40.0, 80.0]);
+        assert_eq!(timecodes.rate, 25.0);
+        assert_eq!(timecodes.frame_at(Time::from_millis(40)), 1);
+        assert_eq!(timecodes.frame_at(Time::from_millis(50)), 2);
+        assert_eq!(timecodes.frame_at(Time::from_millis(100)), 3);
+        assert_eq!(timecodes.time_of(4), Time::from_millis(160));
+    }
+
+    #[test]
+    fn frame_starts_are_rounded_down_to_centiseconds() {
+        let timecodes = Timecodes::parse("# timecode format v2\n0\n41.708\n").unwrap();
+        assert_eq!(timecodes.time_of(1), Time::from_millis(40));
+        assert_eq!(timecodes.frame_at(Time::from_millis(40)), 1);
+    }
+
+    #[test]
+    fn invalid_timecodes_are_rejected() {
+        assert!(matches!(
+            Timecodes::parse("0\n40\n"),
+            Err(Error::UnknownFormat)
+        ));
+        assert!(matches!(
+            Timecodes::parse("# timecode format v2\n0\n40\n40\n"),
+            Err(Error::InvalidLine(4))
+        ));
+        assert!(matches!(
+            Timecodes::parse("# timecode format v1\n25\n"),
+            Err(Error::InvalidLine(2))
+        ));
+        assert!(matches!(
+            Timecodes::parse("# timecode format v1\nAssume 25\n3,2,50\n"),
+            Err(Error::InvalidLine(3))
+        ));
+        assert!(matches!(
+            Timecodes::parse("# timecode format v2\n"),
+            Err(Error::Empty)
+        ));
+    }
+}
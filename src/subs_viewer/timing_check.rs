@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use iced::{Align, Element, Row, Text};
+
+use iced_aw::number_input::{self, NumberInput};
+
+use super::time::Time;
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+
+// Gaps shorter than this are flickers on screen
+const DEFAULT_MIN_GAP: u64 = 100;
+
+/// A timing problem of a line. Lines are referred by their index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingIssue {
+    ZeroDuration,
+    EndsBeforeStart,
+    Overlap(usize),
+    ShortGap(usize, Time),
+}
+
+impl fmt::Display for TimingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingIssue::ZeroDuration => write!(f, "The line has no duration"),
+            TimingIssue::EndsBeforeStart => write!(f, "The line ends before it starts"),
+            TimingIssue::Overlap(row) => write!(f, "Overlaps line {}", row + 1),
+            TimingIssue::ShortGap(row, gap) => {
+                write!(f, "Only {} ms away from line {}", gap.as_millis(), row + 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingSettings {
+    pub min_gap: Time,
+}
+
+impl Default for TimingSettings {
+    fn default() -> Self {
+        Self {
+            min_gap: Time::from_millis(DEFAULT_MIN_GAP),
+        }
+    }
+}
+
+impl TimingSettings {
+    /// Issues of every line, indexed as `lines`. Comments are not checked and
+    /// lines only conflict with lines of the same layer and style.
+    pub fn check(&self, lines: &[SubsControlsValues]) -> Vec<Vec<TimingIssue>> {
+        let mut issues = vec![Vec::new(); lines.len()];
+        let mut groups: HashMap<(usize, String), Vec<(usize, Time, Time)>> = HashMap::new();
+
+        for (row, line) in lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.is_comment)
+        {
+            let (start, end) = match (line.start(), line.end()) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+
+            if end < start {
+                issues[row].push(TimingIssue::EndsBeforeStart);
+            } else if end == start {
+                issues[row].push(TimingIssue::ZeroDuration);
+            } else {
+                groups
+                    .entry((line.layer, line.style.clone()))
+                    .or_default()
+                    .push((row, start, end));
+            }
+        }
+
+        for group in groups.values_mut() {
+            group.sort_by_key(|&(row, start, _)| (start, row));
+            self.check_group(group, &mut issues);
+        }
+
+        issues
+    }
+
+    // `group` is sorted by start time
+    fn check_group(&self, group: &[(usize, Time, Time)], issues: &mut [Vec<TimingIssue>]) {
+        // Already visited lines still shown when the current one starts
+        let mut active: Vec<(usize, Time)> = Vec::new();
+        // Line ending last among the already visited ones
+        let mut last: Option<(usize, Time)> = None;
+
+        for &(row, start, end) in group {
+            active.retain(|&(_, active_end)| start < active_end);
+            for &(active_row, _) in &active {
+                issues[row].push(TimingIssue::Overlap(active_row));
+                issues[active_row].push(TimingIssue::Overlap(row));
+            }
+            // Gaps only matter between lines that do not overlap
+            if let (true, Some((last_row, last_end))) = (active.is_empty(), last) {
+                let gap = start.saturating_sub(last_end);
+                if gap > Time::ZERO && gap < self.min_gap {
+                    issues[row].push(TimingIssue::ShortGap(last_row, gap));
+                    issues[last_row].push(TimingIssue::ShortGap(row, gap));
+                }
+            }
+
+            active.push((row, end));
+            if last.map_or(true, |(_, last_end)| end > last_end) {
+                last = Some((row, end));
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TimingControls {
+    min_gap: number_input::State,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    MinGapPicked(u64),
+}
+
+impl TimingControls {
+    pub fn update(&mut self, message: Message, settings: &mut TimingSettings) {
+        match message {
+            Message::MinGapPicked(min_gap) => settings.min_gap = Time::from_millis(min_gap),
+        }
+    }
+
+    pub fn view(&mut self, settings: &TimingSettings) -> Element<Message> {
+        Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(Text::new("Minimum gap (ms)").size(TEXT_SIZE))
+            .push(
+                NumberInput::new(
+                    &mut self.min_gap,
+                    settings.min_gap.as_millis(),
+                    5000,
+                    Message::MinGapPicked,
+                )
+                .step(10)
+                .min(0),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+
+use iced::{
+    button, pick_list, scrollable, text_input, Align, Button, Checkbox, Column, Element, Length,
+    PickList, Row, Scrollable, Text, TextInput,
+};
+
+use iced_aw::number_input::{self, NumberInput};
+
+use super::keyframes::{self, Keyframes};
+use super::time::Time;
+use super::timecodes::Timebase;
+use super::timing_tools::{self, Change, Scope};
+use super::SubsControlsValues;
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+const FRAME_RATE_WIDTH: u16 = 200;
+const MAX_MILLIS: u64 = 5000;
+const MILLIS_STEP: u64 = 10;
+const MAX_FRAMES: u64 = 100;
+
+// Lines from a time are only shifted
+static SCOPES: &[Scope] = &[Scope::All, Scope::Selection];
+
+/// Steps of the processor, applied in this order. Times are in
+/// milliseconds, thresholds in frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Settings {
+    adds_lead: bool,
+    lead_in: u64,
+    lead_out: u64,
+    /// Joins lines of the same layer and style at most `max_gap` apart, or
+    /// overlapping by at most as much, in the middle.
+    chains: bool,
+    max_gap: u64,
+    snaps: bool,
+    starts_before: u64,
+    starts_after: u64,
+    ends_before: u64,
+    ends_after: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            adds_lead: true,
+            lead_in: 120,
+            lead_out: 250,
+            chains: true,
+            max_gap: 500,
+            snaps: true,
+            starts_before: 5,
+            starts_after: 4,
+            ends_before: 5,
+            ends_after: 8,
+        }
+    }
+}
+
+impl Settings {
+    /// Changes to the lines of `rows`, by row. Comments and lines without a
+    /// duration are left as they are, and lines are only snapped with
+    /// `keyframes`.
+    fn changes(
+        &self,
+        lines: &[SubsControlsValues],
+        rows: Range<usize>,
+        keyframes: Option<(&Keyframes, &Timebase)>,
+    ) -> Vec<Change> {
+        let lead_in = Time::from_millis(self.lead_in);
+        let lead_out = Time::from_millis(self.lead_out);
+        let mut groups: HashMap<(usize, &str), Vec<(usize, Time, Time)>> = HashMap::new();
+
+        for row in rows {
+            let line = &lines[row];
+            let (start, end) = match (line.start(), line.end()) {
+                (Some(start), Some(end)) if start < end && !line.is_comment => (start, end),
+                _ => continue,
+            };
+            let times = if self.adds_lead {
+                (start.saturating_sub(lead_in), end.saturating_add(lead_out))
+            } else {
+                (start, end)
+            };
+            groups
+                .entry((line.layer, line.style.as_str()))
+                .or_default()
+                .push((row, times.0, times.1));
+        }
+
+        let mut changes = Vec::new();
+        for group in groups.values_mut() {
+            group.sort_by_key(|&(row, start, _)| (start, row));
+            if self.chains {
+                self.chain(group);
+            }
+
+            for &(row, start, end) in group.iter() {
+                let (new_start, new_end) = match keyframes {
+                    Some((keyframes, timebase)) if self.snaps => {
+                        self.snapped(start, end, keyframes, timebase)
+                    }
+                    _ => (start, end),
+                };
+                let line = &lines[row];
+                let (start, end) = (
+                    line.start().unwrap_or_default(),
+                    line.end().unwrap_or_default(),
+                );
+                if (new_start, new_end) != (start, end) {
+                    changes.push(Change {
+                        row,
+                        start,
+                        end,
+                        new_start,
+                        new_end,
+                        reference: None,
+                    });
+                }
+            }
+        }
+
+        changes.sort_by_key(|change| change.row);
+        changes
+    }
+
+    // `group` is sorted by start time
+    fn chain(&self, group: &mut [(usize, Time, Time)]) {
+        let max_gap = self.max_gap as i64;
+        for index in 1..group.len() {
+            let (_, start, end) = group[index - 1];
+            let (_, next_start, next_end) = group[index];
+            // Lines starting together or containing the next one do not follow
+            // each other
+            if next_start <= start || next_end <= end {
+                continue;
+            }
+
+            let gap = next_start.diff(end);
+            if gap != 0 && gap.abs() <= max_gap {
+                let middle = end.offset(gap / 2);
+                group[index - 1].2 = middle;
+                group[index].1 = middle;
+            }
+        }
+    }
+
+    // Moves the start and the end to their nearest keyframe within the
+    // thresholds, unless the line would have no duration left
+    fn snapped(
+        &self,
+        start: Time,
+        end: Time,
+        keyframes: &Keyframes,
+        timebase: &Timebase,
+    ) -> (Time, Time) {
+        let snap = |time: Time, before: u64, after: u64| {
+            keyframes
+                .nearest(timebase.frame_at(time), before, after)
+                .map_or(time, |keyframe| timebase.time_of(keyframe))
+        };
+        let new_start = snap(start, self.starts_before, self.starts_after);
+        let new_end = snap(end, self.ends_before, self.ends_after);
+
+        if new_start < new_end {
+            (new_start, new_end)
+        } else {
+            (start, end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `lines` replace the lines of `rows`.
+    Processed {
+        name: String,
+        rows: Range<usize>,
+        lines: Vec<SubsControlsValues>,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    LeadToggled(bool),
+    LeadInPicked(u64),
+    LeadOutPicked(u64),
+    ChainToggled(bool),
+    MaxGapPicked(u64),
+    PathChanged(String),
+    Load,
+    FrameRateChanged(String),
+    SnapToggled(bool),
+    StartsBeforePicked(u64),
+    StartsAfterPicked(u64),
+    EndsBeforePicked(u64),
+    EndsAfterPicked(u64),
+    ScopePicked(Scope),
+    Preview,
+    Apply,
+    Close,
+}
+
+/// Adds lead-in and lead-out to lines, chains them and snaps them to
+/// keyframes, showing the changes before applying them.
+#[derive(Default)]
+pub struct TimingProcessor {
+    lead_in: number_input::State,
+    lead_out: number_input::State,
+    max_gap: number_input::State,
+    path: text_input::State,
+    load: button::State,
+    frame_rate: text_input::State,
+    starts_before: number_input::State,
+    starts_after: number_input::State,
+    ends_before: number_input::State,
+    ends_after: number_input::State,
+    scope: pick_list::State<Scope>,
+    preview_button: button::State,
+    apply: button::State,
+    close: button::State,
+    preview_scroll: scrollable::State,
+    settings: Settings,
+    path_input: String,
+    frame_rate_input: String,
+    keyframes: Option<Keyframes>,
+    scope_value: Scope,
+    // Changes of the last preview, cleared when the settings change
+    preview: Vec<Change>,
+    // Outcome of the last operation
+    status: String,
+}
+
+impl TimingProcessor {
+    // Changes with the current settings, snapping once keyframes are loaded
+    fn changes(
+        &self,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+    ) -> Result<Vec<Change>, String> {
+        let rows = self.scope_value.rows(lines.len(), selection);
+        match &self.keyframes {
+            Some(keyframes) if self.settings.snaps => {
+                let timebase =
+                    Timebase::read(&self.frame_rate_input).map_err(|error| error.to_string())?;
+                Ok(self
+                    .settings
+                    .changes(lines, rows, Some((keyframes, &timebase))))
+            }
+            _ => Ok(self.settings.changes(lines, rows, None)),
+        }
+    }
+
+    fn load(&mut self) {
+        let keyframes = fs::read_to_string(self.path_input.trim())
+            .map_err(keyframes::Error::from)
+            .and_then(|text| text.parse::<Keyframes>());
+        self.status = match keyframes {
+            Ok(keyframes) => {
+                timing_tools::prefill_frame_rate(&mut self.frame_rate_input, keyframes.frame_rate);
+                let status = format!("{} keyframes", keyframes.count());
+                self.keyframes = Some(keyframes);
+                status
+            }
+            Err(error) => error.to_string(),
+        };
+    }
+
+    /// Processes `lines`, of which `selection` is selected.
+    pub fn update(
+        &mut self,
+        message: Message,
+        lines: &[SubsControlsValues],
+        selection: &Range<usize>,
+    ) -> Option<Event> {
+        // A preview only holds for the settings it was made with
+        if !matches!(message, Message::Preview | Message::Close) {
+            self.preview.clear();
+        }
+
+        match message {
+            Message::LeadToggled(adds_lead) => self.settings.adds_lead = adds_lead,
+            Message::LeadInPicked(lead_in) => self.settings.lead_in = lead_in,
+            Message::LeadOutPicked(lead_out) => self.settings.lead_out = lead_out,
+            Message::ChainToggled(chains) => self.settings.chains = chains,
+            Message::MaxGapPicked(max_gap) => self.settings.max_gap = max_gap,
+            Message::PathChanged(path) => self.path_input = path,
+            Message::Load => self.load(),
+            Message::FrameRateChanged(frame_rate) => self.frame_rate_input = frame_rate,
+            Message::SnapToggled(snaps) => self.settings.snaps = snaps,
+            Message::StartsBeforePicked(frames) => self.settings.starts_before = frames,
+            Message::StartsAfterPicked(frames) => self.settings.starts_after = frames,
+            Message::EndsBeforePicked(frames) => self.settings.ends_before = frames,
+            Message::EndsAfterPicked(frames) => self.settings.ends_after = frames,
+            Message::ScopePicked(scope) => self.scope_value = scope,
+            Message::Preview => match self.changes(lines, selection) {
+                Ok(changes) => {
+                    self.status = format!("{} lines would change", changes.len());
+                    self.preview = changes;
+                }
+                Err(error) => self.status = error,
+            },
+            Message::Apply => match self.changes(lines, selection) {
+                Ok(changes) if changes.is_empty() => self.status = "No line to change".to_owned(),
+                Ok(changes) => {
+                    let rows = self.scope_value.rows(lines.len(), selection);
+                    let mut processed = lines[rows.clone()].to_vec();
+                    for change in &changes {
+                        processed[change.row - rows.start]
+                            .set_times(change.new_start, change.new_end);
+                    }
+                    self.status = format!("Changed {} lines", changes.len());
+                    return Some(Event::Processed {
+                        name: format!("Process the timing of {} lines", changes.len()),
+                        rows,
+                        lines: processed,
+                    });
+                }
+                Err(error) => self.status = error,
+            },
+            Message::Close => return Some(Event::Closed),
+        }
+        None
+    }
+
+    fn number_input(
+        state: &mut number_input::State,
+        value: u64,
+        max: u64,
+        step: u64,
+        on_change: fn(u64) -> Message,
+    ) -> Element<Message> {
+        NumberInput::new(state, value, max, on_change)
+            .step(step)
+            .min(0)
+            .into()
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let settings = self.settings;
+        let lead = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Checkbox::new(settings.adds_lead, "Lead-in (ms)", Message::LeadToggled)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(Self::number_input(
+                &mut self.lead_in,
+                settings.lead_in,
+                MAX_MILLIS,
+                MILLIS_STEP,
+                Message::LeadInPicked,
+            ))
+            .push(Text::new("lead-out (ms)").size(TEXT_SIZE))
+            .push(Self::number_input(
+                &mut self.lead_out,
+                settings.lead_out,
+                MAX_MILLIS,
+                MILLIS_STEP,
+                Message::LeadOutPicked,
+            ));
+
+        let chain = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Checkbox::new(
+                    settings.chains,
+                    "Chain lines of the same style up to (ms) apart",
+                    Message::ChainToggled,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(Self::number_input(
+                &mut self.max_gap,
+                settings.max_gap,
+                MAX_MILLIS,
+                MILLIS_STEP,
+                Message::MaxGapPicked,
+            ));
+
+        let file = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut self.path,
+                    "Keyframes file path",
+                    &self.path_input,
+                    Message::PathChanged,
+                )
+                .on_submit(Message::Load)
+                .size(TEXT_SIZE),
+            )
+            .push(
+                Button::new(&mut self.load, Text::new("Load").size(TEXT_SIZE))
+                    .on_press(Message::Load),
+            )
+            .push(Text::new("Frame rate").size(TEXT_SIZE))
+            .push(
+                TextInput::new(
+                    &mut self.frame_rate,
+                    "23.976 or timecodes file",
+                    &self.frame_rate_input,
+                    Message::FrameRateChanged,
+                )
+                .width(Length::Units(FRAME_RATE_WIDTH))
+                .size(TEXT_SIZE),
+            );
+
+        let snap = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Checkbox::new(settings.snaps, "Snap starts up to", Message::SnapToggled)
+                    .text_size(TEXT_SIZE),
+            )
+            .push(Self::number_input(
+                &mut self.starts_before,
+                settings.starts_before,
+                MAX_FRAMES,
+                1,
+                Message::StartsBeforePicked,
+            ))
+            .push(Text::new("frames before and").size(TEXT_SIZE))
+            .push(Self::number_input(
+                &mut self.starts_after,
+                settings.starts_after,
+                MAX_FRAMES,
+                1,
+                Message::StartsAfterPicked,
+            ))
+            .push(Text::new("after a keyframe, ends up to").size(TEXT_SIZE))
+            .push(Self::number_input(
+                &mut self.ends_before,
+                settings.ends_before,
+                MAX_FRAMES,
+                1,
+                Message::EndsBeforePicked,
+            ))
+            .push(Text::new("before and").size(TEXT_SIZE))
+            .push(Self::number_input(
+                &mut self.ends_after,
+                settings.ends_after,
+                MAX_FRAMES,
+                1,
+                Message::EndsAfterPicked,
+            ))
+            .push(Text::new("after").size(TEXT_SIZE));
+
+        let actions = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.scope,
+                    SCOPES,
+                    Some(self.scope_value),
+                    Message::ScopePicked,
+                )
+                .text_size(TEXT_SIZE),
+            )
+            .push(
+                Button::new(
+                    &mut self.preview_button,
+                    Text::new("Preview").size(TEXT_SIZE),
+                )
+                .on_press(Message::Preview),
+            )
+            .push(
+                Button::new(&mut self.apply, Text::new("Apply").size(TEXT_SIZE))
+                    .on_press(Message::Apply),
+            )
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(&self.status).size(TEXT_SIZE));
+
+        let preview = self.preview.iter().fold(
+            Scrollable::new(&mut self.preview_scroll)
+                .spacing(COLUMN_SPACING)
+                .height(Length::Fill),
+            |preview, change| preview.push(Text::new(change.to_string()).size(TEXT_SIZE)),
+        );
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(Text::new("Process the timing of lines, in this order").size(TEXT_SIZE))
+            .push(lead)
+            .push(chain)
+            .push(file)
+            .push(snap)
+            .push(actions)
+            .push(preview)
+            .into()
+    }
+}
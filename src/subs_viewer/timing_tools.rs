@@ -0,0 +1,87 @@
+use std::fmt;
+use std::ops::Range;
+
+use super::frame_rate::FrameRate;
+use super::time::Time;
+
+/// Lines a timing tool applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    All,
+    Selection,
+    /// Lines starting at or after a time.
+    After,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl From<Scope> for &'static str {
+    fn from(val: Scope) -> Self {
+        match val {
+            Scope::All => "All lines",
+            Scope::Selection => "Selected lines",
+            Scope::After => "Lines from",
+        }
+    }
+}
+
+pub static ALL_SCOPES: &[Scope] = &[Scope::All, Scope::Selection, Scope::After];
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+impl Scope {
+    /// Rows the tool may change among `len` lines. Lines from a time are
+    /// picked among all of them by the tool.
+    pub fn rows(self, len: usize, selection: &Range<usize>) -> Range<usize> {
+        match self {
+            Scope::Selection => selection.clone(),
+            Scope::All | Scope::After => 0..len,
+        }
+    }
+}
+
+/// New times of a line, and the reference line it matched, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub row: usize,
+    pub start: Time,
+    pub end: Time,
+    pub new_start: Time,
+    pub new_end: Time,
+    pub reference: Option<(usize, String)>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Line {}: {} - {} → {} - {}",
+            self.row + 1,
+            self.start,
+            self.end,
+            self.new_start,
+            self.new_end
+        )?;
+        match &self.reference {
+            Some((row, text)) => write!(f, ", matches reference line {}: {}", row + 1, text),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fills the frame rate field with the one of a loaded file, a typed frame
+/// rate wins.
+pub fn prefill_frame_rate(input: &mut String, frame_rate: Option<FrameRate>) {
+    if let (true, Some(frame_rate)) = (input.is_empty(), frame_rate) {
+        *input = frame_rate.to_string();
+    }
+}
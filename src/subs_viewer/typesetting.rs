@@ -0,0 +1,760 @@
+use std::fmt;
+use std::ops::Range;
+
+use iced::{
+    button, pick_list, Align, Button, Column, Element, Image, Length, PickList, Point, Row, Text,
+};
+
+use super::drawing::{self, Drawing, DrawingEditor, Segment};
+use super::formatting;
+use super::handles::{self, Drag, Handles, Shape};
+use super::preview::PreviewFrame;
+use super::styles::AssStyle;
+use super::tags::{ClipShape, Item, Line, NodeKind, Tag, TagKind};
+
+const ROW_SPACING: u16 = 6;
+const COLUMN_SPACING: u16 = 6;
+const TEXT_SIZE: u16 = 16;
+
+// Distance in script pixels under which a handle is grabbed
+pub const GRAB_DISTANCE: f32 = 8.0;
+// Radius of the rotation guide, in script pixels
+const ROTATION_RADIUS: f32 = 40.0;
+// Degrees of `\frx` and `\fry` per dragged pixel
+const DEGREES_PER_PIXEL: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Position,
+    Move,
+    RotateZ,
+    RotateXY,
+    Scale,
+    Clip,
+    VectorClip,
+}
+
+impl From<Tool> for &'static str {
+    fn from(val: Tool) -> Self {
+        match val {
+            Tool::Position => "\\pos",
+            Tool::Move => "\\move",
+            Tool::RotateZ => "\\frz",
+            Tool::RotateXY => "\\frx \\fry",
+            Tool::Scale => "\\fscx \\fscy",
+            Tool::Clip => "\\clip",
+            Tool::VectorClip => "Vector \\clip",
+        }
+    }
+}
+
+pub static ALL_TOOLS: &[Tool] = &[
+    Tool::Position,
+    Tool::Move,
+    Tool::RotateZ,
+    Tool::RotateXY,
+    Tool::Scale,
+    Tool::Clip,
+    Tool::VectorClip,
+];
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let val: &str = (*self).into();
+        write!(f, "{}", val)
+    }
+}
+
+impl Tool {
+    fn hint(self) -> &'static str {
+        match self {
+            Tool::Position => "Drag to place the line",
+            Tool::Move => "Drag the start or the end of the movement",
+            Tool::RotateZ => "Drag around the origin to rotate",
+            Tool::RotateXY => "Drag to tilt the line",
+            Tool::Scale => "Drag right and up to enlarge",
+            Tool::Clip => "Drag a rectangle, or its corners",
+            Tool::VectorClip => "Click to add points, drag them to move them",
+        }
+    }
+
+    // Tags set for the whole line, wherever they are
+    fn is_line_wide(self) -> bool {
+        matches!(
+            self,
+            Tool::Position | Tool::Move | Tool::Clip | Tool::VectorClip
+        )
+    }
+
+    // Whether the tool writes over `kind`
+    fn replaces(self, kind: &TagKind<'_>) -> bool {
+        match self {
+            Tool::Position | Tool::Move => {
+                matches!(kind, TagKind::Position(..) | TagKind::Move { .. })
+            }
+            Tool::RotateZ => matches!(kind, TagKind::RotationZ(_)),
+            Tool::RotateXY => matches!(kind, TagKind::RotationX(_) | TagKind::RotationY(_)),
+            Tool::Scale => matches!(kind, TagKind::ScaleX(_) | TagKind::ScaleY(_)),
+            Tool::Clip | Tool::VectorClip => matches!(kind, TagKind::Clip { .. }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clip {
+    Rectangle {
+        inverse: bool,
+        from: Point,
+        to: Point,
+    },
+    /// Commands in script pixels.
+    Vector { inverse: bool, drawing: Drawing },
+}
+
+impl Clip {
+    fn is_inverse(&self) -> bool {
+        match self {
+            Clip::Rectangle { inverse, .. } | Clip::Vector { inverse, .. } => *inverse,
+        }
+    }
+
+    fn from_shape(inverse: bool, shape: &ClipShape<'_>) -> Self {
+        match shape {
+            ClipShape::Rectangle(left, top, right, bottom) => Clip::Rectangle {
+                inverse,
+                from: Point::new(*left, *top),
+                to: Point::new(*right, *bottom),
+            },
+            ClipShape::Drawing { scale, commands } => {
+                let factor = 2f32.powi((*scale).max(1) as i32 - 1);
+                let mut drawing = Drawing::parse(commands);
+                drawing.map_points(|point| Point::new(point.x / factor, point.y / factor));
+                Clip::Vector { inverse, drawing }
+            }
+        }
+    }
+}
+
+/// Placement of a line in script pixels, read from its tags. Rotations and
+/// scales are read from the first override block, where they are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    /// `\pos`, the start of `\move` or where the style puts the line.
+    pub position: Point,
+    /// End and times of `\move`.
+    pub movement: Option<(Point, Option<(i32, i32)>)>,
+    pub origin: Option<Point>,
+    pub rotation_x: f32,
+    pub rotation_y: f32,
+    pub rotation_z: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub clip: Option<Clip>,
+}
+
+impl Placement {
+    /// Reads the placement of `text` in a script of `resolution`.
+    pub fn read(text: &str, style: &AssStyle, resolution: (u32, u32)) -> Self {
+        let mut placement = Self {
+            position: anchor(text, style, resolution),
+            movement: None,
+            origin: None,
+            rotation_x: 0.0,
+            rotation_y: 0.0,
+            rotation_z: style.angle,
+            scale_x: style.scale_x,
+            scale_y: style.scale_y,
+            clip: None,
+        };
+        let mut is_positioned = false;
+
+        let line = Line::parse(text);
+        for (is_first, tag) in line_tags(&line) {
+            match &tag.kind {
+                // The first of these tags wins
+                TagKind::Position(x, y) if !is_positioned => {
+                    placement.position = Point::new(*x, *y);
+                    is_positioned = true;
+                }
+                TagKind::Move { from, to, times } if !is_positioned => {
+                    placement.position = Point::new(from.0, from.1);
+                    placement.movement = Some((Point::new(to.0, to.1), *times));
+                    is_positioned = true;
+                }
+                TagKind::Origin(x, y) if placement.origin.is_none() => {
+                    placement.origin = Some(Point::new(*x, *y))
+                }
+                TagKind::Clip { inverse, shape } if placement.clip.is_none() => {
+                    placement.clip = Some(Clip::from_shape(*inverse, shape))
+                }
+                TagKind::RotationX(angle) if is_first => placement.rotation_x = *angle,
+                TagKind::RotationY(angle) if is_first => placement.rotation_y = *angle,
+                TagKind::RotationZ(angle) if is_first => placement.rotation_z = *angle,
+                TagKind::ScaleX(scale) if is_first => placement.scale_x = *scale,
+                TagKind::ScaleY(scale) if is_first => placement.scale_y = *scale,
+                _ => {}
+            }
+        }
+
+        placement
+    }
+
+    // Center of the rotations
+    fn center(&self) -> Point {
+        self.origin.unwrap_or(self.position)
+    }
+
+    /// Tags writing what `tool` edits.
+    pub fn tags(&self, tool: Tool) -> String {
+        match tool {
+            Tool::Position => format!(
+                "\\pos({},{})",
+                number(self.position.x),
+                number(self.position.y)
+            ),
+            Tool::Move => match self.movement {
+                Some((end, times)) => format!(
+                    "\\move({},{},{},{}{})",
+                    number(self.position.x),
+                    number(self.position.y),
+                    number(end.x),
+                    number(end.y),
+                    times.map_or_else(String::new, |(start, end)| format!(",{},{}", start, end))
+                ),
+                None => self.tags(Tool::Position),
+            },
+            Tool::RotateZ => format!("\\frz{}", number(self.rotation_z)),
+            Tool::RotateXY => format!(
+                "\\frx{}\\fry{}",
+                number(self.rotation_x),
+                number(self.rotation_y)
+            ),
+            Tool::Scale => format!(
+                "\\fscx{}\\fscy{}",
+                number(self.scale_x),
+                number(self.scale_y)
+            ),
+            Tool::Clip | Tool::VectorClip => {
+                let clip = match &self.clip {
+                    Some(clip) => clip,
+                    None => return String::new(),
+                };
+                let name = if clip.is_inverse() { "iclip" } else { "clip" };
+                match clip {
+                    Clip::Rectangle { from, to, .. } => format!(
+                        "\\{}({},{},{},{})",
+                        name,
+                        number(from.x.min(to.x)),
+                        number(from.y.min(to.y)),
+                        number(from.x.max(to.x)),
+                        number(from.y.max(to.y))
+                    ),
+                    Clip::Vector { drawing, .. } => format!("\\{}({})", name, drawing),
+                }
+            }
+        }
+    }
+
+    // Guides of `tool`, in fractions of the preview
+    fn shapes(&self, tool: Tool, resolution: (u32, u32)) -> Vec<Shape> {
+        let at = |point: Point| fraction(point, resolution);
+        let offset = |point: Point, x: f32, y: f32| at(Point::new(point.x + x, point.y + y));
+
+        match tool {
+            Tool::Position => vec![Shape::Handle(at(self.position))],
+            Tool::Move => match self.movement {
+                Some((end, _)) => vec![
+                    Shape::Line(at(self.position), at(end)),
+                    Shape::Handle(at(self.position)),
+                    Shape::Handle(at(end)),
+                ],
+                None => vec![Shape::Handle(at(self.position))],
+            },
+            Tool::RotateZ => {
+                let center = self.center();
+                let angle = self.rotation_z.to_radians();
+                let handle = offset(
+                    center,
+                    ROTATION_RADIUS * angle.cos(),
+                    -ROTATION_RADIUS * angle.sin(),
+                );
+                vec![
+                    Shape::Circle(at(center), ROTATION_RADIUS / resolution.0 as f32),
+                    Shape::Line(at(center), handle),
+                    Shape::Handle(at(center)),
+                    Shape::Handle(handle),
+                ]
+            }
+            Tool::RotateXY => {
+                // A cross foreshortened by the rotations
+                let center = self.center();
+                let width = ROTATION_RADIUS * self.rotation_y.to_radians().cos();
+                let height = ROTATION_RADIUS * self.rotation_x.to_radians().cos();
+                vec![
+                    Shape::Line(offset(center, -width, 0.0), offset(center, width, 0.0)),
+                    Shape::Line(offset(center, 0.0, -height), offset(center, 0.0, height)),
+                    Shape::Handle(at(center)),
+                ]
+            }
+            Tool::Scale => {
+                let x_handle = offset(self.position, self.scale_x / 2.0, 0.0);
+                let y_handle = offset(self.position, 0.0, -self.scale_y / 2.0);
+                vec![
+                    Shape::Line(at(self.position), x_handle),
+                    Shape::Line(at(self.position), y_handle),
+                    Shape::Handle(at(self.position)),
+                    Shape::Handle(x_handle),
+                    Shape::Handle(y_handle),
+                ]
+            }
+            Tool::Clip | Tool::VectorClip => match &self.clip {
+                Some(Clip::Rectangle { from, to, .. }) => vec![
+                    Shape::Polygon(vec![
+                        at(*from),
+                        at(Point::new(to.x, from.y)),
+                        at(*to),
+                        at(Point::new(from.x, to.y)),
+                    ]),
+                    Shape::Handle(at(*from)),
+                    Shape::Handle(at(*to)),
+                ],
+                // Curves show their control points
+                Some(Clip::Vector { drawing, .. }) => {
+                    let mut shapes = vec![Shape::Polygon(drawing.points().map(at).collect())];
+                    shapes.extend(drawing.points().map(|point| Shape::Handle(at(point))));
+                    shapes
+                }
+                None => Vec::new(),
+            },
+        }
+    }
+}
+
+// Where the alignment and the style margins put a line without `\pos`
+fn anchor(text: &str, style: &AssStyle, (width, height): (u32, u32)) -> Point {
+    let (width, height) = (width as f32, height as f32);
+    let alignment = formatting::line_alignment(text).unwrap_or(style.alignment);
+    let numpad = alignment.0.max(1) - 1;
+
+    let x = match numpad % 3 {
+        0 => style.margin_left as f32,
+        1 => width / 2.0,
+        _ => width - style.margin_right as f32,
+    };
+    let y = match numpad / 3 {
+        0 => height - style.margin_vertical as f32,
+        1 => height / 2.0,
+        _ => style.margin_vertical as f32,
+    };
+    Point::new(x, y)
+}
+
+// Tags of the line, with whether they are in the block starting it
+fn line_tags<'a>(line: &'a Line<'a>) -> impl Iterator<Item = (bool, &'a Tag<'a>)> {
+    line.nodes
+        .iter()
+        .filter_map(|node| match &node.kind {
+            NodeKind::Block(items) => Some((node.span.start == 0, items)),
+            _ => None,
+        })
+        .flat_map(|(is_first, items)| {
+            items.iter().filter_map(move |item| match item {
+                Item::Tag(tag) => Some((is_first, tag)),
+                Item::Comment(_) => None,
+            })
+        })
+}
+
+/// Converts script pixels to fractions of the preview, the whole script of
+/// `resolution` being shown.
+pub fn fraction(point: Point, (width, height): (u32, u32)) -> Point {
+    Point::new(point.x / width as f32, point.y / height as f32)
+}
+
+/// Converts fractions of the preview to pixels of a script of `resolution`.
+pub fn script(point: Point, (width, height): (u32, u32)) -> Point {
+    Point::new(point.x * width as f32, point.y * height as f32)
+}
+
+/// Whether a handle at `point` is grabbed from `at`, in script pixels.
+pub fn is_near(point: Point, at: Point) -> bool {
+    let (x, y) = (point.x - at.x, point.y - at.y);
+    (x * x + y * y).sqrt() <= GRAB_DISTANCE
+}
+
+/// Formats a coordinate or an angle with two decimals at most.
+pub fn number(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // No "-0"
+    format!("{}", rounded + 0.0)
+}
+
+/// Replaces the tags of `tool` in `text` by `tags`, written in the block
+/// starting the line. Line-wide tags are removed from every block.
+pub fn write(text: &str, tool: Tool, tags: &str) -> String {
+    let line = Line::parse(text);
+    let mut removed: Vec<Range<usize>> = Vec::new();
+    // End of the first block once the tags are removed
+    let mut insert_at = None;
+
+    for node in &line.nodes {
+        let items = match &node.kind {
+            NodeKind::Block(items) => items,
+            _ => continue,
+        };
+        let is_first = node.span.start == 0;
+        if !is_first && !tool.is_line_wide() {
+            continue;
+        }
+
+        let replaced: Vec<&Range<usize>> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Tag(tag) if tool.replaces(&tag.kind) => Some(&tag.span),
+                _ => None,
+            })
+            .collect();
+
+        let is_emptied = replaced.len() == items.len();
+        if is_emptied && (!is_first || tags.is_empty()) {
+            removed.push(node.span.clone());
+            continue;
+        }
+
+        if is_first {
+            let length: usize = replaced.iter().map(|span| span.len()).sum();
+            insert_at = Some(node.span.end - 1 - length);
+        }
+        removed.extend(replaced.into_iter().cloned());
+    }
+
+    let mut text = text.to_owned();
+    removed.sort_by_key(|span| std::cmp::Reverse(span.start));
+    for span in removed {
+        text.replace_range(span, "");
+    }
+
+    match insert_at {
+        Some(at) => text.insert_str(at, tags),
+        None if !tags.is_empty() => text.insert_str(0, &format!("{{{}}}", tags)),
+        None => {}
+    }
+    text
+}
+
+// What a drag edits
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Position,
+    MoveStart,
+    MoveEnd,
+    Rotation,
+    Scale,
+    ClipCorner { is_end: bool },
+    ClipPoint(usize),
+}
+
+// A drag in progress, with the placement it started from
+#[derive(Debug, Clone)]
+struct Grab {
+    target: Target,
+    placement: Placement,
+    from: Point,
+}
+
+impl Grab {
+    fn new(tool: Tool, mut placement: Placement, at: Point) -> Self {
+        let is_near = |point: Point| is_near(point, at);
+        let inverse = placement.clip.as_ref().map_or(false, Clip::is_inverse);
+
+        let target = match tool {
+            Tool::Position => {
+                placement.movement = None;
+                Target::Position
+            }
+            Tool::Move => match placement.movement {
+                Some((end, _)) if is_near(end) => Target::MoveEnd,
+                Some(_) if is_near(placement.position) => Target::MoveStart,
+                // A new movement starts where the line is
+                movement => {
+                    placement.movement = Some((at, movement.and_then(|(_, times)| times)));
+                    Target::MoveEnd
+                }
+            },
+            Tool::RotateZ | Tool::RotateXY => Target::Rotation,
+            Tool::Scale => Target::Scale,
+            Tool::Clip => match &placement.clip {
+                Some(Clip::Rectangle { to, .. }) if is_near(*to) => {
+                    Target::ClipCorner { is_end: true }
+                }
+                Some(Clip::Rectangle { from, .. }) if is_near(*from) => {
+                    Target::ClipCorner { is_end: false }
+                }
+                _ => {
+                    placement.clip = Some(Clip::Rectangle {
+                        inverse,
+                        from: at,
+                        to: at,
+                    });
+                    Target::ClipCorner { is_end: true }
+                }
+            },
+            Tool::VectorClip => match &mut placement.clip {
+                Some(Clip::Vector { drawing, .. }) => {
+                    let grabbed = drawing.points().position(is_near);
+                    match grabbed {
+                        Some(index) => Target::ClipPoint(index),
+                        None => {
+                            drawing.push(Segment::Line, at, false);
+                            Target::ClipPoint(drawing.points().count() - 1)
+                        }
+                    }
+                }
+                _ => {
+                    let mut drawing = Drawing::default();
+                    drawing.push(Segment::Line, at, false);
+                    placement.clip = Some(Clip::Vector { inverse, drawing });
+                    Target::ClipPoint(0)
+                }
+            },
+        };
+
+        Self {
+            target,
+            placement,
+            from: at,
+        }
+    }
+
+    // Placement once dragged to `to`
+    fn dragged(&self, tool: Tool, to: Point) -> Placement {
+        let mut placement = self.placement.clone();
+        let (dx, dy) = (to.x - self.from.x, to.y - self.from.y);
+
+        match self.target {
+            Target::Position | Target::MoveStart => placement.position = to,
+            Target::MoveEnd => {
+                if let Some((end, _)) = &mut placement.movement {
+                    *end = to;
+                }
+            }
+            Target::Rotation if tool == Tool::RotateZ => {
+                // Angles go counterclockwise, the y axis goes down
+                let center = placement.center();
+                let angle = |point: Point| (center.y - point.y).atan2(point.x - center.x);
+                let turn = (angle(to) - angle(self.from)).to_degrees();
+                placement.rotation_z = (placement.rotation_z + turn).rem_euclid(360.0);
+            }
+            Target::Rotation => {
+                placement.rotation_x -= dy * DEGREES_PER_PIXEL;
+                placement.rotation_y += dx * DEGREES_PER_PIXEL;
+            }
+            Target::Scale => {
+                placement.scale_x = (placement.scale_x + dx).max(0.0);
+                placement.scale_y = (placement.scale_y - dy).max(0.0);
+            }
+            Target::ClipCorner { is_end } => {
+                if let Some(Clip::Rectangle { from, to: end, .. }) = &mut placement.clip {
+                    *if is_end { end } else { from } = to;
+                }
+            }
+            Target::ClipPoint(index) => {
+                if let Some(Clip::Vector { drawing, .. }) = &mut placement.clip {
+                    if let Some(point) = drawing.point_mut(index) {
+                        *point = to;
+                    }
+                }
+            }
+        }
+
+        placement
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The text of the line, with the name of the edit for the history.
+    Edited {
+        name: String,
+        text: String,
+    },
+    Closed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ToolPicked(Tool),
+    Drag(Drag),
+    ClearClip,
+    ToggleDrawing,
+    Drawing(drawing::Message),
+    Close,
+}
+
+/// Edits the placement tags or the drawing of the active line by dragging on
+/// its preview.
+#[derive(Default)]
+pub struct Typesetter {
+    tools: pick_list::State<Tool>,
+    clear_clip: button::State,
+    toggle_drawing: button::State,
+    close: button::State,
+    handles: handles::State,
+    tool: Option<Tool>,
+    grab: Option<Grab>,
+    drawing: DrawingEditor,
+    // Drags edit the drawing instead of the tags
+    is_drawing: bool,
+    frame: Option<PreviewFrame>,
+}
+
+impl Typesetter {
+    /// Shows `frame`, the preview of the active line.
+    pub fn set_frame(&mut self, frame: Option<PreviewFrame>) {
+        self.frame = frame;
+    }
+
+    fn tool(&self) -> Tool {
+        self.tool.unwrap_or(Tool::Position)
+    }
+
+    /// Edits `text`, the text of the active line of style `style`, in a
+    /// script of `resolution`.
+    pub fn update(
+        &mut self,
+        message: Message,
+        text: &str,
+        style: &AssStyle,
+        resolution: (u32, u32),
+    ) -> Option<Event> {
+        let tool = self.tool();
+        // Drags come as fractions of the preview
+        let edit = |placement: Placement| {
+            let text = write(text, tool, &placement.tags(tool));
+            let name = format!("Set {}", tool);
+            Some(Event::Edited { name, text })
+        };
+        let drawn = |text| Event::Edited {
+            name: "Edit drawing".to_owned(),
+            text,
+        };
+
+        match message {
+            Message::Drag(drag) if self.is_drawing => {
+                self.drawing.drag(drag, text, style, resolution).map(drawn)
+            }
+            Message::Drawing(message) => self.drawing.update(message, text).map(drawn),
+            Message::ToggleDrawing => {
+                self.is_drawing = !self.is_drawing;
+                self.grab = None;
+                None
+            }
+            Message::ToolPicked(tool) => {
+                self.tool = Some(tool);
+                self.grab = None;
+                None
+            }
+            Message::Drag(Drag::Pressed(at)) => {
+                let at = script(at, resolution);
+                let placement = Placement::read(text, style, resolution);
+                let grab = Grab::new(tool, placement, at);
+                let placement = grab.dragged(tool, at);
+                self.grab = Some(grab);
+                edit(placement)
+            }
+            Message::Drag(Drag::Moved(at)) => {
+                let at = script(at, resolution);
+                let placement = self.grab.as_ref()?.dragged(tool, at);
+                edit(placement)
+            }
+            Message::Drag(Drag::Released) => {
+                self.grab = None;
+                None
+            }
+            Message::ClearClip => Some(Event::Edited {
+                name: "Remove clip".to_owned(),
+                text: write(text, Tool::Clip, ""),
+            }),
+            Message::Close => Some(Event::Closed),
+        }
+    }
+
+    pub fn view(
+        &mut self,
+        text: &str,
+        style: &AssStyle,
+        resolution: (u32, u32),
+    ) -> Element<Message> {
+        let tool = self.tool();
+        let placement = Placement::read(text, style, resolution);
+
+        let (controls, shapes, hint): (Element<Message>, _, _) = if self.is_drawing {
+            let shapes = self.drawing.shapes(text, style, resolution);
+            (
+                self.drawing.controls(text).map(Message::Drawing),
+                shapes,
+                DrawingEditor::hint(text),
+            )
+        } else {
+            let mut clear_clip = Button::new(
+                &mut self.clear_clip,
+                Text::new("Remove clip").size(TEXT_SIZE),
+            );
+            if placement.clip.is_some() {
+                clear_clip = clear_clip.on_press(Message::ClearClip);
+            }
+
+            let controls = Row::new()
+                .spacing(ROW_SPACING)
+                .align_items(Align::Center)
+                .push(
+                    PickList::new(&mut self.tools, ALL_TOOLS, Some(tool), Message::ToolPicked)
+                        .text_size(TEXT_SIZE),
+                )
+                .push(clear_clip);
+            (
+                controls.into(),
+                placement.shapes(tool, resolution),
+                tool.hint(),
+            )
+        };
+
+        let toggle_drawing = if self.is_drawing { "Edit tags" } else { "Draw" };
+        let toolbar = Row::new()
+            .spacing(ROW_SPACING)
+            .align_items(Align::Center)
+            .push(
+                Button::new(
+                    &mut self.toggle_drawing,
+                    Text::new(toggle_drawing).size(TEXT_SIZE),
+                )
+                .on_press(Message::ToggleDrawing),
+            )
+            .push(controls)
+            .push(
+                Button::new(&mut self.close, Text::new("Close").size(TEXT_SIZE))
+                    .on_press(Message::Close),
+            )
+            .push(Text::new(hint).size(TEXT_SIZE));
+
+        let preview: Element<Message> = match &self.frame {
+            Some(frame) => Handles::new(
+                &mut self.handles,
+                Image::new(frame.handle.clone())
+                    .width(Length::Units(frame.width as u16))
+                    .height(Length::Units(frame.height as u16)),
+                shapes,
+                Message::Drag,
+            )
+            .into(),
+            None => Text::new("The preview needs libass").size(TEXT_SIZE).into(),
+        };
+
+        Column::new()
+            .spacing(COLUMN_SPACING)
+            .width(Length::Fill)
+            .push(toolbar)
+            .push(preview)
+            .into()
+    }
+}